@@ -0,0 +1,47 @@
+#ifndef TYPST_MUTILATE_H
+#define TYPST_MUTILATE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define TYPST_MUTILATE_OK 0
+#define TYPST_MUTILATE_INVALID_ARGUMENT 1
+#define TYPST_MUTILATE_IO_ERROR 2
+#define TYPST_MUTILATE_SYNTAX_ERROR 3
+#define TYPST_MUTILATE_INTERNAL_ERROR 4
+
+/* Settings that control how a document is mutilated. */
+typedef struct {
+    /* A language tag, like "de" or "de-AT", or NULL for English. */
+    const char *language;
+    /* The path to a line-separated wordlist, or NULL. */
+    const char *wordlist;
+    /* Whether to replace elements that are more likely to change behavior, like strings. */
+    bool aggressive;
+    /* Whether `seed` should be used. */
+    bool has_seed;
+    /* A seed for the random number generator. */
+    uint64_t seed;
+} TypstMutilateOptions;
+
+/* An owned byte buffer that must be released with `typst_mutilate_free`. */
+typedef struct {
+    uint8_t *ptr;
+    size_t len;
+} TypstMutilateBuffer;
+
+/*
+ * Mutilate the UTF-8 typst document in `input`. `options` may be NULL.
+ *
+ * On success, `out` receives the mutilated document and TYPST_MUTILATE_OK is returned.
+ * Otherwise, `out` receives a UTF-8 error message and an error code is returned. In both
+ * cases, `out` must be released with `typst_mutilate_free`.
+ */
+int32_t typst_mutilate(const uint8_t *input, size_t len, const TypstMutilateOptions *options,
+                       TypstMutilateBuffer *out);
+
+/* Release a buffer returned by `typst_mutilate`. */
+void typst_mutilate_free(TypstMutilateBuffer buffer);
+
+#endif
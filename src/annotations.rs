@@ -0,0 +1,109 @@
+//! Annotations of the problems that `check` and `verify` find, for continuous integration.
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+use typst_mutilate::json_string;
+
+/// How to write annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationFormat {
+    /// GitHub workflow commands like `::warning file=..,line=..::..`, which GitHub Actions
+    /// show inline on pull requests.
+    Github,
+    /// One JSON object per line.
+    Json,
+}
+
+/// A problem at a position of a file.
+///
+/// The message never contains the text of the document, so that annotations don't reveal what
+/// the document is about.
+pub struct Annotation<'a> {
+    pub file: &'a str,
+    /// The line of the problem, counted from 1.
+    pub line: usize,
+    /// The column of the problem in characters, counted from 1.
+    pub column: usize,
+    /// The kind of the problem, like `leak`, which GitHub shows as the title.
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl<'a> Annotation<'a> {
+    /// An annotation at a byte offset of `code`, which was read from `file`.
+    pub fn at(
+        file: &'a str,
+        code: &str,
+        offset: usize,
+        kind: &'static str,
+        message: impl Into<String>,
+    ) -> Self {
+        let before = &code[..offset.min(code.len())];
+        Self {
+            file,
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap_or("").chars().count() + 1,
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl AnnotationFormat {
+    /// Write an annotation as one line.
+    pub fn write(self, out: &mut impl Write, annotation: &Annotation) -> io::Result<()> {
+        let Annotation {
+            file,
+            line,
+            column,
+            kind,
+            message,
+        } = annotation;
+        match self {
+            Self::Github => writeln!(
+                out,
+                "::warning file={},line={line},col={column},title={}::{}",
+                escape_property(file),
+                escape_property(kind),
+                escape_data(message),
+            ),
+            Self::Json => writeln!(
+                out,
+                "{{\"level\":\"warning\",\"file\":{},\"line\":{line},\"column\":{column},\
+                 \"kind\":{},\"message\":{}}}",
+                json_string(file),
+                json_string(kind),
+                json_string(message),
+            ),
+        }
+    }
+}
+
+impl FromStr for AnnotationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(Self::Github),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unknown annotation format `{s}`, expected one of `github`, `json`"
+            )),
+        }
+    }
+}
+
+/// Escape the message of a workflow command.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property of a workflow command, which also ends at `,` and `::`.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
@@ -0,0 +1,175 @@
+use std::{
+    io::{self, Write},
+    ops::Range,
+};
+
+use sha2::{Digest, Sha256};
+use typst_syntax::SyntaxKind;
+
+use crate::{cache::hex, kind_name};
+
+/// How a replaced range was chosen, for audit logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// The replaced byte range, like the range of the corresponding [`Edit`](crate::Edit).
+    pub range: Range<usize>,
+    /// The kind of the syntax node containing the range, or `None` outside of typst.
+    pub kind: Option<SyntaxKind>,
+    /// The strategy that chose the replacement: the name of a [`Strategy`](crate::Strategy),
+    /// `custom` for custom replacers, `name` for repeated names, `identifier` for DOIs and the
+    /// like, `pii` for scrubbed identifiers, `date` for shifted dates, `regenerate` for
+    /// generated paragraphs, `script` for the letters of words that mix scripts, `mixed` for
+    /// words of letters and digits, `filler` for the identifiers of raw text, `rename` for the
+    /// renamed bindings of a package and `manual` for replacements typed during an interactive
+    /// review.
+    pub strategy: &'static str,
+}
+
+/// Writes audit records as JSON lines, with salted hashes instead of the replaced text.
+pub struct AuditLog<W> {
+    out: W,
+    salt: Vec<u8>,
+}
+
+impl<W: Write> AuditLog<W> {
+    /// Write records to `out`, hashing the replaced text together with `salt`.
+    pub fn new(out: W, salt: impl Into<Vec<u8>>) -> Self {
+        Self {
+            out,
+            salt: salt.into(),
+        }
+    }
+
+    /// Write the records of the replacements made in `code`, which was read from `file`.
+    pub fn write(&mut self, file: &str, code: &str, records: &[AuditRecord]) -> io::Result<()> {
+        let mut line = 1;
+        let mut line_start = 0;
+        let mut cursor = 0;
+        for record in records {
+            for (i, _) in code[cursor..record.range.start].match_indices('\n') {
+                line += 1;
+                line_start = cursor + i + 1;
+            }
+            cursor = record.range.start;
+
+            let column = code[line_start..record.range.start].chars().count() + 1;
+            let kind = record.kind.map_or_else(|| "text".into(), kind_name);
+            let hash = salted_hash(&self.salt, &code[record.range.clone()]);
+            writeln!(
+                self.out,
+                "{{\"file\":{},\"start\":{},\"end\":{},\"line\":{line},\"column\":{column},\
+                 \"kind\":{},\"strategy\":{},\"hash\":\"{hash}\"}}",
+                json_string(file),
+                record.range.start,
+                record.range.end,
+                json_string(&kind),
+                json_string(record.strategy),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// An entry read back from an audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The file the replaced text was read from.
+    pub file: String,
+    /// The replaced byte range.
+    pub range: Range<usize>,
+    /// The salted hash of the replaced text.
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Parse a line written by an [`AuditLog`], or `None` if it isn't one.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (mut file, mut start, mut end, mut hash) = (None, None, None, None);
+        let mut rest = line.trim().strip_prefix('{')?.strip_suffix('}')?;
+        while !rest.is_empty() {
+            let (key, after) = json_value(rest)?;
+            let after = after.trim_start().strip_prefix(':')?;
+            let (value, after) = json_value(after)?;
+            match key.as_str() {
+                "file" => file = Some(value),
+                "start" => start = value.parse().ok(),
+                "end" => end = value.parse().ok(),
+                "hash" => hash = Some(value),
+                _ => {}
+            }
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+        Some(Self {
+            file: file?,
+            range: start?..end?,
+            hash: hash?,
+        })
+    }
+
+    /// Whether the entry matches the text at its range in `code`, hashed with `salt`.
+    pub fn matches(&self, code: &str, salt: &[u8]) -> bool {
+        code.get(self.range.clone())
+            .is_some_and(|text| salted_hash(salt, text) == self.hash)
+    }
+}
+
+/// The hex-encoded SHA-256 hash of the salt followed by the text.
+fn salted_hash(salt: &[u8], text: &str) -> String {
+    hex(&Sha256::new()
+        .chain_update(salt)
+        .chain_update(text)
+        .finalize())
+}
+
+/// Read a JSON string or number at the start of `s`, returning it and the rest.
+fn json_value(s: &str) -> Option<(String, &str)> {
+    let s = s.trim_start();
+    let Some(quoted) = s.strip_prefix('"') else {
+        let end = s.find([',', '}', ' ']).unwrap_or(s.len());
+        return Some((s[..end].into(), &s[end..]));
+    };
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &quoted[i + 1..])),
+            '\\' => value.push(match chars.next()?.1 {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                }
+                c => c,
+            }),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Quote and escape a string for JSON.
+pub fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
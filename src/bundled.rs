@@ -0,0 +1,38 @@
+use hypher::Lang;
+
+/// The wordlist shipped with this crate for a language, if its `bundled-*` feature is enabled.
+#[cfg(any(
+    feature = "bundled-en",
+    feature = "bundled-de",
+    feature = "bundled-fr",
+    feature = "bundled-es",
+    feature = "bundled-ru",
+))]
+pub fn bundled_wordlist(language: Lang) -> Option<&'static [&'static str]> {
+    let language = match language {
+        #[cfg(feature = "bundled-en")]
+        Lang::English => random_word::Lang::En,
+        #[cfg(feature = "bundled-de")]
+        Lang::German => random_word::Lang::De,
+        #[cfg(feature = "bundled-fr")]
+        Lang::French => random_word::Lang::Fr,
+        #[cfg(feature = "bundled-es")]
+        Lang::Spanish => random_word::Lang::Es,
+        #[cfg(feature = "bundled-ru")]
+        Lang::Russian => random_word::Lang::Ru,
+        _ => return None,
+    };
+    Some(random_word::all(language))
+}
+
+/// The wordlist shipped with this crate for a language, if its `bundled-*` feature is enabled.
+#[cfg(not(any(
+    feature = "bundled-en",
+    feature = "bundled-de",
+    feature = "bundled-fr",
+    feature = "bundled-es",
+    feature = "bundled-ru",
+)))]
+pub fn bundled_wordlist(_: Lang) -> Option<&'static [&'static str]> {
+    None
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use ecow::EcoVec;
+use hypher::Lang;
+use memmap2::Mmap;
+use sha2::{Digest, Sha256};
+
+use crate::{replacer::hyphenation, wordlist::Text, Patterns};
+
+/// The directory in which typst-mutilate caches downloaded wordlists and hyphenations.
+///
+/// This is `$XDG_CACHE_HOME/typst-mutilate` or the platform's equivalent.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let base = if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir)
+    } else if cfg!(windows) {
+        PathBuf::from(env::var_os("LOCALAPPDATA")?)
+    } else {
+        let home = PathBuf::from(env::var_os("HOME")?);
+        if cfg!(target_os = "macos") {
+            home.join("Library").join("Caches")
+        } else {
+            home.join(".cache")
+        }
+    };
+    Some(base.join("typst-mutilate"))
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Identifies the format of hyphenation cache files.
+const MAGIC: &[u8; 8] = b"TMHYPH01";
+
+/// The hyphenation patterns of the words of a wordlist, stored in a file.
+///
+/// The patterns are grouped by the length of the words and are in the order of the words in
+/// the wordlist. The file starts with a table of the groups, each being the length, the number
+/// of words and the offset of its patterns, followed by the patterns, each prefixed with the
+/// number of syllables.
+pub(crate) struct HyphenationCache {
+    data: Text,
+    /// The number of words and the offset of the patterns by the length of the words.
+    groups: HashMap<usize, (usize, usize)>,
+}
+
+impl HyphenationCache {
+    /// The file in which the patterns of a wordlist are cached.
+    ///
+    /// The name depends on the contents of the wordlist, the language, custom patterns, the
+    /// version of this crate and the hyphenation of a sample word, so that a change to any of
+    /// them invalidates the cache.
+    pub(crate) fn path(
+        dir: &Path,
+        text: &[u8],
+        language: Lang,
+        patterns: Option<&Patterns>,
+    ) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(format!("{language:?}"));
+        if let Some(patterns) = patterns {
+            hasher.update(patterns.digest);
+        }
+        hasher.update(hyphenation("characterization", language, patterns));
+        hasher.update(text);
+        dir.join(format!("{}.bin", hex(&hasher.finalize())))
+    }
+
+    /// Open a cache file, if it exists and has the expected number of words of each length.
+    pub(crate) fn open(path: &Path, counts: &HashMap<usize, usize>) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        // SAFETY: Cache files are only replaced atomically, never modified in place.
+        let data = Text::Mapped(unsafe { Mmap::map(&file) }.ok()?);
+        if data.get(..MAGIC.len())? != MAGIC {
+            return None;
+        }
+
+        let mut cursor = MAGIC.len();
+        let group_count = read_u64(&data, &mut cursor)?;
+        let mut groups = HashMap::new();
+        for _ in 0..group_count {
+            let length = read_u64(&data, &mut cursor)?;
+            let count = read_u64(&data, &mut cursor)?;
+            let offset = read_u64(&data, &mut cursor)?;
+            groups.insert(length, (count, offset));
+        }
+
+        let matches = groups.len() == counts.len()
+            && counts
+                .iter()
+                .all(|(length, &count)| groups.get(length).is_some_and(|group| group.0 == count));
+        matches.then_some(Self { data, groups })
+    }
+
+    /// Write the patterns of the words of each length to a cache file.
+    pub(crate) fn write(path: &Path, patterns: &HashMap<usize, Vec<EcoVec<u8>>>) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&temporary)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&(patterns.len() as u64).to_le_bytes())?;
+        let mut offset = MAGIC.len() + 8 + patterns.len() * 24;
+        for (&length, group) in patterns {
+            for value in [length, group.len(), offset] {
+                out.write_all(&(value as u64).to_le_bytes())?;
+            }
+            offset += group.iter().map(|pattern| 1 + pattern.len()).sum::<usize>();
+        }
+        for group in patterns.values() {
+            for pattern in group {
+                out.write_all(&[u8::try_from(pattern.len()).unwrap_or(u8::MAX)])?;
+                out.write_all(&pattern[..pattern.len().min(u8::MAX.into())])?;
+            }
+        }
+        out.into_inner().map_err(io::IntoInnerError::into_error)?;
+        std::fs::rename(&temporary, path)
+    }
+
+    /// The position of the first pattern of the words with the given length.
+    pub(crate) fn start(&self, length: usize) -> Option<usize> {
+        Some(self.groups.get(&length)?.1)
+    }
+
+    /// Read the pattern at `cursor` and move the cursor to the next one.
+    pub(crate) fn read(&self, cursor: &mut usize) -> Option<&[u8]> {
+        let len = usize::from(*self.data.get(*cursor)?);
+        let pattern = self.data.get(*cursor + 1..*cursor + 1 + len)?;
+        *cursor += 1 + len;
+        Some(pattern)
+    }
+}
+
+fn read_u64(data: &[u8], cursor: &mut usize) -> Option<usize> {
+    let bytes = data.get(*cursor..*cursor + 8)?;
+    *cursor += 8;
+    usize::try_from(u64::from_le_bytes(bytes.try_into().ok()?)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "typst-mutilate-cache-test-{}.bin",
+            std::process::id()
+        ));
+        let patterns = HashMap::from([
+            (3, vec![EcoVec::from([1, 2]), EcoVec::from([3])]),
+            (5, vec![EcoVec::from([2, 3])]),
+        ]);
+        HyphenationCache::write(&path, &patterns).unwrap();
+
+        let cache = HyphenationCache::open(&path, &HashMap::from([(3, 2), (5, 1)])).unwrap();
+        let mut cursor = cache.start(3).unwrap();
+        assert_eq!(cache.read(&mut cursor), Some(&[1, 2][..]));
+        assert_eq!(cache.read(&mut cursor), Some(&[3][..]));
+        let mut cursor = cache.start(5).unwrap();
+        assert_eq!(cache.read(&mut cursor), Some(&[2, 3][..]));
+        assert_eq!(cache.start(4), None);
+        drop(cache);
+
+        // A wordlist with other words of a length doesn't use the cache.
+        assert!(HyphenationCache::open(&path, &HashMap::from([(3, 1), (5, 1)])).is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
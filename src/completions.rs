@@ -0,0 +1,389 @@
+//! Completion scripts for shells, generated from the arguments of the command line interface.
+
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use argh::{CommandInfoWithArgs, FlagInfoKind};
+use typst_mutilate::{Encoding, Strategy};
+
+/// A shell to generate completions for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    const NAMES: &'static [&'static str] = &["bash", "zsh", "fish", "powershell"];
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            "powershell" => Ok(Self::Powershell),
+            _ => Err(format!(
+                "unknown shell `{s}`, expected one of `bash`, `zsh`, `fish`, `powershell`"
+            )),
+        }
+    }
+}
+
+/// A command with its flags, like `typst-mutilate wordlist build`.
+struct Command {
+    /// The names of the command and its parents, separated by spaces.
+    path: String,
+    flags: Vec<Flag>,
+    /// The names and descriptions of the subcommands.
+    subcommands: Vec<(&'static str, &'static str)>,
+    /// The values of the positional arguments, or `None` if they are files or there are none.
+    positionals: Option<Vec<&'static str>>,
+    /// Whether the positional arguments are files.
+    files: bool,
+}
+
+struct Flag {
+    long: &'static str,
+    short: Option<char>,
+    /// Whether the flag is an option with a value.
+    value: bool,
+    description: String,
+}
+
+impl Flag {
+    /// The long and short spellings of the flag.
+    fn spellings(&self) -> impl Iterator<Item = String> + '_ {
+        std::iter::once(self.long.to_string()).chain(self.short.map(|short| format!("-{short}")))
+    }
+}
+
+/// The values an option accepts, or `None` if they are files or arbitrary.
+fn option_values(long: &str) -> Option<Vec<&'static str>> {
+    Some(match long {
+        "--language" => typst_mutilate::supported_languages()
+            .map(|(code, _)| code)
+            .collect(),
+        "--strategy" => Strategy::ALL
+            .iter()
+            .map(|strategy| strategy.name())
+            .collect(),
+        "--format" => vec!["typst", "plain", "markdown", "latex"],
+        "--mode" => vec!["markup", "code", "math"],
+        "--numbers" => vec!["digits", "magnitude"],
+        "--segmentation" => vec!["unicode", "alphanumeric"],
+        "--normalize-output" => vec!["preserve", "nfc", "nfd"],
+        "--shuffle" => vec!["paragraphs", "sections", "sentences"],
+        "--quotes" => vec!["keep", "only"],
+        "--raw" => vec!["words", "filler"],
+        "--newline" => vec!["lf", "crlf", "native"],
+        "--encoding" => Encoding::ALL
+            .iter()
+            .map(|encoding| encoding.name())
+            .collect(),
+        "--color" => vec!["auto", "always", "never"],
+        "--review-by" => vec!["word", "hunk"],
+        "--annotations" => vec!["github", "json"],
+        _ => return None,
+    })
+}
+
+/// Collect the command and its subcommands, parents first.
+fn collect(path: String, info: &CommandInfoWithArgs, commands: &mut Vec<Command>) {
+    let flags = info
+        .flags
+        .iter()
+        .filter(|flag| !flag.hidden)
+        .map(|flag| Flag {
+            long: flag.long,
+            short: flag.short,
+            value: matches!(flag.kind, FlagInfoKind::Option { .. }),
+            // Descriptions are format strings for the help of argh, with braces doubled.
+            description: flag
+                .description
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace("{{", "{")
+                .replace("}}", "}"),
+        })
+        .collect();
+    let positionals = (path == "typst-mutilate completions").then(|| Shell::NAMES.to_vec());
+    let files = positionals.is_none() && !info.positionals.is_empty();
+    commands.push(Command {
+        path: path.clone(),
+        flags,
+        subcommands: info
+            .commands
+            .iter()
+            .map(|sub| (sub.name, sub.command.description))
+            .collect(),
+        positionals,
+        files,
+    });
+    for sub in &info.commands {
+        collect(format!("{path} {}", sub.name), &sub.command, commands);
+    }
+}
+
+/// Write the completion script of `info`, the arguments of `typst-mutilate`.
+pub fn write(out: &mut impl Write, shell: Shell, info: &CommandInfoWithArgs) -> io::Result<()> {
+    let mut commands = Vec::new();
+    collect("typst-mutilate".into(), info, &mut commands);
+    let script = match shell {
+        Shell::Bash => bash(&commands),
+        Shell::Zsh => zsh(&commands),
+        Shell::Fish => fish(&commands),
+        Shell::Powershell => powershell(&commands),
+    };
+    out.write_all(script.as_bytes())
+}
+
+/// The paths of the subcommands, like `typst-mutilate wordlist build`.
+fn subcommand_paths(commands: &[Command]) -> impl Iterator<Item = &str> {
+    commands[1..].iter().map(|command| command.path.as_str())
+}
+
+/// The options with values that are or aren't files, and their spellings.
+fn value_options(commands: &[Command]) -> (Vec<(String, Vec<&'static str>)>, Vec<String>) {
+    let mut listed = Vec::new();
+    let mut files = Vec::new();
+    for flag in commands.iter().flat_map(|command| &command.flags) {
+        if !flag.value {
+            continue;
+        }
+        for spelling in flag.spellings() {
+            match option_values(flag.long) {
+                Some(values) if !listed.iter().any(|(s, _)| *s == spelling) => {
+                    listed.push((spelling, values))
+                }
+                None if !files.contains(&spelling) => files.push(spelling),
+                _ => {}
+            }
+        }
+    }
+    (listed, files)
+}
+
+/// The words completed for a command: its subcommands, flags and positional values.
+fn words(command: &Command) -> Vec<String> {
+    let mut words: Vec<String> = command
+        .subcommands
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    words.extend(command.flags.iter().flat_map(Flag::spellings));
+    words.extend(
+        command
+            .positionals
+            .iter()
+            .flatten()
+            .map(|value| value.to_string()),
+    );
+    words
+}
+
+fn bash(commands: &[Command]) -> String {
+    let (listed, files) = value_options(commands);
+    let mut s = String::new();
+    s.push_str("_typst_mutilate() {\n");
+    s.push_str(
+        "    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n",
+    );
+    s.push_str("    local cmd=\"typst-mutilate\" word words\n");
+    s.push_str("    for word in \"${COMP_WORDS[@]:1:COMP_CWORD-1}\"; do\n");
+    s.push_str("        case \"$cmd $word\" in\n");
+    let paths: Vec<String> = subcommand_paths(commands)
+        .map(|path| format!("\"{path}\""))
+        .collect();
+    let _ = writeln!(s, "            {}) cmd=\"$cmd $word\" ;;", paths.join("|"));
+    s.push_str("        esac\n    done\n\n");
+    s.push_str("    case \"$prev\" in\n");
+    for (spelling, values) in &listed {
+        let _ = writeln!(
+            s,
+            "        {spelling}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;",
+            values.join(" ")
+        );
+    }
+    let _ = writeln!(
+        s,
+        "        {}) COMPREPLY=($(compgen -f -- \"$cur\")); return ;;",
+        files.join("|")
+    );
+    s.push_str("    esac\n\n    case \"$cmd\" in\n");
+    for command in commands {
+        let _ = writeln!(
+            s,
+            "        \"{}\") words=\"{}\" ;;",
+            command.path,
+            words(command).join(" ")
+        );
+    }
+    s.push_str("    esac\n");
+    s.push_str("    COMPREPLY=($(compgen -W \"$words\" -- \"$cur\"))\n");
+    let with_files: Vec<String> = commands
+        .iter()
+        .filter(|command| command.files)
+        .map(|command| format!("\"{}\"", command.path))
+        .collect();
+    s.push_str("    case \"$cmd\" in\n");
+    let _ = writeln!(
+        s,
+        "        {}) [[ \"$cur\" != -* ]] && COMPREPLY+=($(compgen -f -- \"$cur\")) ;;",
+        with_files.join("|")
+    );
+    s.push_str("    esac\n}\n\n");
+    s.push_str("complete -o filenames -F _typst_mutilate typst-mutilate\n");
+    s
+}
+
+fn zsh(commands: &[Command]) -> String {
+    let (listed, files) = value_options(commands);
+    let mut s = String::new();
+    s.push_str("#compdef typst-mutilate\n\n_typst_mutilate() {\n");
+    s.push_str("    local cmd=\"typst-mutilate\" word\n");
+    s.push_str("    for word in \"${(@)words[2,CURRENT-1]}\"; do\n");
+    s.push_str("        case \"$cmd $word\" in\n");
+    let paths: Vec<String> = subcommand_paths(commands)
+        .map(|path| format!("\"{path}\""))
+        .collect();
+    let _ = writeln!(s, "            ({}) cmd=\"$cmd $word\" ;;", paths.join("|"));
+    s.push_str("        esac\n    done\n\n");
+    s.push_str("    case \"${words[CURRENT-1]}\" in\n");
+    for (spelling, values) in &listed {
+        let _ = writeln!(
+            s,
+            "        ({spelling}) compadd -- {}; return ;;",
+            values.join(" ")
+        );
+    }
+    let _ = writeln!(s, "        ({}) _files; return ;;", files.join("|"));
+    s.push_str("    esac\n\n    case \"$cmd\" in\n");
+    for command in commands {
+        let _ = write!(s, "        (\"{}\")", command.path);
+        let words = words(command);
+        if !words.is_empty() {
+            let _ = write!(s, " compadd -- {};", words.join(" "));
+        }
+        if command.files {
+            s.push_str(" _files;");
+        }
+        s.push_str(" ;;\n");
+    }
+    s.push_str("    esac\n}\n\n_typst_mutilate \"$@\"\n");
+    s
+}
+
+fn fish(commands: &[Command]) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut s = String::new();
+    for command in commands {
+        // The command is being completed if its name and none of its subcommands were seen.
+        let mut condition: Vec<String> = command
+            .path
+            .split(' ')
+            .skip(1)
+            .map(|name| format!("__fish_seen_subcommand_from {name}"))
+            .collect();
+        if !command.subcommands.is_empty() {
+            let names: Vec<&str> = command.subcommands.iter().map(|(name, _)| *name).collect();
+            condition.push(format!(
+                "not __fish_seen_subcommand_from {}",
+                names.join(" ")
+            ));
+        }
+        let condition = match condition.is_empty() {
+            true => String::new(),
+            false => format!(" -n {}", quote(&condition.join("; and "))),
+        };
+
+        if !command.files {
+            let _ = writeln!(s, "complete -c typst-mutilate{condition} -f");
+        }
+        for (name, description) in &command.subcommands {
+            let _ = writeln!(
+                s,
+                "complete -c typst-mutilate{condition} -a {name} -d {}",
+                quote(description)
+            );
+        }
+        for value in command.positionals.iter().flatten() {
+            let _ = writeln!(s, "complete -c typst-mutilate{condition} -a {value}");
+        }
+        for flag in &command.flags {
+            let _ = write!(
+                s,
+                "complete -c typst-mutilate{condition} -l {}",
+                flag.long.trim_start_matches('-')
+            );
+            if let Some(short) = flag.short {
+                let _ = write!(s, " -s {short}");
+            }
+            if flag.value {
+                match option_values(flag.long) {
+                    Some(values) => {
+                        let _ = write!(s, " -x -a {}", quote(&values.join(" ")));
+                    }
+                    None => s.push_str(" -r -F"),
+                }
+            }
+            let _ = writeln!(s, " -d {}", quote(&flag.description));
+        }
+    }
+    s
+}
+
+fn powershell(commands: &[Command]) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    let list = |words: &[String]| {
+        let quoted: Vec<String> = words.iter().map(|word| quote(word)).collect();
+        format!("@({})", quoted.join(", "))
+    };
+    let (listed, files) = value_options(commands);
+    let mut s = String::new();
+    s.push_str("Register-ArgumentCompleter -Native -CommandName 'typst-mutilate' -ScriptBlock {\n");
+    s.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    s.push_str("    $words = @($commandAst.CommandElements |\n");
+    s.push_str("        Where-Object { $_.Extent.EndOffset -lt $cursorPosition } |\n");
+    s.push_str("        ForEach-Object { $_.ToString() })\n");
+    let paths: Vec<String> = subcommand_paths(commands).map(String::from).collect();
+    let _ = writeln!(s, "    $commands = {}", list(&paths));
+    s.push_str("    $cmd = 'typst-mutilate'\n");
+    s.push_str("    foreach ($word in ($words | Select-Object -Skip 1)) {\n");
+    s.push_str("        if ($commands -ccontains \"$cmd $word\") { $cmd = \"$cmd $word\" }\n");
+    s.push_str("    }\n\n");
+    s.push_str("    $values = @{\n");
+    for (spelling, values) in &listed {
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        let _ = writeln!(s, "        {} = {}", quote(spelling), list(&values));
+    }
+    s.push_str("    }\n    $words_of = @{\n");
+    for command in commands {
+        let _ = writeln!(
+            s,
+            "        {} = {}",
+            quote(&command.path),
+            list(&words(command))
+        );
+    }
+    s.push_str("    }\n");
+    let _ = writeln!(s, "    $files = {}", list(&files));
+    s.push_str("    $prev = if ($words.Count -gt 1) { $words[-1] } else { '' }\n");
+    s.push_str("    if ($files -ccontains $prev) { return }\n");
+    s.push_str("    $candidates = if ($values.ContainsKey($prev)) { $values[$prev] } else { $words_of[$cmd] }\n");
+    s.push_str(
+        "    $candidates | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n",
+    );
+    s.push_str("        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n");
+    s.push_str("    }\n}\n");
+    s
+}
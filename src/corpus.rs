@@ -0,0 +1,62 @@
+use std::{collections::HashMap, io};
+
+use ecow::EcoString;
+
+use crate::{plan_str_with, Context, Format, Mode, MutilateOptions, Replacer, Result, WordInfo};
+
+/// Counts the words of a corpus of documents, to build a wordlist from them.
+///
+/// Only the words that would be mutilated are counted, so markup, code and protected words
+/// don't end up in the wordlist.
+pub struct WordCounter {
+    context: Context,
+    counts: HashMap<EcoString, u64>,
+}
+
+impl WordCounter {
+    /// Create a counter that finds words like a context built from `options` would.
+    pub fn new(options: &MutilateOptions) -> Self {
+        Self {
+            context: Context::with_replacer(options, Discard),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// The context used for finding words.
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /// Count the words in a document.
+    pub fn add(&mut self, code: &str, format: Format, mode: Mode) -> Result<()> {
+        self.context.format = format;
+        self.context.mode = mode;
+        for edit in plan_str_with(code, &mut self.context)? {
+            let word = &code[edit.range];
+            if !word.chars().all(char::is_numeric) {
+                *self.counts.entry(word.into()).or_default() += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every word that was found with its number of occurrences, most frequent first.
+    pub fn words(&self) -> Vec<(EcoString, u64)> {
+        let mut words: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(word, &count)| (word.clone(), count))
+            .collect();
+        words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        words
+    }
+}
+
+/// A replacer that doesn't produce anything, as only the positions of the words are needed.
+pub(crate) struct Discard;
+
+impl Replacer for Discard {
+    fn replace(&mut self, _: &str, _: &WordInfo, _: &mut dyn io::Write) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+use std::ops::Range;
+
+use ecow::{eco_format, EcoString};
+use hypher::Lang;
+use typst_syntax::{LinkedNode, SyntaxKind};
+
+/// The names of the months in the languages whose written dates are recognized, in full and
+/// abbreviated, if the language abbreviates them.
+const MONTHS: &[[&str; 12]] = &[
+    [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+];
+
+/// The range of random offsets in days, if no offset is given.
+pub(crate) const RANDOM_OFFSETS: Range<i64> = -365..366;
+
+/// A calendar date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Date {
+    year: i64,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    fn new(year: i64, month: u32, day: u32) -> Option<Self> {
+        let valid = (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day);
+        valid.then_some(Self { year, month, day })
+    }
+
+    /// The date `days` days later.
+    fn shift(self, days: i64) -> Self {
+        from_days(to_days(self) + days)
+    }
+}
+
+/// Moves the dates of a document by the same number of days, so that the intervals between
+/// them stay the same.
+pub(crate) struct DateShifter {
+    days: i64,
+    /// Whether numeric dates with slashes put the month first, like `1/31/2024`.
+    month_first: bool,
+}
+
+impl DateShifter {
+    pub(crate) fn new(days: i64, language: Lang) -> Self {
+        Self {
+            days,
+            month_first: language == Lang::English,
+        }
+    }
+
+    /// The number of days dates are moved by.
+    pub(crate) fn days(&self) -> i64 {
+        self.days
+    }
+
+    /// Find the dates in `text` and choose their shifted replacements.
+    ///
+    /// The ranges are sorted and disjoint.
+    pub(crate) fn shift(&self, text: &str) -> Vec<(Range<usize>, EcoString)> {
+        let mut found = Vec::new();
+        let mut i = 0;
+        while i < text.len() {
+            let c = text[i..].chars().next().unwrap();
+            let boundary = !text[..i]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric);
+            if boundary && c.is_alphanumeric() {
+                if let Some((len, replacement)) = self.date_at(&text[i..]) {
+                    let boundary = !text[i + len..].starts_with(char::is_alphanumeric);
+                    if boundary {
+                        found.push((i..i + len, replacement));
+                        i += len;
+                        continue;
+                    }
+                }
+            }
+            i += c.len_utf8();
+        }
+        found
+    }
+
+    /// Match a date at the start of `text`, returning its length and shifted replacement.
+    fn date_at(&self, text: &str) -> Option<(usize, EcoString)> {
+        self.numeric_date(text)
+            .or_else(|| self.day_month_year(text))
+            .or_else(|| self.month_day_year(text))
+    }
+
+    /// Match `2024-01-31`, `31.01.2024` or `01/31/2024`, keeping the separators and padding.
+    fn numeric_date(&self, text: &str) -> Option<(usize, EcoString)> {
+        let (first, rest) = digits(text)?;
+        let separator = rest
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '-' | '.' | '/'))?;
+        let (second, rest) = digits(&rest[1..])?;
+        let (third, rest) = digits(rest.strip_prefix(separator)?)?;
+        let len = text.len() - rest.len();
+
+        let fields = [first, second, third];
+        let (year, month, day) = match fields.map(str::len) {
+            [4, 1 | 2, 1 | 2] => (0, 1, 2),
+            [1 | 2, 1 | 2, 4] if separator == '/' && self.month_first => (2, 0, 1),
+            [1 | 2, 1 | 2, 4] => (2, 1, 0),
+            _ => return None,
+        };
+        let date = Date::new(
+            fields[year].parse().ok()?,
+            fields[month].parse().ok()?,
+            fields[day].parse().ok()?,
+        )?
+        .shift(self.days);
+
+        let mut shifted = [EcoString::new(), EcoString::new(), EcoString::new()];
+        shifted[year] = eco_format!("{:04}", date.year);
+        // Dates with two digit months and days, like `2024-12-31`, keep them when the shifted
+        // month or day has one digit.
+        let padded = (fields[month].len() == 2 && fields[day].len() == 2)
+            || fields[month].starts_with('0')
+            || fields[day].starts_with('0');
+        let width = |field| if padded { "00" } else { fields[field] };
+        shifted[month] = pad(date.month, width(month));
+        shifted[day] = pad(date.day, width(day));
+        let [a, b, c] = shifted;
+        Some((len, eco_format!("{a}{separator}{b}{separator}{c}")))
+    }
+
+    /// Match `31 January 2024`, `31. Januar 2024`, `31 Jan. 2024` or `31 de enero de 2024`.
+    fn day_month_year(&self, text: &str) -> Option<(usize, EcoString)> {
+        let (day, rest) = digits(text)?;
+        let dot = if rest.starts_with('.') { "." } else { "" };
+        let rest = rest[dot.len()..].strip_prefix(' ')?;
+        let connector = if rest.starts_with("de ") { "de " } else { "" };
+        let (month, style, rest) = month_name(&rest[connector.len()..])?;
+        let month_dot = if rest.starts_with('.') { "." } else { "" };
+        let rest = rest[month_dot.len()..].strip_prefix(' ')?;
+        let rest = rest.strip_prefix(connector)?;
+        let (year, rest) = digits(rest).filter(|(year, _)| year.len() == 4)?;
+        let len = text.len() - rest.len();
+
+        let date = Date::new(year.parse().ok()?, month, day.parse().ok()?)?.shift(self.days);
+        let day = pad(date.day, day);
+        let month = style.name(date.month);
+        let year = date.year;
+        Some((
+            len,
+            eco_format!("{day}{dot} {connector}{month}{month_dot} {connector}{year}"),
+        ))
+    }
+
+    /// Match `January 31, 2024` or `Jan. 31st, 2024`.
+    fn month_day_year(&self, text: &str) -> Option<(usize, EcoString)> {
+        let (month, style, rest) = month_name(text)?;
+        let dot = if rest.starts_with('.') { "." } else { "" };
+        let (day, rest) = digits(rest[dot.len()..].strip_prefix(' ')?)?;
+        let ordinal = ["st", "nd", "rd", "th"]
+            .into_iter()
+            .find(|suffix| rest.starts_with(suffix))
+            .is_some();
+        let rest = if ordinal { &rest[2..] } else { rest };
+        let rest = rest.strip_prefix(", ")?;
+        let (year, rest) = digits(rest).filter(|(year, _)| year.len() == 4)?;
+        let len = text.len() - rest.len();
+
+        let date = Date::new(year.parse().ok()?, month, day.parse().ok()?)?.shift(self.days);
+        let month = style.name(date.month);
+        let mut day = pad(date.day, day);
+        if ordinal {
+            day.push_str(ordinal_suffix(date.day));
+        }
+        Some((len, eco_format!("{month}{dot} {day}, {}", date.year)))
+    }
+}
+
+/// How a month name is written, to write the shifted month the same way.
+#[derive(Debug, Clone, Copy)]
+struct MonthStyle {
+    table: usize,
+    uppercase: bool,
+}
+
+impl MonthStyle {
+    fn name(self, month: u32) -> EcoString {
+        let name = MONTHS[self.table][month as usize - 1];
+        if self.uppercase {
+            name.to_uppercase().into()
+        } else {
+            name.into()
+        }
+    }
+}
+
+/// Match a month name at the start of `text`, returning the month, its style and the rest.
+fn month_name(text: &str) -> Option<(u32, MonthStyle, &str)> {
+    let len = text
+        .char_indices()
+        .find(|(_, c)| !c.is_alphabetic())
+        .map_or(text.len(), |(i, _)| i);
+    let word = &text[..len];
+    if !(3..=10).contains(&word.chars().count()) {
+        return None;
+    }
+    for (table, names) in MONTHS.iter().enumerate() {
+        for (month, name) in names.iter().enumerate() {
+            let uppercase = word.chars().eq(name.chars().flat_map(char::to_uppercase));
+            if word == *name || uppercase {
+                let style = MonthStyle { table, uppercase };
+                return Some((month as u32 + 1, style, &text[len..]));
+            }
+        }
+    }
+    None
+}
+
+/// Split a run of one to four ASCII digits off the start of `text`.
+fn digits(text: &str) -> Option<(&str, &str)> {
+    let len = text.bytes().take_while(u8::is_ascii_digit).count();
+    (1..=4).contains(&len).then(|| text.split_at(len))
+}
+
+/// Format a number with as many digits as `original` if it was padded with zeros.
+fn pad(value: u32, original: &str) -> EcoString {
+    let width = if original.starts_with('0') {
+        original.len()
+    } else {
+        1
+    };
+    eco_format!("{value:0width$}")
+}
+
+/// The English ordinal suffix of a day, like `st` for 1.
+fn ordinal_suffix(day: u32) -> &'static str {
+    match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days since 1970-01-01.
+fn to_days(date: Date) -> i64 {
+    let year = if date.month <= 2 {
+        date.year - 1
+    } else {
+        date.year
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(date.month);
+    let day_of_year =
+        (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(date.day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date a number of days after 1970-01-01.
+fn from_days(days: i64) -> Date {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    Date { year, month, day }
+}
+
+/// Find the `datetime(year: .., month: .., day: ..)` calls of a typst document and compute
+/// the edits of their arguments.
+pub(crate) fn shift_datetime_calls(
+    node: &LinkedNode,
+    shifter: &DateShifter,
+    shifted: &mut Vec<(Range<usize>, EcoString)>,
+) {
+    let mut children = node.children();
+    let is_datetime = node.kind() == SyntaxKind::FuncCall
+        && children.next().is_some_and(|callee| {
+            callee.kind() == SyntaxKind::Ident && callee.text() == "datetime"
+        });
+    if is_datetime {
+        if let Some(args) = children.next() {
+            shift_arguments(&args, shifter, shifted);
+        }
+    }
+    for child in node.children() {
+        shift_datetime_calls(&child, shifter, shifted);
+    }
+}
+
+/// Shift the date given by the named `year`, `month` and `day` arguments, if all of them are
+/// integer literals.
+fn shift_arguments(
+    args: &LinkedNode,
+    shifter: &DateShifter,
+    shifted: &mut Vec<(Range<usize>, EcoString)>,
+) {
+    let mut fields: [Option<(Range<usize>, i64)>; 3] = [None, None, None];
+    for named in args
+        .children()
+        .filter(|arg| arg.kind() == SyntaxKind::Named)
+    {
+        let mut parts = named.children();
+        let Some(name) = parts.next() else { continue };
+        let Some(value) = parts.find(|part| part.kind() == SyntaxKind::Int) else {
+            continue;
+        };
+        let index = match name.text().as_str() {
+            "year" => 0,
+            "month" => 1,
+            "day" => 2,
+            _ => continue,
+        };
+        let Ok(number) = value.text().parse() else {
+            continue;
+        };
+        fields[index] = Some((value.range(), number));
+    }
+    let [Some(year), Some(month), Some(day)] = fields else {
+        return;
+    };
+    let (Ok(month_number), Ok(day_number)) = (u32::try_from(month.1), u32::try_from(day.1)) else {
+        return;
+    };
+    let Some(date) = Date::new(year.1, month_number, day_number) else {
+        return;
+    };
+    let date = date.shift(shifter.days);
+    let mut edits = [
+        (year.0, eco_format!("{}", date.year)),
+        (month.0, eco_format!("{}", date.month)),
+        (day.0, eco_format!("{}", date.day)),
+    ];
+    edits.sort_by_key(|(range, _)| range.start);
+    shifted.extend(edits);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift(days: i64, text: &str) -> Vec<EcoString> {
+        let shifter = DateShifter::new(days, Lang::English);
+        shifter
+            .shift(text)
+            .into_iter()
+            .map(|(_, date)| date)
+            .collect()
+    }
+
+    #[test]
+    fn numeric_dates() {
+        assert_eq!(shift(1, "2024-02-28"), ["2024-02-29"]);
+        assert_eq!(shift(1, "2023-02-28"), ["2023-03-01"]);
+        assert_eq!(shift(-1, "1.3.2000"), ["29.2.2000"]);
+        assert_eq!(shift(31, "01/31/2024"), ["03/02/2024"]);
+        assert!(shift(1, "2023-02-29 and 12024-01-01").is_empty());
+    }
+
+    #[test]
+    fn named_dates() {
+        assert_eq!(shift(1, "on December 31st, 2023."), ["January 1st, 2024"]);
+        assert_eq!(shift(-1, "am 1. März 2024"), ["29. Februar 2024"]);
+        assert_eq!(shift(365, "31 de enero de 2024"), ["30 de enero de 2025"]);
+    }
+}
@@ -0,0 +1,94 @@
+use hypher::Lang;
+
+use crate::{corpus::Discard, plan_str_with, Context, Error, MutilateOptions, Result};
+
+/// The confidence below which a detected language is ignored.
+///
+/// This is lower than the threshold of [`whatlang::Info::is_reliable`], which rejects most
+/// short English texts.
+const MINIMUM_CONFIDENCE: f64 = 0.25;
+
+/// Guess the dominant language of a document from the words of its prose.
+///
+/// Only the format and mode of the options are used, so the same document is detected to be in
+/// the same language however it is mutilated.
+///
+/// Returns `None` if the language can't be determined reliably or can't be hyphenated, and for
+/// documents with syntax errors, which are left to be reported by mutilating them. A language
+/// without a wordlist in the options or a bundled one is warned about and `None` is returned
+/// too, as its words could only be replaced with random letters.
+pub fn detect_language(code: &str, options: &MutilateOptions) -> Result<Option<Lang>> {
+    let prose = MutilateOptions::new()
+        .format(options.format)
+        .mode(options.mode);
+    let mut context = Context::with_replacer(&prose, Discard);
+    let edits = match plan_str_with(code, &mut context) {
+        Err(Error::Syntax(_)) => return Ok(None),
+        result => result?,
+    };
+    let mut text = String::new();
+    for edit in edits {
+        text.push_str(&code[edit.range]);
+        text.push(' ');
+    }
+
+    let Some(info) = whatlang::detect(&text) else {
+        return Ok(None);
+    };
+    log::debug!(
+        "detected {} with a confidence of {:.2}",
+        info.lang().eng_name(),
+        info.confidence()
+    );
+    if info.confidence() < MINIMUM_CONFIDENCE {
+        return Ok(None);
+    }
+    let Some(language) = crate::parse_language(info.lang().code()) else {
+        return Ok(None);
+    };
+    if !has_wordlist(language, options) {
+        log::warn!("detected {language:?} text, but there is no {language:?} wordlist");
+        return Ok(None);
+    }
+    Ok(Some(language))
+}
+
+/// Whether words of a language are replaced with words of a wordlist.
+fn has_wordlist(language: Lang, options: &MutilateOptions) -> bool {
+    options
+        .wordlists
+        .iter()
+        .any(|(wordlist_language, _)| wordlist_language.is_none_or(|other| other == language))
+        || crate::bundled_wordlist(language).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quotes;
+
+    const FRENCH: &str = "Le chat est sur la table et il mange une souris grise.";
+
+    #[test]
+    fn independent_of_options() {
+        let code = "\"Hello,\" she said, \"the weather is quite nice today, is it not?\"";
+        let options = MutilateOptions::new();
+        assert_eq!(
+            detect_language(code, &options.clone().quotes(Quotes::Keep)).unwrap(),
+            detect_language(code, &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn languages_with_wordlists() {
+        let options = MutilateOptions::new();
+        let expected = crate::bundled_wordlist(Lang::French).map(|_| Lang::French);
+        assert_eq!(detect_language(FRENCH, &options).unwrap(), expected);
+        let words = vec!["chien".into(), "maison".into()];
+        let options = options.language_wordlist(Lang::French, words);
+        assert_eq!(
+            detect_language(FRENCH, &options).unwrap(),
+            Some(Lang::French)
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Word-level diffs of planned edits for terminals.
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+use typst_mutilate::Edit;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// When to color the output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color the output of terminals, unless `NO_COLOR` is set or the terminal is dumb.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color the output of a stream, depending on whether it is a terminal.
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            Self::Auto => {
+                terminal
+                    && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::env::var_os("TERM").is_none_or(|term| term != "dumb")
+            }
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "unknown color choice `{s}`, expected one of `auto`, `always`, `never`"
+            )),
+        }
+    }
+}
+
+/// Write the lines of `code` that the `edits` change, prefixed by the file and line number.
+///
+/// With `color`, replaced text is red, its replacement green and the rest of the line dim;
+/// otherwise, they are marked like `[-old-]{+new+}`, as by `git diff --word-diff`.
+pub fn write(
+    out: &mut impl Write,
+    file: &str,
+    code: &str,
+    edits: &[Edit],
+    color: bool,
+) -> io::Result<()> {
+    let mut edits = edits.iter().peekable();
+    let mut line_start = 0;
+    // Where the written text ends, which is beyond the line after edits that span lines.
+    let mut cursor = 0;
+    for (number, line) in code.split_inclusive('\n').enumerate() {
+        let line_end = line_start + line.len();
+        cursor = cursor.max(line_start);
+        let mut changed = false;
+        while let Some(edit) = edits.next_if(|edit| edit.range.start < line_end) {
+            if !changed {
+                write!(out, "{file}:{}: ", number + 1)?;
+                changed = true;
+            }
+            unchanged(out, &code[cursor..edit.range.start], color)?;
+            let original = &code[edit.range.clone()];
+            if color {
+                write!(
+                    out,
+                    "{RED}{original}{RESET}{GREEN}{}{RESET}",
+                    edit.replacement
+                )?;
+            } else {
+                write!(out, "[-{original}-]{{+{}+}}", edit.replacement)?;
+            }
+            cursor = edit.range.end;
+        }
+        if changed {
+            if cursor < line_end {
+                let rest = code[cursor..line_end].trim_end_matches(['\r', '\n']);
+                unchanged(out, rest, color)?;
+            }
+            writeln!(out)?;
+        }
+        line_start = line_end;
+    }
+    Ok(())
+}
+
+fn unchanged(out: &mut impl Write, text: &str, color: bool) -> io::Result<()> {
+    if color && !text.is_empty() {
+        write!(out, "{DIM}{text}{RESET}")
+    } else {
+        write!(out, "{text}")
+    }
+}
@@ -0,0 +1,270 @@
+use std::ops::Range;
+
+use ecow::EcoString;
+
+use crate::Newline;
+
+/// A replacement of a byte range in the source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edit {
+    /// The byte range in the source that is replaced.
+    pub range: Range<usize>,
+    /// The text that replaces the range.
+    pub replacement: EcoString,
+}
+
+/// Splice sorted, non-overlapping `edits` into `source`, keeping everything else byte for byte.
+pub fn apply_edits(source: &str, edits: &[Edit]) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for edit in edits {
+        debug_assert!(
+            cursor <= edit.range.start,
+            "edits must be sorted and disjoint"
+        );
+        output.push_str(&source[cursor..edit.range.start]);
+        output.push_str(&edit.replacement);
+        cursor = edit.range.end;
+    }
+    output.push_str(&source[cursor..]);
+    output
+}
+
+/// Extend sorted, non-overlapping `edits` with the edits that convert every line break of the
+/// document they produce to `newline`, including those in the replacements.
+///
+/// Documents keep their line breaks and byte order mark unless they are converted like this.
+/// Both `\r\n`, `\n` and a lone `\r` are line breaks, as in typst.
+pub fn convert_newlines(source: &str, edits: Vec<Edit>, newline: Newline) -> Vec<Edit> {
+    let newline = newline.as_str();
+    let mut converted = Vec::with_capacity(edits.len());
+    let mut cursor = 0;
+    let unchanged = |converted: &mut Vec<Edit>, start: usize, end: usize| {
+        for (offset, len) in line_breaks(&source[start..end]) {
+            let range = start + offset..start + offset + len;
+            if &source[range.clone()] != newline {
+                converted.push(Edit {
+                    range,
+                    replacement: newline.into(),
+                });
+            }
+        }
+    };
+    for mut edit in edits {
+        unchanged(&mut converted, cursor, edit.range.start);
+        let mut replacement = EcoString::new();
+        let mut last = 0;
+        for (offset, len) in line_breaks(&edit.replacement) {
+            replacement.push_str(&edit.replacement[last..offset]);
+            replacement.push_str(newline);
+            last = offset + len;
+        }
+        if last > 0 {
+            replacement.push_str(&edit.replacement[last..]);
+            edit.replacement = replacement;
+        }
+        cursor = edit.range.end;
+        converted.push(edit);
+    }
+    unchanged(&mut converted, cursor, source.len());
+    converted
+}
+
+/// Adapt sorted, non-overlapping `edits` so that the document they produce has its line breaks
+/// exactly where `source` has them.
+///
+/// Replacements of text that spans lines, like regenerated paragraphs, are reflowed onto the
+/// lines of the replaced text, keeping their indentation and line breaks. Those that have too
+/// few words for its lines are left as they are, which [`changed_line`] reports.
+pub fn preserve_lines(source: &str, edits: Vec<Edit>) -> Vec<Edit> {
+    edits
+        .into_iter()
+        .map(|mut edit| {
+            let original = &source[edit.range.clone()];
+            if line_breaks(original).count() != line_breaks(&edit.replacement).count() {
+                if let Some(reflowed) = reflow(original, &edit.replacement) {
+                    edit.replacement = reflowed;
+                }
+            }
+            edit
+        })
+        .collect()
+}
+
+/// The number of the first line, counted from 1, that is blank in one of two documents but not
+/// in the other, or that only one of them has.
+pub fn changed_line(source: &str, output: &str) -> Option<usize> {
+    let blank = |text: &str| {
+        lines(text)
+            .map(|(line, _)| line.trim().is_empty())
+            .collect::<Vec<_>>()
+    };
+    let (before, after) = (blank(source), blank(output));
+    let line = before
+        .iter()
+        .zip(&after)
+        .position(|(before, after)| before != after)
+        .unwrap_or(before.len().min(after.len()));
+    (before.len() != after.len() || line < before.len()).then_some(line + 1)
+}
+
+/// Keep only the edits that touch one of the `lines` of `source`, counted from 1.
+pub(crate) fn within_lines(
+    source: &str,
+    lines: &[Range<usize>],
+    mut edits: Vec<Edit>,
+) -> Vec<Edit> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for (i, (line, newline)) in self::lines(source).enumerate() {
+        let end = start + line.len() + newline.len();
+        if lines.iter().any(|changed| changed.contains(&(i + 1))) {
+            ranges.push(start..end);
+        }
+        start = end;
+    }
+    edits.retain(|edit| {
+        // Insertions touch the line they are on.
+        let end = edit.range.end.max(edit.range.start + 1);
+        ranges
+            .iter()
+            .any(|line| line.start < end && edit.range.start < line.end)
+    });
+    edits
+}
+
+/// Distribute the words of a replacement over the lines of the text it replaces, in
+/// proportion to their lengths, with at least one word on every line that isn't blank.
+fn reflow(original: &str, replacement: &str) -> Option<EcoString> {
+    let lines: Vec<_> = lines(original).collect();
+    let words: Vec<_> = replacement.split_whitespace().collect();
+    let weights: Vec<_> = lines
+        .iter()
+        .map(|(line, _)| line.trim().chars().count())
+        .collect();
+    let filled = weights.iter().filter(|&&weight| weight > 0).count();
+    if words.len() < filled {
+        return None;
+    }
+
+    let total: usize = weights.iter().sum();
+    let mut reflowed = EcoString::new();
+    let (mut taken, mut weight, mut remaining) = (0, 0, filled);
+    for ((line, newline), &own) in lines.iter().zip(&weights) {
+        if own == 0 {
+            reflowed.push_str(line);
+        } else {
+            remaining -= 1;
+            weight += own;
+            // Leave at least a word for every remaining line.
+            let end = (words.len() * weight)
+                .div_ceil(total)
+                .clamp(taken + 1, words.len() - remaining);
+            reflowed.push_str(&line[..line.len() - line.trim_start().len()]);
+            reflowed.push_str(&words[taken..end].join(" "));
+            taken = end;
+        }
+        reflowed.push_str(newline);
+    }
+    Some(reflowed)
+}
+
+/// The lines of a text and the line breaks that end them, of which the last one has none.
+fn lines(text: &str) -> impl Iterator<Item = (&str, &str)> + '_ {
+    let mut start = 0;
+    line_breaks(text)
+        .map(|(offset, len)| (offset, offset + len))
+        .chain([(text.len(), text.len())])
+        .map(move |(end, next)| {
+            let line = (&text[start..end], &text[end..next]);
+            start = next;
+            line
+        })
+}
+
+/// The offsets and lengths of the line breaks of a text.
+fn line_breaks(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let bytes = text.as_bytes();
+    bytes
+        .iter()
+        .enumerate()
+        .filter_map(move |(i, &byte)| match byte {
+            b'\n' if i > 0 && bytes[i - 1] == b'\r' => None,
+            b'\n' => Some((i, 1)),
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => Some((i, 2)),
+            b'\r' => Some((i, 1)),
+            _ => None,
+        })
+}
+
+/// Merge two lists of edits, dropping the `edits` that overlap with one of the `overrides`.
+///
+/// Both lists have to be sorted and disjoint, and so is the result.
+pub(crate) fn override_edits(edits: Vec<Edit>, overrides: Vec<Edit>) -> Vec<Edit> {
+    let mut merged = Vec::with_capacity(edits.len() + overrides.len());
+    let mut overrides = overrides.into_iter().peekable();
+    for edit in edits {
+        while let Some(over) = overrides.next_if(|over| over.range.start < edit.range.end) {
+            merged.push(over);
+        }
+        let overlaps =
+            |over: &Edit| over.range.start < edit.range.end && edit.range.start < over.range.end;
+        if !merged.last().is_some_and(overlaps) {
+            merged.push(edit);
+        }
+    }
+    merged.extend(overrides);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(range: Range<usize>, replacement: &str) -> Edit {
+        Edit {
+            range,
+            replacement: replacement.into(),
+        }
+    }
+
+    #[test]
+    fn apply() {
+        let edits = [edit(0..5, "Howdy"), edit(6..11, "you"), edit(12..12, "!")];
+        assert_eq!(apply_edits("Hello world.", &edits), "Howdy you.!");
+    }
+
+    #[test]
+    fn overrides() {
+        let edits = vec![
+            edit(0..3, "a"),
+            edit(4..7, "b"),
+            edit(8..10, "c"),
+            edit(12..13, "d"),
+        ];
+        let overrides = vec![edit(5..9, "x"), edit(20..21, "y")];
+        assert_eq!(
+            override_edits(edits, overrides),
+            [
+                edit(0..3, "a"),
+                edit(5..9, "x"),
+                edit(12..13, "d"),
+                edit(20..21, "y")
+            ]
+        );
+    }
+
+    #[test]
+    fn lines() {
+        let edits = vec![
+            edit(0..1, "x"),
+            edit(2..2, "y"),
+            edit(3..5, "z"),
+            edit(6..7, "w"),
+        ];
+        assert_eq!(
+            within_lines("a\nb\nc\nd", &[2..3, 9..10], edits),
+            [edit(2..2, "y"), edit(3..5, "z")]
+        );
+    }
+}
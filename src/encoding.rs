@@ -0,0 +1,147 @@
+use std::{fmt, io, str::FromStr};
+
+/// The characters of the bytes `0x80..0xA0` in Windows-1252, or `None` for the five bytes it
+/// leaves undefined. All other bytes are the same as in Latin-1.
+const WINDOWS_1252: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+/// The character encoding of a document.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// ISO 8859-1, in which every byte is the character with the same code point.
+    Latin1,
+    /// The superset of Latin-1 of older Windows versions, with typographic quotes, dashes and
+    /// `€` instead of control characters.
+    Windows1252,
+}
+
+impl Encoding {
+    /// Every encoding.
+    pub const ALL: &'static [Encoding] = &[Self::Utf8, Self::Latin1, Self::Windows1252];
+
+    /// The name of the encoding, as accepted by [`FromStr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Latin1 => "latin1",
+            Self::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// Guess the encoding of a document: UTF-8 if it is valid UTF-8, and otherwise
+    /// Windows-1252, unless it has bytes that Windows-1252 leaves undefined.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if std::str::from_utf8(bytes).is_ok() {
+            Self::Utf8
+        } else if bytes.iter().all(|&byte| {
+            !(0x80..0xA0).contains(&byte) || WINDOWS_1252[usize::from(byte - 0x80)].is_some()
+        }) {
+            Self::Windows1252
+        } else {
+            Self::Latin1
+        }
+    }
+
+    /// Decode a document.
+    pub fn decode(self, bytes: Vec<u8>) -> io::Result<String> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Self::Latin1 => Ok(bytes.iter().map(|&byte| char::from(byte)).collect()),
+            Self::Windows1252 => bytes
+                .iter()
+                .map(|&byte| match byte {
+                    0x80..0xA0 => WINDOWS_1252[usize::from(byte - 0x80)].ok_or_else(|| {
+                        invalid(format!("byte {byte:#04x} is undefined in windows-1252"))
+                    }),
+                    _ => Ok(char::from(byte)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Encode a document, failing if it has characters the encoding lacks.
+    pub fn encode(self, text: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Utf8 => Ok(text.as_bytes().to_vec()),
+            Self::Latin1 | Self::Windows1252 => text
+                .chars()
+                .map(|c| {
+                    let special = || {
+                        WINDOWS_1252
+                            .iter()
+                            .position(|&special| special == Some(c))
+                            .map(|i| 0x80 + i as u8)
+                    };
+                    match u8::try_from(c) {
+                        Ok(byte) if self == Self::Latin1 || !(0x80..0xA0).contains(&byte) => {
+                            Some(byte)
+                        }
+                        _ if self == Self::Windows1252 => special(),
+                        _ => None,
+                    }
+                    .ok_or_else(|| invalid(format!("`{c}` can't be encoded in {self}")))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Self::Latin1),
+            "windows-1252" | "cp1252" => Ok(Self::Windows1252),
+            _ => Err(format!(
+                "unknown encoding `{s}`, expected one of `utf-8`, `latin1`, `windows-1252`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
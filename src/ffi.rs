@@ -0,0 +1,172 @@
+//! A C interface, enabled by the `ffi` feature. See `include/typst_mutilate.h`.
+
+use std::{
+    ffi::{c_char, CStr},
+    panic::AssertUnwindSafe,
+    path::PathBuf,
+};
+
+use crate::{Error, MutilateOptions};
+
+/// Settings that control how a document is mutilated.
+#[repr(C)]
+pub struct TypstMutilateOptions {
+    /// A language tag, like `de` or `de-AT`, or null for English.
+    pub language: *const c_char,
+    /// The path to a line-separated wordlist, or null.
+    pub wordlist: *const c_char,
+    /// Whether to replace elements that are more likely to change behavior, like strings.
+    pub aggressive: bool,
+    /// Whether `seed` should be used.
+    pub has_seed: bool,
+    /// A seed for the random number generator.
+    pub seed: u64,
+}
+
+/// An owned byte buffer that must be released with [`typst_mutilate_free`].
+#[repr(C)]
+pub struct TypstMutilateBuffer {
+    pub ptr: *mut u8,
+    pub len: usize,
+}
+
+impl TypstMutilateBuffer {
+    fn new(bytes: impl Into<Box<[u8]>>) -> Self {
+        let bytes = Box::into_raw(bytes.into());
+        Self {
+            ptr: bytes.cast(),
+            len: bytes.len(),
+        }
+    }
+}
+
+pub const TYPST_MUTILATE_OK: i32 = 0;
+pub const TYPST_MUTILATE_INVALID_ARGUMENT: i32 = 1;
+pub const TYPST_MUTILATE_IO_ERROR: i32 = 2;
+pub const TYPST_MUTILATE_SYNTAX_ERROR: i32 = 3;
+/// A bug in typst-mutilate, which is caught instead of unwinding into the caller.
+pub const TYPST_MUTILATE_INTERNAL_ERROR: i32 = 4;
+
+/// Mutilate the UTF-8 typst document in `input`.
+///
+/// On success, `out` receives the mutilated document and `TYPST_MUTILATE_OK` is returned.
+/// Otherwise, `out` receives a UTF-8 error message and an error code is returned. In both
+/// cases, `out` must be released with [`typst_mutilate_free`].
+///
+/// # Safety
+/// `input` must point to `len` readable bytes, `options` must be null or point to valid
+/// options whose strings are null or nul-terminated, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn typst_mutilate(
+    input: *const u8,
+    len: usize,
+    options: *const TypstMutilateOptions,
+    out: *mut TypstMutilateBuffer,
+) -> i32 {
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| mutilate(input, len, options)))
+        .unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            Err((
+                TYPST_MUTILATE_INTERNAL_ERROR,
+                format!("typst-mutilate panicked: {message}"),
+            ))
+        });
+    let (code, bytes) = match result {
+        Ok(output) => (TYPST_MUTILATE_OK, output.into_bytes()),
+        Err((code, message)) => (code, message.into_bytes()),
+    };
+    out.write(TypstMutilateBuffer::new(bytes));
+    code
+}
+
+unsafe fn mutilate(
+    input: *const u8,
+    len: usize,
+    options: *const TypstMutilateOptions,
+) -> Result<String, (i32, String)> {
+    let invalid = |message: String| (TYPST_MUTILATE_INVALID_ARGUMENT, message);
+
+    let input = if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(input, len)
+    };
+    let code = std::str::from_utf8(input).map_err(|err| invalid(err.to_string()))?;
+
+    let mut mutilate_options = MutilateOptions::new();
+    if let Some(options) = options.as_ref() {
+        if let Some(language) = c_str(options.language) {
+            let language = language.map_err(invalid)?;
+            let language = crate::parse_language(language)
+                .ok_or_else(|| invalid(format!("language `{language}` is not supported")))?;
+            mutilate_options = mutilate_options.language(language);
+        }
+        if let Some(wordlist) = c_str(options.wordlist) {
+            mutilate_options = mutilate_options.wordlist(PathBuf::from(wordlist.map_err(invalid)?));
+        }
+        mutilate_options = mutilate_options.aggressive(options.aggressive);
+        if options.has_seed {
+            mutilate_options = mutilate_options.seed(options.seed);
+        }
+    }
+
+    crate::mutilate_str(code, &mutilate_options).map_err(|err| {
+        let code = match err {
+            Error::Io(_) => TYPST_MUTILATE_IO_ERROR,
+            Error::Syntax(_) => TYPST_MUTILATE_SYNTAX_ERROR,
+            Error::Unsupported(_) => TYPST_MUTILATE_INVALID_ARGUMENT,
+        };
+        (code, err.to_string())
+    })
+}
+
+unsafe fn c_str<'a>(ptr: *const c_char) -> Option<Result<&'a str, String>> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(ptr).to_str().map_err(|err| err.to_string()))
+}
+
+/// Release a buffer returned by [`typst_mutilate`].
+///
+/// # Safety
+/// `buffer` must have been returned by [`typst_mutilate`] and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn typst_mutilate_free(buffer: TypstMutilateBuffer) {
+    if !buffer.ptr.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            buffer.ptr, buffer.len,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(input: &[u8]) -> (i32, Vec<u8>) {
+        let mut out = TypstMutilateBuffer {
+            ptr: std::ptr::null_mut(),
+            len: 0,
+        };
+        unsafe {
+            let code = typst_mutilate(input.as_ptr(), input.len(), std::ptr::null(), &mut out);
+            let bytes = std::slice::from_raw_parts(out.ptr, out.len).to_vec();
+            typst_mutilate_free(out);
+            (code, bytes)
+        }
+    }
+
+    #[test]
+    fn codes() {
+        let (code, output) = call(b"Some text.");
+        assert_eq!(code, TYPST_MUTILATE_OK);
+        assert_eq!(output.len(), b"Some text.".len());
+        assert_eq!(call(b"\xff").0, TYPST_MUTILATE_INVALID_ARGUMENT);
+        assert_eq!(call(b"#let x = (").0, TYPST_MUTILATE_SYNTAX_ERROR);
+    }
+}
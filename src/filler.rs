@@ -0,0 +1,62 @@
+use std::ops::Range;
+
+use ecow::EcoString;
+use rand::seq::SliceRandom;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::scan;
+
+const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const DIGITS: &[u8] = b"0123456789";
+
+/// Replaces the identifiers and numbers of raw text with random characters of the same kinds,
+/// so that the text keeps its exact shape.
+pub(crate) struct Filler {
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Filler {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x3f84_d5b5_b547_0917);
+        Self { rng }
+    }
+
+    /// The ranges of the runs of alphanumeric characters and underscores in `text`, with
+    /// replacements of as many characters.
+    ///
+    /// Letters become random letters of the same case and digits random digits, while
+    /// underscores, brackets, punctuation and whitespace stay where they are.
+    pub(crate) fn fill(&mut self, text: &str) -> Vec<(Range<usize>, EcoString)> {
+        let mut runs = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            if c.is_alphanumeric() || c == '_' {
+                start.get_or_insert(i);
+                continue;
+            }
+            let Some(start) = start.take() else {
+                continue;
+            };
+            let run = &text[start..i];
+            if run.chars().all(|c| c == '_') {
+                continue;
+            }
+            let mut filled = EcoString::with_capacity(scan::char_count(run));
+            for c in run.chars() {
+                if c == '_' {
+                    filled.push(c);
+                    continue;
+                }
+                let pool = if c.is_numeric() { DIGITS } else { LETTERS };
+                let replaced = char::from(*pool.choose(&mut self.rng).unwrap());
+                filled.push(if c.is_uppercase() {
+                    replaced.to_ascii_uppercase()
+                } else {
+                    replaced
+                });
+            }
+            runs.push((start..i, filled));
+        }
+        runs
+    }
+}
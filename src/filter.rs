@@ -0,0 +1,290 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use typst_syntax::{LinkedNode, SyntaxKind};
+
+/// What to do with a syntax node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeAction {
+    /// Replace the words in the node.
+    ///
+    /// Nodes with children are descended into, so that each child is decided on separately.
+    Mutilate,
+    /// Copy the node and all of its children verbatim.
+    Copy,
+    /// Decide on each child of the node separately, without replacing anything in the node
+    /// itself, so that leaves, which have no children, are copied verbatim.
+    Descend,
+}
+
+impl FromStr for NodeAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mutilate" => Ok(Self::Mutilate),
+            "copy" => Ok(Self::Copy),
+            "descend" => Ok(Self::Descend),
+            _ => Err(format!(
+                "unknown action `{s}`, expected one of `mutilate`, `copy`, `descend`"
+            )),
+        }
+    }
+}
+
+/// Decides what happens to a syntax node, overriding the built-in rules.
+///
+/// The node is passed as a [`LinkedNode`], which gives access to its kind, its range in the
+/// source and its ancestors. Returning `None` applies the built-in rules.
+pub trait NodeFilter {
+    /// Decide what to do with `node`.
+    fn filter(&mut self, node: &LinkedNode) -> Option<NodeAction>;
+}
+
+impl<F> NodeFilter for F
+where
+    F: FnMut(&LinkedNode) -> Option<NodeAction>,
+{
+    fn filter(&mut self, node: &LinkedNode) -> Option<NodeAction> {
+        self(node)
+    }
+}
+
+/// The kinds of the nodes whose text is replaced by the built-in rules, with the strings of
+/// aggressive mode.
+const MUTILATED: &[SyntaxKind] = &[
+    SyntaxKind::Text,
+    SyntaxKind::LineComment,
+    SyntaxKind::BlockComment,
+    SyntaxKind::Raw,
+    SyntaxKind::Link,
+    SyntaxKind::Str,
+];
+
+/// A filter that assigns actions to whole node kinds.
+#[derive(Debug, Clone, Default)]
+pub struct KindFilter {
+    actions: HashMap<SyntaxKind, NodeAction>,
+    /// The kinds of the nodes that are the only ones mutilated, with everything in them.
+    only: Option<HashSet<SyntaxKind>>,
+}
+
+impl KindFilter {
+    /// Create a filter that doesn't override anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `action` to all nodes of the given kind.
+    pub fn set(&mut self, kind: SyntaxKind, action: NodeAction) {
+        self.actions.insert(kind, action);
+    }
+
+    /// Only mutilate the nodes of the given kinds and what is inside them, like `text` or
+    /// `heading`; can be called repeatedly to add more kinds.
+    ///
+    /// Strings are mutilated if their kind is given, even if that isn't aggressive, while
+    /// the actions [`set`](Self::set) for a kind take precedence.
+    pub fn only(&mut self, kinds: impl IntoIterator<Item = SyntaxKind>) {
+        self.only.get_or_insert_with(HashSet::new).extend(kinds);
+    }
+}
+
+impl NodeFilter for KindFilter {
+    fn filter(&mut self, node: &LinkedNode) -> Option<NodeAction> {
+        // The text of `raw` calls counts as raw text.
+        let kind = match node.kind() {
+            SyntaxKind::Str if crate::raw_body(node) => SyntaxKind::Raw,
+            kind => kind,
+        };
+        if let Some(&action) = self.actions.get(&kind) {
+            return Some(action);
+        }
+        let only = self.only.as_ref()?;
+        if only.contains(&kind) {
+            return MUTILATED.contains(&kind).then_some(NodeAction::Mutilate);
+        }
+        let mut ancestor = node.parent();
+        while let Some(node) = ancestor {
+            if only.contains(&node.kind()) {
+                return None;
+            }
+            ancestor = node.parent();
+        }
+        MUTILATED.contains(&kind).then_some(NodeAction::Copy)
+    }
+}
+
+/// The syntax kinds of `typst-syntax`, which doesn't provide a list of them.
+const ALL_KINDS: &[SyntaxKind] = &[
+    SyntaxKind::Markup,
+    SyntaxKind::Text,
+    SyntaxKind::Space,
+    SyntaxKind::Linebreak,
+    SyntaxKind::Parbreak,
+    SyntaxKind::Escape,
+    SyntaxKind::Shorthand,
+    SyntaxKind::SmartQuote,
+    SyntaxKind::Strong,
+    SyntaxKind::Emph,
+    SyntaxKind::Raw,
+    SyntaxKind::RawLang,
+    SyntaxKind::RawDelim,
+    SyntaxKind::RawTrimmed,
+    SyntaxKind::Link,
+    SyntaxKind::Label,
+    SyntaxKind::Ref,
+    SyntaxKind::RefMarker,
+    SyntaxKind::Heading,
+    SyntaxKind::HeadingMarker,
+    SyntaxKind::ListItem,
+    SyntaxKind::ListMarker,
+    SyntaxKind::EnumItem,
+    SyntaxKind::EnumMarker,
+    SyntaxKind::TermItem,
+    SyntaxKind::TermMarker,
+    SyntaxKind::Equation,
+    SyntaxKind::Math,
+    SyntaxKind::MathIdent,
+    SyntaxKind::MathAlignPoint,
+    SyntaxKind::MathDelimited,
+    SyntaxKind::MathAttach,
+    SyntaxKind::MathPrimes,
+    SyntaxKind::MathFrac,
+    SyntaxKind::MathRoot,
+    SyntaxKind::Hash,
+    SyntaxKind::LeftBrace,
+    SyntaxKind::RightBrace,
+    SyntaxKind::LeftBracket,
+    SyntaxKind::RightBracket,
+    SyntaxKind::LeftParen,
+    SyntaxKind::RightParen,
+    SyntaxKind::Comma,
+    SyntaxKind::Semicolon,
+    SyntaxKind::Colon,
+    SyntaxKind::Star,
+    SyntaxKind::Underscore,
+    SyntaxKind::Dollar,
+    SyntaxKind::Plus,
+    SyntaxKind::Minus,
+    SyntaxKind::Slash,
+    SyntaxKind::Hat,
+    SyntaxKind::Prime,
+    SyntaxKind::Dot,
+    SyntaxKind::Eq,
+    SyntaxKind::EqEq,
+    SyntaxKind::ExclEq,
+    SyntaxKind::Lt,
+    SyntaxKind::LtEq,
+    SyntaxKind::Gt,
+    SyntaxKind::GtEq,
+    SyntaxKind::PlusEq,
+    SyntaxKind::HyphEq,
+    SyntaxKind::StarEq,
+    SyntaxKind::SlashEq,
+    SyntaxKind::Dots,
+    SyntaxKind::Arrow,
+    SyntaxKind::Root,
+    SyntaxKind::Not,
+    SyntaxKind::And,
+    SyntaxKind::Or,
+    SyntaxKind::None,
+    SyntaxKind::Auto,
+    SyntaxKind::Let,
+    SyntaxKind::Set,
+    SyntaxKind::Show,
+    SyntaxKind::Context,
+    SyntaxKind::If,
+    SyntaxKind::Else,
+    SyntaxKind::For,
+    SyntaxKind::In,
+    SyntaxKind::While,
+    SyntaxKind::Break,
+    SyntaxKind::Continue,
+    SyntaxKind::Return,
+    SyntaxKind::Import,
+    SyntaxKind::Include,
+    SyntaxKind::As,
+    SyntaxKind::Code,
+    SyntaxKind::Ident,
+    SyntaxKind::Bool,
+    SyntaxKind::Int,
+    SyntaxKind::Float,
+    SyntaxKind::Numeric,
+    SyntaxKind::Str,
+    SyntaxKind::CodeBlock,
+    SyntaxKind::ContentBlock,
+    SyntaxKind::Parenthesized,
+    SyntaxKind::Array,
+    SyntaxKind::Dict,
+    SyntaxKind::Named,
+    SyntaxKind::Keyed,
+    SyntaxKind::Unary,
+    SyntaxKind::Binary,
+    SyntaxKind::FieldAccess,
+    SyntaxKind::FuncCall,
+    SyntaxKind::Args,
+    SyntaxKind::Spread,
+    SyntaxKind::Closure,
+    SyntaxKind::Params,
+    SyntaxKind::LetBinding,
+    SyntaxKind::SetRule,
+    SyntaxKind::ShowRule,
+    SyntaxKind::Contextual,
+    SyntaxKind::Conditional,
+    SyntaxKind::WhileLoop,
+    SyntaxKind::ForLoop,
+    SyntaxKind::ModuleImport,
+    SyntaxKind::ImportItems,
+    SyntaxKind::RenamedImportItem,
+    SyntaxKind::ModuleInclude,
+    SyntaxKind::LoopBreak,
+    SyntaxKind::LoopContinue,
+    SyntaxKind::FuncReturn,
+    SyntaxKind::Destructuring,
+    SyntaxKind::DestructAssignment,
+    SyntaxKind::LineComment,
+    SyntaxKind::BlockComment,
+    SyntaxKind::Error,
+    SyntaxKind::Eof,
+];
+
+/// Every syntax kind, in declaration order.
+pub fn all_kinds() -> impl Iterator<Item = SyntaxKind> {
+    ALL_KINDS.iter().copied()
+}
+
+/// The kebab-case name of a syntax kind, like `line-comment`.
+pub fn kind_name(kind: SyntaxKind) -> String {
+    let mut name = String::new();
+    for c in format!("{kind:?}").chars() {
+        if c.is_uppercase() && !name.is_empty() {
+            name.push('-');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// Find the syntax kind with the given kebab-case name.
+pub fn parse_kind(name: &str) -> Option<SyntaxKind> {
+    all_kinds().find(|&kind| kind_name(kind) == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds() {
+        // The kinds are numbered in declaration order, so a missing one would leave a gap.
+        for (i, &kind) in ALL_KINDS.iter().enumerate() {
+            assert_eq!(kind as usize, i);
+            assert_eq!(parse_kind(&kind_name(kind)), Some(kind));
+        }
+        assert_eq!(ALL_KINDS.last(), Some(&SyntaxKind::Eof));
+        assert_eq!(kind_name(SyntaxKind::LineComment), "line-comment");
+    }
+}
@@ -0,0 +1,103 @@
+use hypher::Lang;
+
+/// The writing system of a word or a language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Georgian,
+}
+
+impl Script {
+    pub(crate) fn of_language(language: Lang) -> Self {
+        match language {
+            Lang::Greek => Self::Greek,
+            Lang::Georgian => Self::Georgian,
+            Lang::Belarusian
+            | Lang::Bulgarian
+            | Lang::Mongolian
+            | Lang::Russian
+            | Lang::Serbian
+            | Lang::Ukrainian => Self::Cyrillic,
+            _ => Self::Latin,
+        }
+    }
+
+    /// The script of the first letter of a word, if it is one of the known scripts.
+    pub(crate) fn of_word(word: &str) -> Option<Self> {
+        let c = word.chars().find(|c| c.is_alphabetic())?;
+        match c {
+            'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => {
+                Some(Self::Latin)
+            }
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Some(Self::Greek),
+            '\u{0400}'..='\u{052F}' => Some(Self::Cyrillic),
+            '\u{10A0}'..='\u{10FF}' | '\u{1C90}'..='\u{1CBF}' => Some(Self::Georgian),
+            _ => None,
+        }
+    }
+
+    /// The language used for words of this script if none of the candidates is written in it.
+    fn default_language(self) -> Lang {
+        match self {
+            Self::Latin => Lang::English,
+            Self::Greek => Lang::Greek,
+            Self::Cyrillic => Lang::Russian,
+            Self::Georgian => Lang::Georgian,
+        }
+    }
+}
+
+/// Guess the language of a single word in text of the language `current`.
+///
+/// Words in a different script than the current language get a language of their script,
+/// preferring the candidates. With the `detect` feature, the language of longer words in the
+/// same script is guessed from their letters among the candidates.
+pub(crate) fn guess_language(word: &str, current: Lang, candidates: &[Lang]) -> Lang {
+    let Some(script) = Script::of_word(word) else {
+        return current;
+    };
+    if script != Script::of_language(current) {
+        return candidates
+            .iter()
+            .copied()
+            .find(|&language| Script::of_language(language) == script)
+            .unwrap_or_else(|| script.default_language());
+    }
+
+    #[cfg(feature = "detect")]
+    if let Some(language) = detect(word, current, candidates, script) {
+        return language;
+    }
+    current
+}
+
+/// Guess the language of a word from its letters.
+#[cfg(feature = "detect")]
+fn detect(word: &str, current: Lang, candidates: &[Lang], script: Script) -> Option<Lang> {
+    /// Shorter words don't carry enough information.
+    const MINIMUM_LENGTH: usize = 5;
+    /// The confidence required to switch away from the current language. Single words rarely
+    /// get much more than this, even if they are obviously in one language.
+    const MINIMUM_CONFIDENCE: f64 = 0.25;
+
+    if word.chars().count() < MINIMUM_LENGTH {
+        return None;
+    }
+    let allowlist: Vec<_> = std::iter::once(current)
+        .chain(candidates.iter().copied())
+        .filter(|&language| Script::of_language(language) == script)
+        .filter_map(|language| {
+            whatlang::Lang::from_code(crate::options::three_letter_code(language)?)
+        })
+        .collect();
+    if allowlist.len() < 2 {
+        return None;
+    }
+    let info = whatlang::Detector::with_allowlist(allowlist).detect(word)?;
+    if info.confidence() < MINIMUM_CONFIDENCE {
+        return None;
+    }
+    crate::parse_language(info.lang().code())
+}
@@ -0,0 +1,321 @@
+use std::ops::Range;
+
+use ecow::EcoString;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// A kind of bibliographic identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// A digital object identifier, like `10.1000/xyz123`.
+    Doi,
+    /// An ISBN with 13 digits, like `978-3-16-148410-0`.
+    Isbn13,
+    /// An ISBN with 10 digits, like `3-16-148410-X`.
+    Isbn10,
+    /// An ORCID iD, like `0000-0002-1825-0097`.
+    Orcid,
+    /// An arXiv identifier after `arXiv:`, like `2101.01234v2` or `hep-th/9901001`.
+    Arxiv,
+}
+
+/// Texts that mark the following number as an ISBN.
+const ISBN_LABELS: &[&str] = &["ISBN", "ISBN-10", "ISBN-13", "ISBN 10", "ISBN 13"];
+
+/// Finds identifiers in text and replaces them with valid identifiers that don't resolve.
+pub(crate) struct Faker {
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Faker {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x3e91_c0a5_84d2_b76f);
+        Self { rng }
+    }
+
+    /// Find the identifiers in `text` and choose their replacements.
+    ///
+    /// The ranges are sorted and disjoint.
+    pub(crate) fn replace(&mut self, text: &str) -> Vec<(Range<usize>, EcoString)> {
+        let bytes = text.as_bytes();
+        let mut found = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let follows_word = text[..i]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric);
+            if bytes[i].is_ascii_alphanumeric() && !follows_word {
+                if let Some((end, kind)) = find_at(text, i) {
+                    let fake = self.fake(&text[i..end], kind);
+                    found.push((i..end, fake));
+                    i = end;
+                    continue;
+                }
+            }
+            i += text[i..].chars().next().unwrap().len_utf8();
+        }
+        found
+    }
+
+    /// Generate an identifier of the same kind and format as `original`.
+    fn fake(&mut self, original: &str, kind: Kind) -> EcoString {
+        let mut fake: Vec<u8> = original.bytes().collect();
+        match kind {
+            Kind::Doi => {
+                // Keep the `10.` of the directory, and a nonzero start of the registrant.
+                let slash = original.find('/').unwrap();
+                self.randomize(&mut fake[3..slash], true);
+                self.randomize(&mut fake[slash + 1..], false);
+            }
+            Kind::Isbn13 => {
+                // Keep the `978` or `979` prefix.
+                let last = fake.len() - 1;
+                self.randomize(&mut fake[3..last], false);
+                fake[last] = isbn13_check(&fake[..last]);
+            }
+            Kind::Isbn10 => {
+                let last = fake.len() - 1;
+                self.randomize(&mut fake[..last], false);
+                fake[last] = isbn10_check(&fake[..last]);
+            }
+            Kind::Orcid => {
+                // Keep the block of the registry, like `0000-0002`.
+                let last = fake.len() - 1;
+                self.randomize(&mut fake[9..last], false);
+                fake[last] = orcid_check(&fake[..last]);
+            }
+            Kind::Arxiv => {
+                // Keep the archive of old identifiers, the year and the version.
+                let start = original.rfind(['/', ':']).map_or(0, |i| i + 1);
+                let end = original
+                    .rfind('v')
+                    .filter(|&v| v > start)
+                    .unwrap_or(fake.len());
+                let month = self.rng.gen_range(1..=12u8);
+                fake[start + 2] = b'0' + month / 10;
+                fake[start + 3] = b'0' + month % 10;
+                self.randomize(&mut fake[start + 4..end], false);
+            }
+        }
+
+        // Only ASCII bytes were replaced by ASCII bytes.
+        String::from_utf8(fake).unwrap().into()
+    }
+
+    /// Replace the letters and digits in `bytes` by random ones of the same class, optionally
+    /// keeping the first digit nonzero.
+    fn randomize(&mut self, bytes: &mut [u8], nonzero: bool) {
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = match *b {
+                b'0'..=b'9' if nonzero && i == 0 => self.rng.gen_range(b'1'..=b'9'),
+                b'0'..=b'9' => self.rng.gen_range(b'0'..=b'9'),
+                b'a'..=b'z' => self.rng.gen_range(b'a'..=b'z'),
+                b'A'..=b'Z' => self.rng.gen_range(b'A'..=b'Z'),
+                other => other,
+            };
+        }
+    }
+}
+
+/// Find an identifier starting at byte `start`, returning where it ends and its kind.
+fn find_at(text: &str, start: usize) -> Option<(usize, Kind)> {
+    let bytes = &text.as_bytes()[start..];
+    let (len, kind) = match_doi(bytes)
+        .map(|len| (len, Kind::Doi))
+        .or_else(|| match_orcid(bytes).map(|len| (len, Kind::Orcid)))
+        .or_else(|| match_arxiv(bytes).map(|len| (len, Kind::Arxiv)))
+        .or_else(|| match_isbn(bytes, is_isbn_label(&text[..start])))?;
+    let end = start + len;
+    let at_boundary = !text[end..].starts_with(char::is_alphanumeric);
+    at_boundary.then_some((end, kind))
+}
+
+/// Match `10.`, a registrant code of digits and dots, a slash and a suffix, which ends before
+/// whitespace and trailing punctuation.
+fn match_doi(bytes: &[u8]) -> Option<usize> {
+    let rest = bytes.strip_prefix(b"10.")?;
+    let registrant = rest
+        .iter()
+        .take_while(|b| b.is_ascii_digit() || **b == b'.')
+        .count();
+    if !(4..=16).contains(&registrant) || !rest[0].is_ascii_digit() || rest[registrant - 1] == b'.'
+    {
+        return None;
+    }
+    let suffix = &rest[registrant..].strip_prefix(b"/")?;
+    let mut len = suffix
+        .iter()
+        .take_while(|b| b.is_ascii_graphic() && !b"\"<>\\".contains(b))
+        .count();
+    while len > 0 {
+        let last = suffix[len - 1];
+        let unbalanced = last == b')' && !suffix[..len].contains(&b'(');
+        if b".,;:!?]}'".contains(&last) || unbalanced {
+            len -= 1;
+        } else {
+            break;
+        }
+    }
+    (len > 0).then_some(3 + registrant + 1 + len)
+}
+
+/// Match `9999-9999-9999-999X` with a valid check character.
+fn match_orcid(bytes: &[u8]) -> Option<usize> {
+    let shape = b"9999-9999-9999-999X";
+    let candidate = bytes.get(..shape.len())?;
+    let matches = candidate.iter().zip(shape).all(|(b, s)| match s {
+        b'9' => b.is_ascii_digit(),
+        b'X' => b.is_ascii_digit() || *b == b'X',
+        _ => b == s,
+    });
+    let last = shape.len() - 1;
+    (matches && orcid_check(&candidate[..last]) == candidate[last]).then_some(shape.len())
+}
+
+/// Match `arXiv:` followed by `YYMM.NNNNN` or an archive and `YYMMNNN`, with an optional
+/// version.
+fn match_arxiv(bytes: &[u8]) -> Option<usize> {
+    let prefix = b"arxiv:";
+    if !bytes.get(..prefix.len())?.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    let rest = &bytes[prefix.len()..];
+    let digits = |bytes: &[u8]| bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+
+    let archive = rest
+        .iter()
+        .take_while(|b| b.is_ascii_alphabetic() || b"-.".contains(b))
+        .count();
+    let (start, end) = if archive > 0 {
+        let number = rest[archive..].strip_prefix(b"/")?;
+        (digits(number) == 7).then_some((archive + 1, archive + 1 + 7))?
+    } else {
+        let number = rest.get(5..)?;
+        let ok = digits(rest) == 4 && rest[4] == b'.' && (4..=5).contains(&digits(number));
+        ok.then_some((0, 5 + digits(number)))?
+    };
+    let month = &rest[start + 2..start + 4];
+    if !(b"01".as_slice()..=b"12").contains(&month) {
+        return None;
+    }
+
+    let version = match rest[end..].strip_prefix(b"v") {
+        Some(version) if digits(version) > 0 => 1 + digits(version),
+        _ => 0,
+    };
+    Some(prefix.len() + end + version)
+}
+
+/// Whether the text before a number labels it as an ISBN.
+fn is_isbn_label(before: &str) -> bool {
+    let before = before.trim_end_matches([' ', ':', '\u{a0}']);
+    ISBN_LABELS.iter().any(|label| before.ends_with(label))
+}
+
+/// Match an ISBN with digits separated by single hyphens or spaces and a valid check digit.
+///
+/// Numbers with 13 digits have to start with `978` or `979`. Numbers with 10 digits have to
+/// be labeled, or hyphenated into four groups like ISBNs are.
+fn match_isbn(bytes: &[u8], labeled: bool) -> Option<(usize, Kind)> {
+    let mut len = 0;
+    let mut digits = Vec::new();
+    let mut hyphens = 0;
+    while len < bytes.len() && digits.len() < 13 {
+        let b = bytes[len];
+        let check = b == b'X' && digits.len() == 9;
+        if b.is_ascii_digit() || check {
+            digits.push(b);
+            len += 1;
+            if check {
+                break;
+            }
+        } else if (b == b'-' || b == b' ') && !digits.is_empty() {
+            match bytes.get(len + 1) {
+                Some(next) if next.is_ascii_digit() || *next == b'X' => {
+                    hyphens += usize::from(b == b'-');
+                    len += 1;
+                }
+                _ => break,
+            }
+        } else {
+            break;
+        }
+    }
+
+    match digits.len() {
+        13 if (digits.starts_with(b"978") || digits.starts_with(b"979"))
+            && isbn13_check(&digits[..12]) == digits[12] =>
+        {
+            Some((len, Kind::Isbn13))
+        }
+        10 if (labeled || hyphens == 3) && isbn10_check(&digits[..9]) == digits[9] => {
+            Some((len, Kind::Isbn10))
+        }
+        _ => None,
+    }
+}
+
+/// The check digit of an ISBN-13, computed from the preceding digits and separators.
+fn isbn13_check(bytes: &[u8]) -> u8 {
+    let sum: u32 = digits(bytes)
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { digit } else { digit * 3 })
+        .sum();
+    b'0' + ((10 - sum % 10) % 10) as u8
+}
+
+/// The check character of an ISBN-10, computed from the preceding digits and separators.
+fn isbn10_check(bytes: &[u8]) -> u8 {
+    let sum: u32 = digits(bytes)
+        .enumerate()
+        .map(|(i, digit)| (10 - i as u32) * digit)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => b'X',
+        check => b'0' + check as u8,
+    }
+}
+
+/// The check character of an ORCID iD according to ISO 7064 11,2.
+fn orcid_check(bytes: &[u8]) -> u8 {
+    let total = digits(bytes).fold(0, |total, digit| (total + digit) * 2);
+    match (12 - total % 11) % 11 {
+        10 => b'X',
+        check => b'0' + check as u8,
+    }
+}
+
+/// The values of the digits in `bytes`, skipping separators.
+fn digits(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .iter()
+        .filter(|b| b.is_ascii_digit())
+        .map(|b| u32::from(b - b'0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits() {
+        assert_eq!(isbn13_check(b"978-3-16-148410-"), b'0');
+        assert_eq!(isbn10_check(b"3-16-148410-"), b'X');
+        assert_eq!(orcid_check(b"0000-0002-1825-009"), b'7');
+    }
+
+    #[test]
+    fn fakes_keep_check_digits() {
+        let text = "ISBN 978-3-16-148410-0 by 0000-0002-1825-0097, doi 10.1000/xyz123";
+        let found = Faker::new(Some(1)).replace(text);
+        assert_eq!(found.len(), 3);
+        for ((range, fake), kind) in found.iter().zip([Kind::Isbn13, Kind::Orcid, Kind::Doi]) {
+            assert_eq!(find_at(text, range.start), Some((range.end, kind)));
+            assert_ne!(&text[range.clone()], fake.as_str());
+            // The fake is found like the original, so its check digit is valid.
+            let faked = format!("{}{fake}", &text[..range.start]);
+            assert_eq!(find_at(&faked, range.start), Some((faked.len(), kind)));
+        }
+    }
+}
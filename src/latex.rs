@@ -0,0 +1,216 @@
+use std::io;
+
+use crate::{plan_text, Context, Edit};
+
+/// Commands whose arguments are prose that should be mutilated.
+const TEXT_COMMANDS: &[&str] = &[
+    "part",
+    "chapter",
+    "section",
+    "subsection",
+    "subsubsection",
+    "paragraph",
+    "subparagraph",
+    "title",
+    "author",
+    "thanks",
+    "caption",
+    "footnote",
+    "marginpar",
+    "emph",
+    "textbf",
+    "textit",
+    "textsl",
+    "textsc",
+    "textup",
+    "textrm",
+    "textsf",
+    "texttt",
+    "textmd",
+    "underline",
+    "mbox",
+    "text",
+    "frametitle",
+    "framesubtitle",
+];
+
+/// Environments whose contents are copied verbatim.
+const VERBATIM_ENVIRONMENTS: &[&str] = &[
+    "equation",
+    "align",
+    "alignat",
+    "flalign",
+    "gather",
+    "multline",
+    "eqnarray",
+    "math",
+    "displaymath",
+    "verbatim",
+    "Verbatim",
+    "lstlisting",
+    "minted",
+    "comment",
+    "tikzpicture",
+    "filecontents",
+];
+
+/// Compute the edits that mutilate the prose in a LaTeX document.
+///
+/// Commands, their non-prose arguments, environments, math and verbatim material are copied
+/// verbatim. The arguments of common text commands like `\section` and `\emph` are mutilated,
+/// as are comments.
+pub(crate) fn plan_latex(
+    code: &str,
+    context: &mut Context,
+    edits: &mut Vec<Edit>,
+) -> io::Result<()> {
+    let mut prose_start = 0;
+    let mut i = 0;
+    while i < code.len() {
+        let rest = &code[i..];
+        let verbatim_end = if rest.starts_with("\\(") {
+            Some(find_after(code, i + 2, "\\)"))
+        } else if rest.starts_with("\\[") {
+            Some(find_after(code, i + 2, "\\]"))
+        } else if rest.starts_with("$$") {
+            Some(find_after(code, i + 2, "$$"))
+        } else if rest.starts_with('$') {
+            Some(find_after(code, i + 1, "$"))
+        } else if rest.starts_with('\\') {
+            Some(command_end(code, i))
+        } else {
+            None
+        };
+
+        if let Some(end) = verbatim_end {
+            plan_text(&code[prose_start..i], prose_start, context, edits)?;
+            i = end;
+            prose_start = i;
+        } else if rest.starts_with('%') {
+            plan_text(&code[prose_start..i], prose_start, context, edits)?;
+            let end = rest.find('\n').map_or(code.len(), |offset| i + offset);
+            plan_text(&code[i + 1..end], i + 1, context, edits)?;
+            i = end;
+            prose_start = i;
+        } else {
+            i += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    plan_text(&code[prose_start..], prose_start, context, edits)
+}
+
+/// The end of the verbatim part of the command starting at `start`.
+fn command_end(code: &str, start: usize) -> usize {
+    let rest = &code[start + 1..];
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    if name_len == 0 {
+        // An escaped character like `\%`, or a line break with an optional length.
+        let end = start + 1 + rest.chars().next().map_or(0, char::len_utf8);
+        if rest.starts_with('\\') {
+            return group_end(code, end, '[', ']');
+        }
+        return end;
+    }
+
+    let name = &rest[..name_len];
+    let mut end = start + 1 + name_len;
+    if code[end..].starts_with('*') {
+        end += 1;
+    }
+
+    if name == "verb" {
+        return match code[end..].chars().next() {
+            Some(delimiter) => {
+                let start = end + delimiter.len_utf8();
+                code[start..]
+                    .find(delimiter)
+                    .map_or(code.len(), |offset| start + offset + delimiter.len_utf8())
+            }
+            None => end,
+        };
+    }
+
+    if name == "begin" {
+        let name_end = group_end(code, end, '{', '}');
+        let environment = code[end..name_end]
+            .trim_start_matches('{')
+            .trim_end_matches('}')
+            .trim_end_matches('*');
+        if VERBATIM_ENVIRONMENTS.contains(&environment) {
+            let closing = format!("\\end{}", &code[end..name_end]);
+            return find_after(code, name_end, &closing);
+        }
+    }
+
+    // Optional arguments are kept, as they usually contain settings.
+    loop {
+        let next = group_end(code, end, '[', ']');
+        if next == end {
+            break;
+        }
+        end = next;
+    }
+    if TEXT_COMMANDS.contains(&name) {
+        return end;
+    }
+
+    loop {
+        let next = group_end(code, end, '{', '}');
+        let next = group_end(code, next, '[', ']');
+        if next == end {
+            break;
+        }
+        end = next;
+    }
+    end
+}
+
+/// The end of the group opened at `start`, or `start` if there is no group.
+fn group_end(code: &str, start: usize, open: char, close: char) -> usize {
+    if !code[start..].starts_with(open) {
+        return start;
+    }
+
+    let mut depth = 0usize;
+    let mut braces = 0usize;
+    let mut chars = code[start..].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '{' if open != '{' => braces += 1,
+            '}' if open != '{' => braces = braces.saturating_sub(1),
+            _ if braces > 0 => {}
+            _ if c == open => depth += 1,
+            _ if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + offset + c.len_utf8();
+                }
+            }
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+/// The position right after the next unescaped `pattern` at or after `start`.
+fn find_after(code: &str, start: usize, pattern: &str) -> usize {
+    let mut i = start;
+    while let Some(offset) = code[i..].find(pattern) {
+        let found = i + offset;
+        let backslashes = code[..found]
+            .bytes()
+            .rev()
+            .take_while(|&b| b == b'\\')
+            .count();
+        if pattern.starts_with('\\') || backslashes % 2 == 0 {
+            return found + pattern.len();
+        }
+        i = found + pattern.len();
+    }
+    code.len()
+}
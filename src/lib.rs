@@ -0,0 +1,1636 @@
+//! Replace all words in a typst document with random garbage.
+//!
+//! The simplest entry point is [`mutilate_str`], which parses a document and returns the
+//! mutilated source. For more control, build a [`Context`] once and call [`mutilate`] on
+//! individual syntax nodes.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::{self, Write},
+    ops::Range,
+    sync::Arc,
+};
+
+use ecow::{EcoString, EcoVec};
+use hypher::Lang;
+use typst_syntax::{ast, LinkedNode, SyntaxError, SyntaxKind, SyntaxNode};
+use unicode_normalization::UnicodeNormalization;
+use unicode_script::UnicodeScript;
+
+mod audit;
+mod bundled;
+mod cache;
+mod corpus;
+mod dates;
+#[cfg(feature = "detect")]
+mod detect;
+mod edit;
+mod encoding;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod filler;
+mod filter;
+mod guess;
+mod identifiers;
+mod latex;
+mod manifest;
+mod map;
+mod markdown;
+mod metadata;
+mod metrics;
+mod morphology;
+mod names;
+mod options;
+mod package;
+mod patterns;
+mod pii;
+mod placeholders;
+#[cfg(feature = "python")]
+mod python;
+mod quotes;
+mod redact;
+mod reduce;
+mod regenerate;
+mod region;
+#[cfg(feature = "remote")]
+pub mod remote;
+mod replacer;
+mod scan;
+mod scripts;
+mod sections;
+mod selector;
+#[cfg(feature = "serve")]
+pub mod serve;
+mod session;
+mod shrink;
+mod shuffle;
+mod stream;
+mod templates;
+mod undo;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod wordlist;
+
+pub use hypher;
+pub use typst_syntax;
+
+#[cfg(feature = "detect")]
+pub use crate::detect::detect_language;
+pub use crate::{
+    audit::{json_string, AuditEntry, AuditLog, AuditRecord},
+    bundled::bundled_wordlist,
+    cache::default_cache_dir,
+    corpus::WordCounter,
+    edit::{apply_edits, changed_line, convert_newlines, preserve_lines, Edit},
+    encoding::Encoding,
+    filter::{all_kinds, kind_name, parse_kind, KindFilter, NodeAction, NodeFilter},
+    manifest::{content_hash, Manifest},
+    map::ReplacementMap,
+    metrics::Metrics,
+    options::{
+        parse_language, supported_languages, Format, Mode, MutilateOptions, Newline, Normalization,
+        NumberMode, Quotes, RawMode, Segmentation, Shuffle, Strategy, WordlistSource,
+    },
+    package::{project_files, Package},
+    patterns::Patterns,
+    reduce::reduce,
+    replacer::{Replacer, WordInfo, WordlistReplacer},
+    sections::HeadingPattern,
+    selector::Selector,
+    session::Session,
+    stream::mutilate_stream,
+    undo::{record_undo, restore_undo, undo_path},
+};
+
+/// The result type used throughout this crate.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error that occurred while mutilating a document.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the wordlist or writing the output failed.
+    Io(io::Error),
+    /// The document could not be parsed.
+    Syntax(Vec<SyntaxError>),
+    /// The requested combination of settings is not supported.
+    Unsupported(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Syntax(errors) => {
+                let messages: Vec<&str> =
+                    errors.iter().map(|error| error.message.as_str()).collect();
+                let noun = if errors.len() == 1 { "error" } else { "errors" };
+                write!(f, "{} syntax {noun}: {}", errors.len(), messages.join("; "))
+            }
+            Error::Unsupported(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Syntax(_) | Error::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Parse `code` in the configured [`Format`] and mutilate it.
+pub fn mutilate_str(code: &str, options: &MutilateOptions) -> Result<String> {
+    mutilate_str_with(code, &mut Context::new(options)?)
+}
+
+/// Parse `code` in the configured [`Format`] and mutilate it using an existing context.
+pub fn mutilate_str_with(code: &str, context: &mut Context) -> Result<String> {
+    let edits = plan_str_with(code, context)?;
+    Ok(apply_edits(code, &edits))
+}
+
+/// Parse `code` in the configured [`Format`] and compute the edits that mutilate it.
+///
+/// The edits are sorted and refer to byte ranges in `code`.
+pub fn plan_str_with(code: &str, context: &mut Context) -> Result<Vec<Edit>> {
+    let audit_start = context.audit.as_ref().map_or(0, Vec::len);
+    let mut edits = Vec::new();
+    match context.format {
+        Format::Typst => {
+            let syntax = parse(code, context.mode)?;
+            return Ok(plan_document(&syntax, code, context)?);
+        }
+        Format::Plain => plan_text(code, 0, context, &mut edits)?,
+        Format::Markdown => markdown::plan_markdown(code, context, &mut edits)?,
+        Format::Latex => latex::plan_latex(code, context, &mut edits)?,
+    }
+    let whole = 0..code.len();
+    let edits = fake_identifiers(code, None, std::slice::from_ref(&whole), context, edits);
+    let edits = scrub(code, None, std::slice::from_ref(&whole), context, edits);
+    let edits = shift_dates(code, None, std::slice::from_ref(&whole), context, edits);
+    let edits = match &context.changed_lines {
+        Some(lines) => edit::within_lines(code, lines, edits),
+        None => edits,
+    };
+    context.finish_audit(audit_start, &edits);
+    Ok(edits)
+}
+
+/// Parse a typst document in the given mode, failing if it contains syntax errors.
+fn parse(code: &str, mode: Mode) -> Result<SyntaxNode> {
+    let syntax = match mode {
+        Mode::Markup => typst_syntax::parse(code),
+        Mode::Code => typst_syntax::parse_code(code),
+        Mode::Math => typst_syntax::parse_math(code),
+    };
+    let errors = syntax.errors();
+    if !errors.is_empty() {
+        return Err(Error::Syntax(errors));
+    }
+    Ok(syntax)
+}
+
+/// Counts of the words a [`Context`] replaced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of words replaced, not counting numbers.
+    pub words: usize,
+    /// The number of those words replaced with random characters because the other strategies
+    /// found no fitting wordlist entry.
+    pub fallbacks: usize,
+}
+
+/// The state required to mutilate documents, including the loaded wordlist.
+pub struct Context {
+    aggressive: bool,
+    alt_text: bool,
+    preserve_suffixes: bool,
+    segmentation: Segmentation,
+    normalize_output: Normalization,
+    /// The languages words may be guessed to be in, if guessing is enabled.
+    word_languages: Option<Vec<Lang>>,
+    language: Lang,
+    format: Format,
+    mode: Mode,
+    protected: HashSet<EcoString>,
+    patterns: Option<Arc<Patterns>>,
+    identifiers: identifiers::Faker,
+    scrubber: Option<pii::Scrubber>,
+    dates: Option<dates::DateShifter>,
+    shrinker: Option<shrink::Shrinker>,
+    shuffler: Option<shuffle::Shuffler>,
+    quotes: Option<Quotes>,
+    sections: Vec<HeadingPattern>,
+    excluded_sections: Vec<HeadingPattern>,
+    selected: Vec<Selector>,
+    kept: Vec<Selector>,
+    /// The package whose internal bindings are renamed, if a file of a package is mutilated.
+    package: Option<Package>,
+    /// The lines of the next documents that are mutilated, counted from 1, or `None` for all.
+    changed_lines: Option<Vec<Range<usize>>>,
+    /// Replaces raw text with filler, if it isn't replaced like prose.
+    filler: Option<filler::Filler>,
+    regenerator: Option<regenerate::Regenerator>,
+    sentences: Option<templates::SentenceGenerator>,
+    scripts: scripts::ScriptFiller,
+    /// The generated paragraphs and sentences of the document being planned, which override
+    /// all other edits in them.
+    generated: Vec<Edit>,
+    /// Decides which words are replaced if only names are.
+    names: Option<names::NameDetector>,
+    scrub_metadata: bool,
+    /// The replacements of the names of authors if only names are not replaced.
+    authors: Option<names::NameDetector>,
+    /// The hyphenation of every word seen so far, as words tend to repeat.
+    hyphenations: HashMap<(Lang, EcoString), EcoVec<u8>>,
+    replacer: Box<dyn Replacer>,
+    filter: Option<Box<dyn NodeFilter>>,
+    /// The records of the replacements, if they are audited.
+    audit: Option<Vec<AuditRecord>>,
+    /// The kind of the node that is being mutilated.
+    node_kind: Option<SyntaxKind>,
+    /// How the last replacement was chosen, for audit records.
+    last_choice: &'static str,
+    stats: Stats,
+}
+
+impl Context {
+    /// Build a context that uses the built-in [`WordlistReplacer`].
+    ///
+    /// Fails with [`Error::Unsupported`] if the share of [`MutilateOptions::shrink`] is not
+    /// between 0 and 1.
+    pub fn new(options: &MutilateOptions) -> Result<Self> {
+        if let Some(fraction) = options
+            .shrink
+            .filter(|fraction| !(0.0..=1.0).contains(fraction))
+        {
+            return Err(Error::Unsupported(format!(
+                "the share of parts to drop must be between 0 and 1, not {fraction}"
+            )));
+        }
+        Ok(Self::with_replacer(
+            options,
+            WordlistReplacer::new(options)?,
+        ))
+    }
+
+    /// Build a context that chooses replacements using a custom [`Replacer`].
+    pub fn with_replacer(options: &MutilateOptions, replacer: impl Replacer + 'static) -> Self {
+        Self::with_boxed_replacer(options, Box::new(replacer))
+    }
+
+    /// Build a context for `options` that keeps the replacer of this one, with the wordlists
+    /// it loaded, and resets everything else as if it were new.
+    ///
+    /// The wordlists and the language of the words of shared wordlists don't change, so only
+    /// options that don't concern them should differ from those the replacer was built with.
+    pub fn reuse(self, options: &MutilateOptions) -> Self {
+        let mut replacer = self.replacer;
+        replacer.reset(options);
+        Self::with_boxed_replacer(options, replacer)
+    }
+
+    fn with_boxed_replacer(options: &MutilateOptions, replacer: Box<dyn Replacer>) -> Self {
+        Context {
+            aggressive: options.aggressive,
+            alt_text: options.alt_text,
+            preserve_suffixes: options.preserve_suffixes,
+            segmentation: options.segmentation,
+            normalize_output: options.normalize_output,
+            word_languages: options.guess_word_languages.then(|| {
+                let mut languages = vec![options.language];
+                for language in options
+                    .wordlists
+                    .iter()
+                    .filter_map(|(language, _)| *language)
+                {
+                    if !languages.contains(&language) {
+                        languages.push(language);
+                    }
+                }
+                if !languages.contains(&Lang::English) {
+                    languages.push(Lang::English);
+                }
+                languages
+            }),
+            language: options.language,
+            format: options.format,
+            mode: options.mode,
+            protected: options.protected.clone(),
+            patterns: options.hyphenation_patterns.clone(),
+            identifiers: identifiers::Faker::new(options.seed),
+            scrubber: options.scrub_pii.then(|| pii::Scrubber::new(options.seed)),
+            dates: options.shift_dates.then(|| {
+                let days = options
+                    .date_offset
+                    .unwrap_or_else(|| random_date_offset(options.seed));
+                dates::DateShifter::new(days, options.language)
+            }),
+            shrinker: options
+                .shrink
+                .map(|fraction| shrink::Shrinker::new(fraction, options.seed)),
+            shuffler: options
+                .shuffle
+                .map(|unit| shuffle::Shuffler::new(unit, options.seed)),
+            quotes: options.quotes,
+            sections: options.sections.clone(),
+            excluded_sections: options.excluded_sections.clone(),
+            selected: options.selected.clone(),
+            kept: options.kept.clone(),
+            package: None,
+            changed_lines: None,
+            filler: (options.raw == RawMode::Filler && !options.names_only)
+                .then(|| filler::Filler::new(options.seed)),
+            regenerator: (options.regenerate_paragraphs && !options.names_only)
+                .then(|| regenerate::Regenerator::new(options.seed)),
+            sentences: (options.strategies.contains(&Strategy::Sentences) && !options.names_only)
+                .then(|| templates::SentenceGenerator::new(options.seed)),
+            scripts: scripts::ScriptFiller::new(options.seed),
+            generated: Vec::new(),
+            names: options
+                .names_only
+                .then(|| names::NameDetector::new(&options.names)),
+            scrub_metadata: options.scrub_metadata,
+            authors: None,
+            hyphenations: HashMap::new(),
+            replacer,
+            filter: None,
+            audit: None,
+            node_kind: None,
+            last_choice: "custom",
+            stats: Stats::default(),
+        }
+    }
+
+    /// A random number generator for one part of the mutilation, like scrubbing, derived from
+    /// the seed and a tag of its own, so that the parts don't affect each other's choices.
+    ///
+    /// Without a seed, the generator is seeded from the operating system.
+    pub(crate) fn substream(seed: Option<u64>, tag: u64) -> rand_xoshiro::Xoshiro256PlusPlus {
+        use rand::SeedableRng;
+        match seed {
+            Some(seed) => rand_xoshiro::Xoshiro256PlusPlus::seed_from_u64(seed ^ tag),
+            None => rand_xoshiro::Xoshiro256PlusPlus::from_rng(rand::thread_rng()).unwrap(),
+        }
+    }
+
+    /// Change how documents are interpreted, for documents of several formats.
+    pub fn set_format(&mut self, format: Format) {
+        self.format = format;
+    }
+
+    /// Change how typst documents are parsed.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// Rename the internal bindings of a package in the documents, which are the files of the
+    /// package, and keep the paths of the files they load.
+    pub fn set_package(&mut self, package: Package) {
+        self.package = Some(package);
+    }
+
+    /// Only mutilate the given lines of the next documents, counted from 1, like those that
+    /// changed since a revision, and keep the others as they are; `None` mutilates all lines.
+    ///
+    /// Replacements that touch one of the lines are kept whole, even if they span other lines.
+    pub fn set_changed_lines(&mut self, lines: Option<Vec<Range<usize>>>) {
+        self.changed_lines = lines;
+    }
+
+    /// Decide per node what happens to it instead of only relying on the built-in rules.
+    pub fn set_filter(&mut self, filter: impl NodeFilter + 'static) {
+        self.filter = Some(Box::new(filter));
+    }
+
+    /// The number of days dates are moved by, if they are shifted.
+    pub fn date_offset(&self) -> Option<i64> {
+        self.dates.as_ref().map(dates::DateShifter::days)
+    }
+
+    /// The ranges of the last planned typst document whose edits move another part of it there
+    /// rather than replace words, with [`MutilateOptions::shuffle`].
+    pub fn moved(&self) -> &[Range<usize>] {
+        self.shuffler
+            .as_ref()
+            .map_or(&[], |shuffler| shuffler.moved())
+    }
+
+    /// How text is split into words.
+    pub fn segmentation(&self) -> Segmentation {
+        self.segmentation
+    }
+
+    /// Counts of the words replaced so far.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Record how every replacement is chosen, to be retrieved with [`take_audit`].
+    ///
+    /// [`take_audit`]: Self::take_audit
+    pub fn enable_audit(&mut self) {
+        self.audit.get_or_insert_with(Vec::new);
+    }
+
+    /// Take the records of the replacements planned since the last call, in the order of the
+    /// planned documents and edits.
+    pub fn take_audit(&mut self) -> Vec<AuditRecord> {
+        self.audit.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Record the replacement of a range in the node currently being mutilated.
+    fn record(&mut self, range: Range<usize>, kind: Option<SyntaxKind>, strategy: &'static str) {
+        if let Some(audit) = &mut self.audit {
+            audit.push(AuditRecord {
+                range,
+                kind,
+                strategy,
+            });
+        }
+    }
+
+    /// Whether a generated paragraph or sentence covers an offset.
+    fn is_generated(&self, offset: usize) -> bool {
+        let i = self
+            .generated
+            .partition_point(|edit| edit.range.start <= offset);
+        i > 0 && offset < self.generated[i - 1].range.end
+    }
+
+    /// Keep only the records since `start` that belong to one of the final `edits`, of the
+    /// last override where several edits replaced the same range.
+    fn finish_audit(&mut self, start: usize, edits: &[Edit]) {
+        let Some(audit) = &mut self.audit else {
+            return;
+        };
+        let mut records = audit.split_off(start);
+        records.reverse();
+        records.sort_by_key(|record| record.range.start);
+        records.dedup_by_key(|record| record.range.clone());
+        records.retain(|record| {
+            edits
+                .binary_search_by_key(&record.range.start, |edit| edit.range.start)
+                .is_ok_and(|i| edits[i].range == record.range)
+        });
+        audit.extend(records);
+    }
+}
+
+/// Mutilate a syntax node and all of its children, writing the result to `output`.
+pub fn mutilate<W: Write>(
+    syntax: &SyntaxNode,
+    context: &mut Context,
+    output: &mut W,
+) -> io::Result<()> {
+    let edits = plan(syntax, context)?;
+    write!(
+        output,
+        "{}",
+        apply_edits(&syntax.clone().into_text(), &edits)
+    )
+}
+
+/// Compute the edits that mutilate a syntax node, relative to the start of the node.
+pub fn plan(syntax: &SyntaxNode, context: &mut Context) -> io::Result<Vec<Edit>> {
+    plan_document(syntax, &syntax.clone().into_text(), context)
+}
+
+/// Compute the edits that mutilate a typst document whose source is `code`, including the
+/// regions marked for redaction and scrubbed identifiers.
+fn plan_document(syntax: &SyntaxNode, code: &str, context: &mut Context) -> io::Result<Vec<Edit>> {
+    let audit_start = context.audit.as_ref().map_or(0, Vec::len);
+    let mut edits = Vec::new();
+    plan_root(syntax, context, &mut edits)?;
+    let root = LinkedNode::new(syntax);
+    let mut regions = Vec::new();
+    text_regions(&root, &mut regions);
+    let edits = redact(code, &root, &regions, context, edits)?;
+    let edits = scrub_authors(code, &root, &regions, context, edits)?;
+    let edits = fake_identifiers(code, Some(&root), &regions, context, edits);
+    let edits = scrub(code, Some(&root), &regions, context, edits);
+    let edits = shift_dates(code, Some(&root), &regions, context, edits);
+    let generated = std::mem::take(&mut context.generated);
+    let edits = edit::override_edits(edits, generated);
+    let edits = match context.quotes {
+        Some(quotes) => quotes::filter(&root, quotes, edits),
+        None => edits,
+    };
+    let edits = if context.sections.is_empty() && context.excluded_sections.is_empty() {
+        edits
+    } else {
+        sections::filter(&root, &context.sections, &context.excluded_sections, edits)
+    };
+    let edits = if context.selected.is_empty() && context.kept.is_empty() {
+        edits
+    } else {
+        selector::filter(&root, &context.selected, &context.kept, edits)
+    };
+    let edits = match &context.changed_lines {
+        Some(lines) => edit::within_lines(code, lines, edits),
+        None => edits,
+    };
+    let edits = match &context.package {
+        Some(package) => {
+            let mut renames = Vec::new();
+            package.plan(&root, &mut renames);
+            for rename in &renames {
+                let kind = root.leaf_at(rename.range.start + 1).map(|leaf| leaf.kind());
+                context.record(rename.range.clone(), kind, "rename");
+            }
+            edit::override_edits(edits, renames)
+        }
+        None => edits,
+    };
+    let edits = match &mut context.shrinker {
+        Some(shrinker) => edit::override_edits(edits, shrinker.plan(&root)),
+        None => edits,
+    };
+    context.finish_audit(audit_start, &edits);
+    // The records of moved words still describe their original ranges.
+    let edits = match &mut context.shuffler {
+        Some(shuffler) => shuffler.plan(&root, code, edits),
+        None => edits,
+    };
+    Ok(edits)
+}
+
+/// Replace every word in the parts of the text `regions` that are marked for redaction,
+/// overriding the planned `edits` there, and keep the markers.
+fn redact(
+    code: &str,
+    root: &LinkedNode,
+    regions: &[Range<usize>],
+    context: &mut Context,
+    mut edits: Vec<Edit>,
+) -> io::Result<Vec<Edit>> {
+    let annotations = redact::annotations(root, code);
+    if annotations.markers.is_empty() {
+        return Ok(edits);
+    }
+
+    let overlaps = |a: &Range<usize>, b: &Range<usize>| a.start < b.end && b.start < a.end;
+    edits.retain(|edit| {
+        !annotations
+            .markers
+            .iter()
+            .any(|marker| overlaps(marker, &edit.range))
+    });
+
+    let mut redacted = Vec::new();
+    for region in regions {
+        for marked in &annotations.regions {
+            let range = region.start.max(marked.start)..region.end.min(marked.end);
+            if range.start >= range.end {
+                continue;
+            }
+            for (start, word) in scan::Words::new(&code[range.clone()], context.segmentation) {
+                let start = range.start + start;
+                let range = start..start + word.len();
+                let replacement = choose_replacement(word, context)?;
+                let kind = root.leaf_at(start + 1).map(|leaf| leaf.kind());
+                context.record(range.clone(), kind, context.last_choice);
+                redacted.push(Edit { range, replacement });
+            }
+        }
+    }
+    Ok(edit::override_edits(edits, redacted))
+}
+
+/// Replace the names in the parts of the text `regions` with information about the authors,
+/// overriding the planned `edits` there, if metadata is scrubbed.
+fn scrub_authors(
+    code: &str,
+    root: &LinkedNode,
+    regions: &[Range<usize>],
+    context: &mut Context,
+    edits: Vec<Edit>,
+) -> io::Result<Vec<Edit>> {
+    if !context.scrub_metadata {
+        return Ok(edits);
+    }
+    let mut authors = Vec::new();
+    metadata::author_regions(root, &mut authors);
+
+    let mut scrubbed = Vec::new();
+    for region in regions {
+        for author in &authors {
+            let range = region.start.max(author.start)..region.end.min(author.end);
+            if range.start >= range.end {
+                continue;
+            }
+            for (start, word) in scan::Words::new(&code[range.clone()], context.segmentation) {
+                let start = range.start + start;
+                let range = start..start + word.len();
+                if let Some(replacement) = replace_author(word, context)? {
+                    let kind = root.leaf_at(start + 1).map(|leaf| leaf.kind());
+                    context.record(range.clone(), kind, context.last_choice);
+                    scrubbed.push(Edit { range, replacement });
+                }
+            }
+        }
+    }
+    Ok(edit::override_edits(edits, scrubbed))
+}
+
+/// Mutilate a `typst.toml` package manifest, replacing the names of the authors like the
+/// names in documents, the description and the URLs of the repository and homepage.
+pub fn mutilate_package_manifest(toml: &str, context: &mut Context) -> Result<String> {
+    let audit_start = context.audit.as_ref().map_or(0, Vec::len);
+    let mut edits = Vec::new();
+    for (range, field) in metadata::package_fields(toml) {
+        let value = &toml[range.clone()];
+        let start = match field {
+            metadata::Field::Url => value.find("://").map_or(0, |i| i + 3),
+            _ => 0,
+        };
+        for (offset, word) in scan::Words::new(&value[start..], context.segmentation) {
+            let offset = range.start + start + offset;
+            let replacement = match field {
+                metadata::Field::Description => Some(choose_replacement(word, context)?),
+                _ => replace_author(word, context)?,
+            };
+            if let Some(replacement) = replacement {
+                let range = offset..offset + word.len();
+                context.record(range.clone(), None, context.last_choice);
+                edits.push(Edit { range, replacement });
+            }
+        }
+    }
+    context.finish_audit(audit_start, &edits);
+    Ok(apply_edits(toml, &edits))
+}
+
+fn plan_root(syntax: &SyntaxNode, context: &mut Context, edits: &mut Vec<Edit>) -> io::Result<()> {
+    let language = context.language;
+    let result = plan_linked(&LinkedNode::new(syntax), context, edits);
+    context.language = language;
+    result
+}
+
+fn plan_linked(node: &LinkedNode, context: &mut Context, edits: &mut Vec<Edit>) -> io::Result<()> {
+    let block = matches!(
+        node.kind(),
+        SyntaxKind::Parbreak
+            | SyntaxKind::Heading
+            | SyntaxKind::ListItem
+            | SyntaxKind::EnumItem
+            | SyntaxKind::TermItem
+    );
+    if let Some(names) = context.names.as_mut().filter(|_| block) {
+        names.block_boundary();
+    }
+    let result = plan_action(node, context, edits);
+    if let Some(names) = context.names.as_mut().filter(|_| block) {
+        names.block_boundary();
+    }
+    result
+}
+
+fn plan_action(node: &LinkedNode, context: &mut Context, edits: &mut Vec<Edit>) -> io::Result<()> {
+    let action = context
+        .filter
+        .as_mut()
+        .and_then(|filter| filter.filter(node))
+        .unwrap_or_else(|| default_action(node, context));
+    match action {
+        NodeAction::Copy => Ok(()),
+        NodeAction::Descend => plan_children(node, context, edits),
+        NodeAction::Mutilate => {
+            let kind = context.node_kind.replace(node.kind());
+            let result = plan_node(node, context, edits);
+            context.node_kind = kind;
+            result
+        }
+    }
+}
+
+/// Plan the children of a node, following changes of the text language.
+fn plan_children(
+    node: &LinkedNode,
+    context: &mut Context,
+    edits: &mut Vec<Edit>,
+) -> io::Result<()> {
+    let language = context.language;
+    if let Some(language) = region::call_language(node) {
+        context.language = language;
+    }
+    let children: Vec<LinkedNode> = node.children().collect();
+    let generating = context.regenerator.is_some() || context.sentences.is_some();
+    let mut paragraphs = match node.kind() {
+        SyntaxKind::Markup if generating => regenerate::paragraphs(node, context.segmentation),
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .peekable();
+    let text = paragraphs
+        .peek()
+        .map(|_| node.get().clone().into_text())
+        .unwrap_or_default();
+    let mut regenerated = None;
+    for (i, child) in children.iter().enumerate() {
+        if let Some(paragraph) = paragraphs.next_if(|paragraph| paragraph.start == child.offset()) {
+            let start = paragraph.start - node.offset();
+            if context.regenerator.is_some() {
+                let original = &text[start..start + paragraph.len()];
+                regenerate_paragraph(original, paragraph.clone(), context)?;
+                regenerated = Some(paragraph);
+            } else {
+                let count = children[i..]
+                    .iter()
+                    .take_while(|child| child.offset() < paragraph.end)
+                    .count();
+                generate_sentences(&text, node.offset(), &children[i..i + count], context);
+            }
+        }
+        if regenerated
+            .as_ref()
+            .is_some_and(|paragraph| child.offset() < paragraph.end)
+        {
+            continue;
+        }
+        plan_linked(child, context, edits)?;
+        if let Some(language) = region::set_rule_language(child) {
+            context.language = language;
+        }
+    }
+
+    // Set rules apply until the end of the enclosing block, which contains the markup or code.
+    if !matches!(node.kind(), SyntaxKind::Markup | SyntaxKind::Code) {
+        context.language = language;
+    }
+    Ok(())
+}
+
+/// Replace a paragraph of running text with a generated one with as many words, keeping
+/// protected words.
+///
+/// The words are replaced by length only, without hyphenating them.
+fn regenerate_paragraph(text: &str, range: Range<usize>, context: &mut Context) -> io::Result<()> {
+    let Some(mut regenerator) = context.regenerator.take() else {
+        return Ok(());
+    };
+    let generated = regenerator.generate(text, context.segmentation, |word| {
+        if context.protected.contains(word) {
+            return Ok(word.into());
+        }
+        let info = WordInfo {
+            length: scan::char_count(word),
+            hyphenation: &[],
+            numeric: scan::is_numeric(word),
+            language: context.language,
+        };
+        replace_with(word, &info, context)
+    });
+    context.regenerator = Some(regenerator);
+    context.record(range.clone(), Some(SyntaxKind::Markup), "regenerate");
+    context.generated.push(Edit {
+        range,
+        replacement: generated?.into(),
+    });
+    Ok(())
+}
+
+/// Replace the words of the sentences of plain text in a paragraph with generated sentences of
+/// as many words, if there are templates for the language.
+///
+/// `text` is the text of the markup, which starts at `offset`. The punctuation before the
+/// first word and after the last one of a sentence is kept, as are the line breaks between
+/// words.
+fn generate_sentences(text: &str, offset: usize, paragraph: &[LinkedNode], context: &mut Context) {
+    if !templates::SentenceGenerator::supports(context.language) {
+        return;
+    }
+    for sentence in shuffle::sentences(paragraph) {
+        let plain = paragraph
+            .iter()
+            .filter(|node| {
+                node.offset() < sentence.end && sentence.start < node.offset() + node.len()
+            })
+            .all(|node| {
+                matches!(
+                    node.kind(),
+                    SyntaxKind::Text | SyntaxKind::Space | SyntaxKind::SmartQuote
+                )
+            });
+        let original = &text[sentence.start - offset..sentence.end - offset];
+        let words: Vec<(usize, &str)> = scan::Words::new(original, context.segmentation).collect();
+        let (Some(first), Some(last)) = (words.first(), words.last()) else {
+            continue;
+        };
+        if !plain
+            || words
+                .iter()
+                .any(|(_, word)| context.protected.contains(*word))
+        {
+            continue;
+        }
+        let avoid: Vec<&str> = words.iter().map(|&(_, word)| word).collect();
+        let capitalize = first.1.starts_with(char::is_uppercase);
+        let language = context.language;
+        let Some(generated) = context
+            .sentences
+            .as_mut()
+            .and_then(|sentences| sentences.generate(words.len(), language, capitalize, &avoid))
+        else {
+            continue;
+        };
+        let mut replacement = String::new();
+        for (i, word) in generated.iter().enumerate() {
+            if i > 0 {
+                let gap = &original[words[i - 1].0 + words[i - 1].1.len()..words[i].0];
+                replacement.push_str(if gap.trim().is_empty() { gap } else { " " });
+            }
+            replacement.push_str(word);
+        }
+        let start = sentence.start + first.0;
+        let range = start..sentence.start + last.0 + last.1.len();
+        context.stats.words += words.len();
+        context.record(
+            range.clone(),
+            Some(SyntaxKind::Text),
+            Strategy::Sentences.name(),
+        );
+        context.generated.push(Edit {
+            range,
+            replacement: replacement.into(),
+        });
+    }
+}
+
+/// The ranges of a typst document with text, links, strings and comments, whose words may be
+/// replaced.
+///
+/// Consecutive text and spaces in markup form a single range, so that phrases spanning
+/// several text nodes, like phone numbers, can be found.
+fn text_regions(node: &LinkedNode, regions: &mut Vec<Range<usize>>) {
+    let text = node.text();
+    let inner = match node.kind() {
+        SyntaxKind::Text | SyntaxKind::Link => 0..text.len(),
+        SyntaxKind::LineComment => 2..text.len(),
+        SyntaxKind::BlockComment => block_comment_body(text),
+        SyntaxKind::Str => 1..text.len() - 1,
+        SyntaxKind::Markup => {
+            let mut run: Option<Range<usize>> = None;
+            for child in node.children() {
+                if matches!(child.kind(), SyntaxKind::Text | SyntaxKind::Space) {
+                    let range = child.range();
+                    match &mut run {
+                        Some(run) => run.end = range.end,
+                        None => run = Some(range),
+                    }
+                    continue;
+                }
+                regions.extend(run.take());
+                text_regions(&child, regions);
+            }
+            regions.extend(run);
+            return;
+        }
+        _ => {
+            for child in node.children() {
+                text_regions(&child, regions);
+            }
+            return;
+        }
+    };
+    let offset = node.offset();
+    regions.push(offset + inner.start..offset + inner.end);
+}
+
+/// The action taken on a node if no filter overrides it.
+fn default_action(node: &LinkedNode, context: &Context) -> NodeAction {
+    let syntax = node.get();
+    match syntax.kind() {
+        SyntaxKind::Text
+        | SyntaxKind::LineComment
+        | SyntaxKind::BlockComment
+        | SyntaxKind::Raw
+        | SyntaxKind::Link => NodeAction::Mutilate,
+        SyntaxKind::Str if raw_body(node) => NodeAction::Mutilate,
+        // Like the language tag of raw blocks, the options of `raw` calls are kept.
+        SyntaxKind::Str if configures_raw(node) => NodeAction::Copy,
+        SyntaxKind::Str if context.package.is_some() && loads(node) => NodeAction::Copy,
+        SyntaxKind::Str
+            if (context.aggressive && !cites(node)) || (context.alt_text && describes(node)) =>
+        {
+            NodeAction::Mutilate
+        }
+        SyntaxKind::ModuleInclude | SyntaxKind::ModuleImport => NodeAction::Copy,
+        _ if syntax.children().next().is_some() => NodeAction::Descend,
+        _ => NodeAction::Copy,
+    }
+}
+
+/// Whether a string is the value of an `alt` or `description` argument, which describes the
+/// content of an element.
+fn describes(node: &LinkedNode) -> bool {
+    node.parent()
+        .and_then(|parent| parent.cast::<ast::Named>())
+        .is_some_and(|named| matches!(named.name().as_str(), "alt" | "description"))
+}
+
+/// Whether a string is the text of a `raw` call, which is treated like a raw block.
+pub(crate) fn raw_body(node: &LinkedNode) -> bool {
+    node.kind() == SyntaxKind::Str && node.parent().is_some_and(calls_raw)
+}
+
+/// Whether a string is the value of a named argument of a `raw` call, like its `lang`.
+fn configures_raw(node: &LinkedNode) -> bool {
+    node.parent()
+        .filter(|parent| parent.kind() == SyntaxKind::Named)
+        .and_then(LinkedNode::parent)
+        .is_some_and(calls_raw)
+}
+
+/// Whether a node is the arguments of a `raw` call.
+fn calls_raw(args: &LinkedNode) -> bool {
+    args.kind() == SyntaxKind::Args
+        && args
+            .parent()
+            .and_then(|call| call.cast::<ast::FuncCall>())
+            .is_some_and(
+                |call| matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "raw"),
+            )
+}
+
+/// Whether a string is an argument of a call that loads a file, like the path of an `image`,
+/// which the files of a package need to keep to compile.
+fn loads(node: &LinkedNode) -> bool {
+    let call = node
+        .parent()
+        .filter(|parent| parent.kind() == SyntaxKind::Args)
+        .and_then(LinkedNode::parent)
+        .and_then(|call| call.cast::<ast::FuncCall>());
+    call.is_some_and(|call| {
+        matches!(
+            call.callee(),
+            ast::Expr::Ident(ident) if matches!(
+                ident.as_str(),
+                "image" | "read" | "json" | "csv" | "yaml" | "toml" | "xml" | "cbor" | "plugin"
+            )
+        )
+    })
+}
+
+/// Whether a string is an argument of a call that refers to the bibliography or labels, like
+/// the key of a `cite` call or the path of a `bibliography`, which must be kept for the
+/// document to compile.
+///
+/// Of the named arguments, only `form` and `style` count, so that supplements and titles are
+/// replaced like any other text, as is content in the arguments.
+fn cites(node: &LinkedNode) -> bool {
+    let mut named = None;
+    let mut ancestor = node.parent();
+    while let Some(node) = ancestor {
+        match node.kind() {
+            SyntaxKind::Named => {
+                let arg: ast::Named = node.cast().unwrap();
+                named = Some(arg.name());
+                ancestor = node.parent();
+            }
+            SyntaxKind::FuncCall => {
+                let call: ast::FuncCall = node.cast().unwrap();
+                return matches!(
+                    call.callee(),
+                    ast::Expr::Ident(ident)
+                        if matches!(ident.as_str(), "cite" | "ref" | "label" | "bibliography")
+                ) && named.is_none_or(|name| matches!(name.as_str(), "form" | "style"));
+            }
+            SyntaxKind::ContentBlock | SyntaxKind::Markup => return false,
+            _ => ancestor = node.parent(),
+        }
+    }
+    false
+}
+
+/// The range of the text of a block comment between its delimiters, without the closing one if
+/// the comment is unterminated at the end of the document.
+fn block_comment_body(text: &str) -> Range<usize> {
+    let inner = text.strip_prefix("/*").unwrap_or(text);
+    let start = text.len() - inner.len();
+    start..start + inner.strip_suffix("*/").unwrap_or(inner).len()
+}
+
+fn plan_node(node: &LinkedNode, context: &mut Context, edits: &mut Vec<Edit>) -> io::Result<()> {
+    let syntax = node.get();
+    let offset = node.offset();
+    let text = syntax.text();
+    match syntax.kind() {
+        SyntaxKind::LineComment => plan_text(&text[2..], offset + 2, context, edits),
+        SyntaxKind::BlockComment => {
+            let body = block_comment_body(text);
+            plan_text(&text[body.clone()], offset + body.start, context, edits)
+        }
+        SyntaxKind::Str if raw_body(node) => {
+            let text = &text[1..text.len() - 1];
+            if context.filler.is_none() {
+                return plan_string(text, offset + 1, context, edits);
+            }
+            // The escape sequences are kept, and only the text between them is filled.
+            let mut cursor = 0;
+            for escape in placeholders::escapes(text) {
+                plan_raw(
+                    &text[cursor..escape.start],
+                    offset + 1 + cursor,
+                    context,
+                    edits,
+                )?;
+                cursor = escape.end;
+            }
+            plan_raw(&text[cursor..], offset + 1 + cursor, context, edits)
+        }
+        SyntaxKind::Str => plan_string(&text[1..text.len() - 1], offset + 1, context, edits),
+        SyntaxKind::Raw => {
+            let raw: ast::Raw = syntax.cast().unwrap();
+            let raw_text = syntax.clone().into_text();
+            let backticks = raw_text.split(|c| c != '`').next().unwrap();
+
+            let mut text = raw_text
+                .trim_start_matches('`')
+                .strip_suffix(backticks)
+                .unwrap();
+            if let Some(lang) = raw.lang() {
+                text = text.strip_prefix(lang.get().as_str()).unwrap();
+            }
+
+            let start = raw_text.len() - backticks.len() - text.len();
+            plan_raw(text, offset + start, context, edits)
+        }
+        SyntaxKind::Link => {
+            let (scheme, rest) = text.split_once(':').unwrap();
+            plan_text(rest, offset + scheme.len() + 1, context, edits)
+        }
+        _ if syntax.children().next().is_some() => plan_children(node, context, edits),
+        _ => plan_text(text, offset, context, edits),
+    }
+}
+
+/// Compute the edits that replace the code in raw text, which starts at `offset`, with filler
+/// if it should be, or its words like prose otherwise.
+fn plan_raw(
+    text: &str,
+    offset: usize,
+    context: &mut Context,
+    edits: &mut Vec<Edit>,
+) -> io::Result<()> {
+    let Some(filler) = &mut context.filler else {
+        return plan_text(text, offset, context, edits);
+    };
+    for (range, replacement) in filler.fill(text) {
+        let range = offset + range.start..offset + range.end;
+        context.record(range.clone(), Some(SyntaxKind::Raw), "filler");
+        edits.push(Edit { range, replacement });
+    }
+    Ok(())
+}
+
+/// Compute the edits that replace the words of the source of a string literal without its
+/// quotes, which starts at `offset`, keeping its escape sequences and placeholders.
+fn plan_string(
+    text: &str,
+    offset: usize,
+    context: &mut Context,
+    edits: &mut Vec<Edit>,
+) -> io::Result<()> {
+    let mut cursor = 0;
+    for span in placeholders::spans(text) {
+        plan_text(&text[cursor..span.start], offset + cursor, context, edits)?;
+        cursor = span.end;
+    }
+    plan_text(&text[cursor..], offset + cursor, context, edits)
+}
+
+/// Replace every word in `text`, keeping the whitespace and punctuation in between.
+pub fn mutilate_text<W: Write>(
+    text: &str,
+    context: &mut Context,
+    output: &mut W,
+) -> io::Result<()> {
+    let audit_start = context.audit.as_ref().map_or(0, Vec::len);
+    let mut edits = Vec::new();
+    plan_text(text, 0, context, &mut edits)?;
+    let whole = 0..text.len();
+    let edits = fake_identifiers(text, None, std::slice::from_ref(&whole), context, edits);
+    let edits = scrub(text, None, std::slice::from_ref(&whole), context, edits);
+    let edits = shift_dates(text, None, std::slice::from_ref(&whole), context, edits);
+    context.finish_audit(audit_start, &edits);
+    let mut cursor = 0;
+    for edit in &edits {
+        output.write_all(&text.as_bytes()[cursor..edit.range.start])?;
+        output.write_all(edit.replacement.as_bytes())?;
+        cursor = edit.range.end;
+    }
+    output.write_all(&text.as_bytes()[cursor..])
+}
+
+/// Replace identifiers in the `regions` of `code` with fakes if scrubbing is enabled,
+/// overriding the planned `edits` there.
+fn scrub(
+    code: &str,
+    root: Option<&LinkedNode>,
+    regions: &[Range<usize>],
+    context: &mut Context,
+    edits: Vec<Edit>,
+) -> Vec<Edit> {
+    let Some(scrubber) = &mut context.scrubber else {
+        return edits;
+    };
+    let mut fakes = Vec::new();
+    for region in regions {
+        for (range, replacement) in scrubber.scrub(&code[region.clone()]) {
+            fakes.push(Edit {
+                range: region.start + range.start..region.start + range.end,
+                replacement,
+            });
+        }
+    }
+    for fake in &fakes {
+        let kind = root
+            .and_then(|root| root.leaf_at(fake.range.start + 1))
+            .map(|leaf| leaf.kind());
+        context.record(fake.range.clone(), kind, "pii");
+    }
+    edit::override_edits(edits, fakes)
+}
+
+/// Replace DOIs, ISBNs, ORCID iDs and arXiv identifiers in the `regions` of `code` that are
+/// at least partially replaced by the planned `edits` with valid identifiers, instead of
+/// replacing their parts like words.
+fn fake_identifiers(
+    code: &str,
+    root: Option<&LinkedNode>,
+    regions: &[Range<usize>],
+    context: &mut Context,
+    edits: Vec<Edit>,
+) -> Vec<Edit> {
+    let mut fakes = Vec::new();
+    for region in regions {
+        for (range, replacement) in context.identifiers.replace(&code[region.clone()]) {
+            let range = region.start + range.start..region.start + range.end;
+            // The edits are sorted and disjoint, so the first one ending after the start of
+            // the identifier is the only candidate for overlapping it.
+            let next = edits.partition_point(|edit| edit.range.end <= range.start);
+            if edits
+                .get(next)
+                .is_some_and(|edit| edit.range.start < range.end)
+            {
+                fakes.push(Edit { range, replacement });
+            }
+        }
+    }
+    for fake in &fakes {
+        let kind = root
+            .and_then(|root| root.leaf_at(fake.range.start + 1))
+            .map(|leaf| leaf.kind());
+        context.record(fake.range.clone(), kind, "identifier");
+    }
+    edit::override_edits(edits, fakes)
+}
+
+/// Move the dates in the `regions` of `code` and, in typst documents, of `datetime` calls if
+/// date shifting is enabled, overriding the planned `edits` there.
+fn shift_dates(
+    code: &str,
+    root: Option<&LinkedNode>,
+    regions: &[Range<usize>],
+    context: &mut Context,
+    edits: Vec<Edit>,
+) -> Vec<Edit> {
+    let Some(shifter) = &context.dates else {
+        return edits;
+    };
+    let mut shifted = Vec::new();
+    for region in regions {
+        for (range, replacement) in shifter.shift(&code[region.clone()]) {
+            let range = region.start + range.start..region.start + range.end;
+            shifted.push((range, replacement));
+        }
+    }
+    if let Some(root) = root {
+        dates::shift_datetime_calls(root, shifter, &mut shifted);
+        shifted.sort_by_key(|(range, _)| range.start);
+    }
+
+    let shifted: Vec<Edit> = shifted
+        .into_iter()
+        .map(|(range, replacement)| Edit { range, replacement })
+        .collect();
+    for edit in &shifted {
+        let kind = root
+            .and_then(|root| root.leaf_at(edit.range.start + 1))
+            .map(|leaf| leaf.kind());
+        context.record(edit.range.clone(), kind, "date");
+    }
+    edit::override_edits(edits, shifted)
+}
+
+/// A random number of days to move dates by, which is never zero.
+fn random_date_offset(seed: Option<u64>) -> i64 {
+    use rand::Rng;
+    let mut rng = Context::substream(seed, 0x0d47_e5f1_1e7a_3b29);
+    match rng.gen_range(dates::RANDOM_OFFSETS) {
+        0 => 1,
+        days => days,
+    }
+}
+
+/// Compute the edits that replace every word in `text`, which starts at `offset`.
+pub(crate) fn plan_text(
+    text: &str,
+    offset: usize,
+    context: &mut Context,
+    edits: &mut Vec<Edit>,
+) -> io::Result<()> {
+    let mut cursor = 0;
+    for (start, word) in scan::Words::new(text, context.segmentation) {
+        if context.is_generated(offset + start) {
+            continue;
+        }
+        let replacement = match &mut context.names {
+            Some(names) => {
+                names.gap(&text[cursor..start]);
+                cursor = start + word.len();
+                replace_name(word, context)?
+            }
+            None => replace_word(word, context)?,
+        };
+        if let Some(replacement) = replacement {
+            let start = offset + start;
+            let range = start..start + word.len();
+            context.record(range.clone(), context.node_kind, context.last_choice);
+            edits.push(Edit { range, replacement });
+        }
+    }
+    if let Some(names) = &mut context.names {
+        names.gap(&text[cursor..]);
+    }
+    Ok(())
+}
+
+/// Choose the replacement for a word if it is a name, reusing the earlier replacement of the
+/// same name.
+fn replace_name(word: &str, context: &mut Context) -> io::Result<Option<EcoString>> {
+    let names = context.names.as_mut().unwrap();
+    if !names.is_name(word) {
+        return Ok(None);
+    }
+    if let Some(replacement) = names.replacement(word) {
+        context.last_choice = "name";
+        return Ok(Some(replacement));
+    }
+    let replacement = replace_word(word, context)?;
+    let names = context.names.as_mut().unwrap();
+    Ok(replacement.map(|replacement| names.remember(word, replacement)))
+}
+
+/// Choose the replacement for a word in the name of an author, reusing the earlier replacement
+/// of the same name in documents and metadata.
+fn replace_author(word: &str, context: &mut Context) -> io::Result<Option<EcoString>> {
+    let names = match &mut context.names {
+        Some(names) => names,
+        None => context
+            .authors
+            .get_or_insert_with(|| names::NameDetector::new(&HashSet::new())),
+    };
+    if let Some(replacement) = names.replacement(word) {
+        context.last_choice = "name";
+        return Ok(Some(replacement));
+    }
+    let Some(replacement) = replace_word(word, context)? else {
+        return Ok(None);
+    };
+    let names = context.names.as_mut().or(context.authors.as_mut()).unwrap();
+    Ok(Some(names.remember(word, replacement)))
+}
+
+/// Replace a single word.
+pub fn mutilate_word<W: Write>(
+    word: &str,
+    context: &mut Context,
+    output: &mut W,
+) -> io::Result<()> {
+    match replace_word(word, context)? {
+        Some(replacement) => write!(output, "{replacement}"),
+        None => write!(output, "{word}"),
+    }
+}
+
+/// Choose the replacement for a word, or `None` if it is kept.
+fn replace_word(word: &str, context: &mut Context) -> io::Result<Option<EcoString>> {
+    if context.protected.contains(word)
+        || (!unicode_normalization::is_nfc(word) && context.protected.contains(nfc(word).as_str()))
+        || (word.contains(scan::is_invisible) && context.protected.contains(visible(word).as_str()))
+    {
+        return Ok(None);
+    }
+    choose_replacement(word, context).map(Some)
+}
+
+/// Choose the replacement for a word, even if it is protected.
+///
+/// The word is analyzed in NFC, and the replacement normalized as configured.
+fn choose_replacement(word: &str, context: &mut Context) -> io::Result<EcoString> {
+    let composed = unicode_normalization::is_nfc(word);
+    let replacement = if composed {
+        replace_normalized(word, context)?
+    } else {
+        replace_normalized(&nfc(word), context)?
+    };
+    let decompose = match context.normalize_output {
+        Normalization::Preserve => !composed && unicode_normalization::is_nfd(word),
+        Normalization::Nfc => return Ok(nfc(&replacement).into()),
+        Normalization::Nfd => true,
+    };
+    Ok(if decompose {
+        replacement.nfd().collect()
+    } else {
+        replacement
+    })
+}
+
+/// A text in NFC.
+fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+/// Choose the replacement for a word in NFC.
+fn replace_normalized(word: &str, context: &mut Context) -> io::Result<EcoString> {
+    if word.contains(scan::is_invisible) {
+        return replace_around_invisible(word, context);
+    }
+    if scan::is_mixed(word) {
+        return replace_mixed(word, context);
+    }
+    if let Some(runs) = scripts::runs(word) {
+        return replace_script_runs(word, &runs, context);
+    }
+    if let Some(script) = scripts::script(word).filter(|_| !is_known_script(word, context)) {
+        context.last_choice = "script";
+        return Ok(context.scripts.fill(word, script).into());
+    }
+    let language = word_language(word, context);
+
+    if context.preserve_suffixes {
+        let (stem, suffix) = morphology::split_suffix(word, language);
+        if !suffix.is_empty() {
+            let mut replacement = replace_stem(stem, language, context)?;
+            replacement.push_str(suffix);
+            return Ok(replacement);
+        }
+    }
+    replace_stem(word, language, context)
+}
+
+/// Choose the replacement for a word with invisible characters like soft hyphens or joiners
+/// in it, which are kept after as many characters of the replacement as in the word.
+///
+/// Soft hyphens mark the syllables of the word, which are used instead of hyphenating it.
+fn replace_around_invisible(word: &str, context: &mut Context) -> io::Result<EcoString> {
+    let plain = visible(word);
+    let replacement = if word.contains('\u{ad}') {
+        let hyphenation: Vec<u8> = word
+            .split('\u{ad}')
+            .map(|syllable| visible(syllable).chars().count())
+            .filter(|&length| length > 0)
+            .map(|length| length.try_into().unwrap_or(u8::MAX))
+            .collect();
+        let info = WordInfo {
+            length: scan::char_count(&plain),
+            hyphenation: &hyphenation,
+            numeric: scan::is_numeric(&plain),
+            language: word_language(&plain, context),
+        };
+        replace_with(&plain, &info, context)?
+    } else {
+        replace_normalized(&plain, context)?
+    };
+
+    let mut chars = replacement.chars();
+    let mut kept = EcoString::new();
+    for c in word.chars() {
+        if scan::is_invisible(c) {
+            kept.push(c);
+        } else if let Some(replaced) = chars.next() {
+            kept.push(replaced);
+        }
+    }
+    kept.extend(chars);
+    Ok(kept)
+}
+
+/// Choose the replacement for a word that mixes scripts, like `αβtest`, run by run.
+///
+/// Runs in the script of the language, or of one of the languages words may be guessed to be
+/// in, are replaced like words; the letters of the others are replaced with random letters of
+/// their script.
+fn replace_script_runs(
+    word: &str,
+    runs: &[(Range<usize>, unicode_script::Script)],
+    context: &mut Context,
+) -> io::Result<EcoString> {
+    let mut replacement = EcoString::new();
+    let mut filled = false;
+    for (range, script) in runs {
+        let run = &word[range.clone()];
+        if is_known_script(run, context) {
+            replacement.push_str(&replace_normalized(run, context)?);
+            filled = false;
+        } else {
+            replacement.push_str(&context.scripts.fill(run, *script));
+            filled = true;
+        }
+    }
+    if filled {
+        context.last_choice = "script";
+    }
+    Ok(replacement)
+}
+
+/// Whether a word is written in the script of the language, or of one of the languages words
+/// may be guessed to be in.
+fn is_known_script(word: &str, context: &Context) -> bool {
+    guess::Script::of_word(word).is_some_and(|script| {
+        context
+            .word_languages
+            .as_deref()
+            .unwrap_or(std::slice::from_ref(&context.language))
+            .iter()
+            .any(|&language| guess::Script::of_language(language) == script)
+    })
+}
+
+/// Choose the replacement for a word of letters and digits, like `IPv6` or `v2.3rc1`, which
+/// keeps its shape: every letter is replaced with a random letter of its script and case, every
+/// run of digits like a number, and the other characters stay where they are.
+fn replace_mixed(word: &str, context: &mut Context) -> io::Result<EcoString> {
+    let mut replacement = EcoString::new();
+    let mut number = None;
+    for (i, c) in word.char_indices().chain([(word.len(), ' ')]) {
+        if c.is_numeric() {
+            number.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = number.take() {
+            let digits = &word[start..i];
+            let info = WordInfo {
+                length: scan::char_count(digits),
+                hyphenation: &[],
+                numeric: true,
+                language: context.language,
+            };
+            replacement.push_str(&replace_with(digits, &info, context)?);
+        }
+        if i == word.len() {
+            break;
+        }
+        if c.is_alphabetic() {
+            replacement.push_str(&context.scripts.fill(c.encode_utf8(&mut [0; 4]), c.script()));
+        } else {
+            replacement.push(c);
+        }
+    }
+    context.last_choice = "mixed";
+    Ok(replacement)
+}
+
+/// A word without its invisible characters.
+fn visible(word: &str) -> String {
+    word.chars().filter(|&c| !scan::is_invisible(c)).collect()
+}
+
+/// The language of a word, which is guessed if enabled.
+fn word_language(word: &str, context: &Context) -> Lang {
+    match &context.word_languages {
+        Some(candidates) => guess::guess_language(word, context.language, candidates),
+        None => context.language,
+    }
+}
+
+/// Choose the replacement for a word or the stem of a word.
+fn replace_stem(word: &str, language: Lang, context: &mut Context) -> io::Result<EcoString> {
+    let hyphenation = context
+        .hyphenations
+        .entry((language, word.into()))
+        .or_insert_with(|| replacer::hyphenation(word, language, context.patterns.as_deref()))
+        .clone();
+    let info = WordInfo {
+        length: scan::char_count(word),
+        hyphenation: &hyphenation,
+        numeric: scan::is_numeric(word),
+        language,
+    };
+    replace_with(word, &info, context)
+}
+
+/// Choose the replacement for a word with the given properties.
+fn replace_with(word: &str, info: &WordInfo, context: &mut Context) -> io::Result<EcoString> {
+    let mut replacement = Vec::new();
+    context.replacer.replace(word, info, &mut replacement)?;
+    let strategy = context.replacer.last_strategy();
+    context.last_choice = strategy.map_or("custom", Strategy::name);
+    if !info.numeric {
+        context.stats.words += 1;
+        if strategy == Some(Strategy::Random) {
+            context.stats.fallbacks += 1;
+        }
+    }
+    let replacement = String::from_utf8(replacement)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(replacement.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mutilate(code: &str) -> String {
+        mutilate_str(code, &MutilateOptions::new().seed(1)).unwrap()
+    }
+
+    #[test]
+    fn reused_context() {
+        let code = "The quick brown fox jumps over the lazy dog.";
+        let options = MutilateOptions::new().seed(1);
+        let mut context = Context::new(&options).unwrap();
+        let first = mutilate_str_with(code, &mut context).unwrap();
+        let mut context = context.reuse(&options);
+        assert_eq!(mutilate_str_with(code, &mut context).unwrap(), first);
+        let mut context = context.reuse(&options.clone().seed(2));
+        assert_eq!(
+            mutilate_str_with(code, &mut context).unwrap(),
+            mutilate_str(code, &MutilateOptions::new().seed(2)).unwrap()
+        );
+    }
+
+    #[test]
+    fn shrink_fraction() {
+        for fraction in [-0.1, 1.5, f64::NAN] {
+            let options = MutilateOptions::new().shrink(fraction);
+            assert!(matches!(Context::new(&options), Err(Error::Unsupported(_))));
+        }
+        assert!(Context::new(&MutilateOptions::new().shrink(1.0)).is_ok());
+    }
+
+    #[test]
+    fn changed_lines() {
+        let code = "First line here.\nSecond line here.\nThird line here.\nFourth line here.\n";
+        let mut context = Context::new(&MutilateOptions::new().seed(1)).unwrap();
+        context.set_changed_lines(Some(vec![2..3, 4..5]));
+        let output = mutilate_str_with(code, &mut context).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "First line here.");
+        assert_ne!(lines[1], "Second line here.");
+        assert_eq!(lines[2], "Third line here.");
+        assert_ne!(lines[3], "Fourth line here.");
+    }
+
+    #[test]
+    fn cite_supplement() {
+        let code = r#"#cite("knuth", form: "prose", supplement: "page twelve")"#;
+        let output = mutilate_str(code, &MutilateOptions::new().seed(1).aggressive(true)).unwrap();
+        assert!(output.starts_with(r#"#cite("knuth", form: "prose", supplement: ""#));
+        assert!(!output.contains("page twelve"));
+    }
+
+    #[test]
+    fn other_scripts() {
+        use unicode_script::{Script, UnicodeScript};
+        let scripts = |text: &str| -> Vec<Script> {
+            text.chars()
+                .filter(|c| c.is_alphabetic())
+                .map(|c| c.script())
+                .collect()
+        };
+        for segmentation in [Segmentation::Unicode, Segmentation::Alphanumeric] {
+            let options = MutilateOptions::new().seed(1).segmentation(segmentation);
+            for code in [
+                "αβ-test",
+                "Wi‑Fi技术",
+                "Wi‑Fi技术 and 技术 here.",
+                "The λόγος of it.",
+            ] {
+                let output = mutilate_str(code, &options).unwrap();
+                assert_ne!(output, code);
+                assert_eq!(scripts(&output), scripts(code), "{code} became {output}");
+            }
+        }
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        assert_eq!(mutilate("Hi /*").len(), "Hi /*".len());
+        assert!(mutilate("Hi /* €").ends_with("/* €"));
+        let output = mutilate("Hi /* abc");
+        assert!(output.contains("/* ") && !output.contains("bc"));
+    }
+}
@@ -0,0 +1,114 @@
+//! A logger that writes messages to stderr, filtered by the verbosity and by the directives in
+//! `TYPST_MUTILATE_LOG`.
+
+use std::{
+    cmp::Reverse,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// The environment variable with the filter directives, like `debug` or
+/// `info,typst_mutilate::wordlist=trace`.
+const ENV: &str = "TYPST_MUTILATE_LOG";
+
+/// Whether labels are colored.
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+struct Logger {
+    /// The level of targets without a directive.
+    default: LevelFilter,
+    /// Targets and their levels, longest first so that the most specific one matches first.
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Logger {
+    fn level(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let label = label(record.level());
+        if record.level() >= Level::Debug {
+            eprintln!("{label} [{}] {}", record.target(), record.args());
+        } else {
+            eprintln!("{label} {}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The label of messages of a level, like `warning:`, in bold and colored if enabled.
+pub fn label(level: Level) -> String {
+    let (name, color) = match level {
+        Level::Error => ("error", 31),
+        Level::Warn => ("warning", 33),
+        Level::Info => ("note", 36),
+        Level::Debug => ("debug", 35),
+        Level::Trace => ("trace", 90),
+    };
+    if COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[1;{color}m{name}:\x1b[0m")
+    } else {
+        format!("{name}:")
+    }
+}
+
+/// Install the logger, showing notes and warnings at a `verbosity` of 0, only warnings below
+/// and debug and trace messages above, with colored labels if `color` is set.
+pub fn init(verbosity: i32, color: bool) -> Result<(), String> {
+    COLOR.store(color, Ordering::Relaxed);
+    let mut default = match verbosity {
+        ..=-1 => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        2.. => LevelFilter::Trace,
+    };
+    let mut directives = Vec::new();
+    if let Ok(filter) = std::env::var(ENV) {
+        for directive in filter.split(',').map(str::trim) {
+            let level = |level: &str| {
+                level
+                    .parse::<LevelFilter>()
+                    .map_err(|_| format!("unknown log level `{level}` in `{ENV}`"))
+            };
+            match directive.split_once('=') {
+                Some((target, level_name)) => {
+                    directives.push((target.replace('-', "_"), level(level_name)?))
+                }
+                None if directive.is_empty() => {}
+                None => default = level(directive)?,
+            }
+        }
+    }
+    directives.sort_by_key(|(target, _)| Reverse(target.len()));
+
+    let max = directives
+        .iter()
+        .map(|(_, level)| *level)
+        .fold(default, Ord::max);
+    let logger = Box::leak(Box::new(Logger {
+        default,
+        directives,
+    }));
+    log::set_logger(logger).map_err(|err| err.to_string())?;
+    log::set_max_level(max);
+    Ok(())
+}
@@ -1,246 +1,2199 @@
+mod annotations;
+mod completions;
+mod diff;
+mod logger;
+mod review;
+mod selftest;
+
 use std::{
-    collections::BTreeMap,
+    collections::HashSet,
     fs::File,
-    io::{self, BufRead, BufReader, Read, Write},
-    path::PathBuf,
+    io::{self, BufReader, BufWriter, IsTerminal, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
 };
 
-use argh::FromArgs;
-use ecow::{EcoString, EcoVec};
-use hypher::Lang;
-use rand::{seq::SliceRandom, SeedableRng};
-use rand_xoshiro::Xoshiro256PlusPlus;
-use typst_syntax::{ast, SyntaxKind, SyntaxNode};
+use annotations::{Annotation, AnnotationFormat};
+use argh::{ArgsInfo, FromArgs};
+use completions::Shell;
+use diff::ColorChoice;
+use review::Grouping;
+use typst_mutilate::{
+    hypher::Lang, AuditEntry, AuditLog, AuditRecord, Context, Edit, Encoding, Error, Format,
+    HeadingPattern, KindFilter, Manifest, Metrics, Mode, MutilateOptions, Newline, NodeAction,
+    Normalization, NumberMode, Package, Patterns, Quotes, RawMode, ReplacementMap, Segmentation,
+    Selector, Shuffle, Strategy, WordCounter,
+};
+use typst_syntax::SyntaxKind;
 
 /// A tool to replace all words in a typst document with random garbage.
-#[derive(FromArgs)]
+#[derive(FromArgs, ArgsInfo)]
 struct Args {
     /// a file to perform in-place replacement on
-    #[argh(option, short = 'i')]
+    #[argh(option, short = 'i', arg_name = "file")]
     in_place: Option<PathBuf>,
-    /// the path to a line-separated wordlist
+    /// the path of the document read from stdin, for choosing the format and reporting errors
+    #[argh(option, arg_name = "file")]
+    stdin_filepath: Option<PathBuf>,
+    /// the path to a line-separated wordlist, optionally prefixed by the language it is used
+    /// for, like `de=words.txt`; can be repeated to merge several wordlists
     #[argh(option, short = 'w')]
-    wordlist: Option<PathBuf>,
-    /// an ISO 639-1 language code, like `de`
-    #[argh(option, short = 'l', default = r#"String::from("en")"#)]
-    language: String,
+    wordlist: Vec<WordlistArg>,
+    /// keep wordlist entries that only differ in case, like `Apple` and `apple`, instead of
+    /// only the first one
+    #[argh(switch)]
+    case_sensitive_wordlist: bool,
+    /// don't read or write the hyphenation cache (the cache speeds up later loads)
+    #[argh(switch)]
+    no_hyphenation_cache: bool,
+    /// the URL of a line-separated wordlist, which is downloaded once and cached
+    #[cfg(feature = "remote")]
+    #[argh(option)]
+    wordlist_url: Option<String>,
+    /// the expected SHA-256 checksum of the wordlist downloaded from `--wordlist-url`
+    #[cfg(feature = "remote")]
+    #[argh(option)]
+    wordlist_sha256: Option<String>,
+    /// download the wordlist from `--wordlist-url` again, even if it is cached
+    #[cfg(feature = "remote")]
+    #[argh(switch)]
+    refresh_wordlist: bool,
+    /// a language code, like `de`, `deu` or `de-AT`; detected from the document by default,
+    /// falling back to English, which is also used when streaming
+    #[argh(option, short = 'l')]
+    language: Option<String>,
+    /// a file of TeX hyphenation patterns, like `hyph-xx.tex`, or a binary trie of hypher,
+    /// like `xx.bin`, that replaces the built-in hyphenation of all languages
+    #[argh(option)]
+    hyphenation_patterns: Option<PathBuf>,
+    /// print the supported languages and exit
+    #[argh(switch)]
+    list_languages: bool,
     /// whether to replace elements that are more likely to change behavior, like strings
     #[argh(switch, short = 'a')]
     aggressive: bool,
+    /// also replace `alt` and `description` strings, like the alternative text of images,
+    /// without replacing all strings as `-a` does
+    #[argh(switch)]
+    alt_text: bool,
+    /// keep common inflectional suffixes of the language, like `-ing`, and only replace the stem
+    #[argh(switch)]
+    preserve_suffixes: bool,
+    /// guess the language of every word, for text that mixes languages
+    #[argh(switch)]
+    guess_word_language: bool,
+    /// replace phone numbers, IBANs, card numbers and national ID numbers with fakes of the
+    /// same format, even in text that is otherwise kept
+    #[argh(switch)]
+    scrub_pii: bool,
+    /// replace the names of authors in `author` and `authors` arguments, fields and variables,
+    /// even in strings that are otherwise kept; always enabled for `batch`
+    #[argh(switch)]
+    scrub_metadata: bool,
+    /// keep ordinary prose and only replace likely names of people, companies and places,
+    /// consistently throughout the document
+    #[argh(switch)]
+    names_only: bool,
+    /// a file with names to replace for `--names-only`, one per line
+    #[argh(option)]
+    names: Option<PathBuf>,
+    /// move every date by the same random number of days, keeping the intervals between them
+    #[argh(switch)]
+    shift_dates: bool,
+    /// the number of days to move dates by, which implies `--shift-dates`
+    #[argh(option)]
+    date_offset: Option<i64>,
+    /// a strategy for choosing replacements: `hyphenation`, `length`, `random` or
+    /// `sentences`, which replaces whole sentences with generated ones; can be repeated to try
+    /// several in order, replacing the default `hyphenation`, `length` and `random`, so include
+    /// `hyphenation` or `length` to still replace words with words of the wordlist
+    #[argh(option)]
+    strategy: Vec<Strategy>,
+    /// how to replace numbers: `digits` for random digits or `magnitude` to keep their order
+    /// of magnitude and precision; `digits` by default
+    #[argh(option)]
+    numbers: Option<NumberMode>,
+    /// how to split text into words: `unicode` for Unicode word segmentation, which keeps
+    /// words like `don't` and `3.14` together, or `alphanumeric` to split at every character
+    /// that isn't alphanumeric; `unicode` by default
+    #[argh(option)]
+    segmentation: Option<Segmentation>,
+    /// the Unicode normalization of replacements: `nfc`, `nfd` or `preserve` to decompose
+    /// the replacements of decomposed words only; `preserve` by default
+    #[argh(option, arg_name = "form")]
+    normalize_output: Option<Normalization>,
+    /// also drop about this share of the paragraphs, list items and table rows of typst
+    /// documents, like `0.2`, to make smaller examples
+    #[argh(option, arg_name = "fraction")]
+    shrink: Option<f64>,
+    /// also move the `paragraphs`, `sections` or `sentences` of typst documents to random
+    /// positions among their siblings
+    #[argh(option, arg_name = "unit")]
+    shuffle: Option<Shuffle>,
+    /// whether to `keep` the quotations of typst documents, the content of `quote` calls and
+    /// text between double quotes, or to replace `only` them
+    #[argh(option)]
+    quotes: Option<Quotes>,
+    /// only mutilate the sections of typst documents whose headings are this text or match
+    /// this `/regex/`, up to the next heading of the same depth; can be repeated
+    #[argh(option, arg_name = "heading")]
+    section: Vec<HeadingPattern>,
+    /// keep the sections of typst documents whose headings are this text or match this
+    /// `/regex/`, even in the sections given with `--section`; can be repeated
+    #[argh(option, arg_name = "heading")]
+    exclude_section: Vec<HeadingPattern>,
+    /// only mutilate the elements of typst documents that a selector like `figure caption` or
+    /// `heading.where(level: 1)` matches; can be repeated
+    #[argh(option, arg_name = "selector")]
+    select: Vec<Selector>,
+    /// keep the elements of typst documents that a selector matches, even in the elements
+    /// given with `--select`; can be repeated
+    #[argh(option, arg_name = "selector")]
+    keep: Vec<Selector>,
+    /// how to replace the code in raw text: `words` like prose or `filler` to replace its
+    /// identifiers and numbers with random characters, keeping the length and indentation of
+    /// every line; `words` by default
+    #[argh(option, arg_name = "mode")]
+    raw: Option<RawMode>,
+    /// replace every paragraph of running text with generated text of as many words instead
+    /// of replacing its words one by one
+    #[argh(switch)]
+    regenerate_paragraphs: bool,
+    /// after mutilating a document, compare the word and sentence lengths, syllables and
+    /// estimated line count of its prose with the original on stderr
+    #[argh(switch)]
+    metrics: bool,
+    /// make sure that mutilated documents have exactly the lines of the input, with blank
+    /// lines in the same places, by reflowing replacements that span lines and checking the
+    /// output
+    #[argh(switch)]
+    preserve_lines: bool,
+    /// only mutilate the lines of documents that changed since this git revision, as `git diff`
+    /// shows them, and keep the others as they are; documents that are new since then are
+    /// mutilated entirely
+    #[argh(option, arg_name = "rev")]
+    since: Option<String>,
+    /// a word to keep unchanged; can be repeated
+    #[argh(option)]
+    protect: Vec<String>,
+    /// a seed for the random number generator, for reproducible output
+    #[argh(option)]
+    seed: Option<u64>,
+    /// make the output depend on nothing but the input and the options, for snapshot tests:
+    /// seed with `--seed` or 0, sort the wordlists and don't use the hyphenation cache
+    #[argh(switch)]
+    stable: bool,
+    /// how to interpret the input: `typst`, `plain`, `markdown` or `latex`; guessed from the file extension by
+    /// default
+    #[argh(option)]
+    format: Option<Format>,
+    /// how to parse typst input: `markup`, `code` or `math`; guessed from the file extension
+    /// by default
+    #[argh(option)]
+    mode: Option<Mode>,
+    /// the encoding of the documents, `utf-8`, `latin1` or `windows-1252`, which mutilated
+    /// documents are written in too; detected by default
+    #[argh(option)]
+    encoding: Option<Encoding>,
+    /// convert the line breaks of mutilated documents to `lf`, `crlf` or `native`; they are
+    /// kept as they are by default
+    #[argh(option)]
+    newline: Option<Newline>,
+    /// process the input block by block to keep memory usage low for very large documents
+    #[argh(switch)]
+    stream: bool,
+    /// act as a git clean filter: read from stdin, write to stdout without notes and seed the
+    /// random number generator with the contents and `--seed`, so that the output only
+    /// changes with the input
+    #[argh(switch)]
+    filter: bool,
+    /// compile the mutilated document to this PDF with the typst compiler, `$TYPST` or
+    /// `typst` by default, instead of writing it to stdout; documents that import, include or
+    /// load other files of their project need `batch --compile`
+    #[argh(option, arg_name = "pdf")]
+    compile: Option<PathBuf>,
+    /// override what happens to nodes of a syntax kind, like `heading=copy`; the action is one of
+    /// `mutilate`, `copy` or `descend`; can be repeated
+    #[argh(option)]
+    node_action: Vec<KindAction>,
+    /// only mutilate nodes of these syntax kinds and what is in them, like `text,comment,link`,
+    /// where `comment` stands for both kinds of comments; can be repeated
+    #[argh(option)]
+    kinds: Vec<Kinds>,
+    /// don't mutilate nodes of these syntax kinds, like `str,raw`; can be repeated
+    #[argh(option)]
+    skip_kinds: Vec<Kinds>,
+    /// append a JSON line for every replacement to this file, with the position, node kind,
+    /// strategy and a salted hash of the replaced text
+    #[argh(option)]
+    audit_log: Option<PathBuf>,
+    /// the salt for the hashes in `--audit-log`, which requires one; keep it to verify the log
+    #[argh(option)]
+    audit_salt: Option<String>,
+    /// write the replacement of every original text to this typst file, which defines a
+    /// `replacements` dictionary
+    #[argh(option, arg_name = "file")]
+    write_map_typ: Option<PathBuf>,
+    /// key the `--write-map-typ` dictionary by hashes of the originals with this salt, instead
+    /// of the originals themselves
+    #[argh(option)]
+    map_salt: Option<String>,
+    /// when mutilating in place, record the replaced text in a `.mutilate-undo` file next to
+    /// the document, so that `undo` can restore it
+    #[argh(switch)]
+    undo: bool,
+    /// print a word-level diff of the replacements instead of writing the mutilated document
+    #[argh(switch)]
+    dry_run: bool,
+    /// ask whether to accept every replacement before writing the document, reading the
+    /// answers from the terminal
+    #[argh(switch)]
+    interactive: bool,
+    /// how `--interactive` groups the replacements: every distinct `word` with all of its
+    /// occurrences, or every line as a `hunk`
+    #[argh(option, default = "Grouping::Word")]
+    review_by: Grouping,
+    /// when to color diffs and messages: `auto`, `always` or `never`
+    #[argh(option, default = "ColorChoice::Auto")]
+    color: ColorChoice,
+    /// write the problems that `check`, `hook --check` and `verify` find to stdout as
+    /// annotations for continuous integration instead of diffs: `github` workflow commands or
+    /// `json` lines
+    #[argh(option, arg_name = "format")]
+    annotations: Option<AnnotationFormat>,
+    /// only show warnings and errors, without notes or the progress of `batch` runs
+    #[argh(switch, short = 'q')]
+    quiet: bool,
+    /// show details like the loaded wordlists and the files of `batch` runs; repeat, like
+    /// `-vv`, to show even more
+    #[argh(switch, short = 'v')]
+    verbose: u8,
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand)]
+enum Command {
+    Mutilate(MutilateCommand),
+    Check(CheckCommand),
+    Verify(VerifyCommand),
+    Map(MapCommand),
+    Undo(UndoCommand),
+    Selftest(SelftestCommand),
+    Reduce(ReduceCommand),
+    Wordlist(WordlistCommand),
+    Completions(CompletionsCommand),
+    Batch(BatchCommand),
+    Hook(HookCommand),
+    InstallHook(InstallHookCommand),
+    #[cfg(feature = "serve")]
+    Serve(ServeCommand),
 }
 
-fn main() -> io::Result<()> {
-    let args: Args = argh::from_env();
+/// Mutilate a document from stdin or `--in-place`, which is also done without a command.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "mutilate")]
+struct MutilateCommand {}
 
-    let mut code = String::new();
-    if let Some(path) = &args.in_place {
-        code = std::fs::read_to_string(path)?;
-    } else {
-        std::io::stdin().read_to_string(&mut code)?;
+/// Check whether mutilating files would change them, failing if one would.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "check")]
+struct CheckCommand {
+    /// the files to check
+    #[argh(positional)]
+    files: Vec<PathBuf>,
+}
+
+/// Verify that the entries of an audit log match the original documents.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "verify")]
+struct VerifyCommand {
+    /// the audit log
+    #[argh(positional)]
+    log: PathBuf,
+    /// check all entries against this copy of the original document instead of the files
+    /// they name, like for documents mutilated in place or read from stdin
+    #[argh(option, arg_name = "file")]
+    original: Option<PathBuf>,
+    /// the salt of the hashes, given by `--audit-salt` when the log was written
+    #[argh(option)]
+    salt: String,
+}
+
+/// Write the replacements of a document from stdin or `-i` as a typst dictionary, without
+/// writing the mutilated document.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "map")]
+struct MapCommand {
+    /// the file to write the dictionary to instead of stdout
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+/// Print a completion script for a shell: `bash`, `zsh`, `fish` or `powershell`.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "completions")]
+struct CompletionsCommand {
+    /// the shell
+    #[argh(positional)]
+    shell: Shell,
+}
+
+/// Restore documents mutilated in place with `--undo` and remove their undo files.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "undo")]
+struct UndoCommand {
+    /// the documents to restore
+    #[argh(positional)]
+    files: Vec<PathBuf>,
+}
+
+/// Mutilate every document of a corpus with several seeds and strategies, checking that the
+/// results parse, keep the number of words and their lengths and leak no original words.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "selftest")]
+struct SelftestCommand {
+    /// the directory with the corpus
+    #[argh(positional)]
+    dir: PathBuf,
+    /// the number of seeds to mutilate every document with
+    #[argh(option, default = "3")]
+    seeds: u64,
+    /// the share by which the length of the replaced text may change
+    #[argh(option, default = "0.25")]
+    tolerance: f64,
+}
+
+/// Shrink a typst document while a command keeps succeeding on it, to make a minimal
+/// reproducer, and write it to stdout.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "reduce")]
+struct ReduceCommand {
+    /// the document
+    #[argh(positional)]
+    file: PathBuf,
+    /// the shell command that succeeds while the document is still interesting, in which `{{}}`
+    /// is replaced by the path of a reduced copy, which is appended if there is no `{{}}`
+    #[argh(option)]
+    check: String,
+    /// mutilate the reduced document with the other options, if the command still succeeds
+    #[argh(switch)]
+    mutilate: bool,
+    /// the file to write the reduced document to instead of stdout
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+/// Mutilate many files at once, mirroring them into an output directory.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "batch")]
+struct BatchCommand {
+    /// files or directories to mutilate; other files in the directories are copied unchanged
+    #[argh(positional)]
+    inputs: Vec<PathBuf>,
+    /// the directory to write the results to
+    #[argh(option, short = 'o')]
+    output: PathBuf,
+    /// process all files, even those that haven't changed since the last run
+    #[argh(switch)]
+    force: bool,
+    /// compile the entry document of the mutilated tree to this PDF
+    #[argh(option, arg_name = "pdf")]
+    compile: Option<PathBuf>,
+    /// the entry document to compile, relative to the output directory
+    #[argh(option, default = "PathBuf::from(\"main.typ\")")]
+    entry: PathBuf,
+    /// mutilate a typst package: also replace its strings, except the paths of the files it
+    /// loads, and rename its internal bindings, but keep the names that the entrypoint of its
+    /// `typst.toml` exports; every file is processed again
+    #[argh(switch)]
+    package: bool,
+}
+
+/// Mutilate files in place for git hooks and the pre-commit framework, failing if one changed.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "hook")]
+struct HookCommand {
+    /// the files to mutilate
+    #[argh(positional)]
+    files: Vec<PathBuf>,
+    /// only check whether the files would change, without writing them
+    #[argh(switch)]
+    check: bool,
+    /// stage the mutilated files with `git add` instead of failing because they changed
+    #[argh(switch)]
+    stage: bool,
+}
+
+/// Install a git pre-commit hook that mutilates the staged typst documents, using the options
+/// given before this command.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "install-hook")]
+struct InstallHookCommand {
+    /// make the hook reject commits with documents that would change instead of mutilating
+    /// and staging them
+    #[argh(switch)]
+    check: bool,
+    /// replace an existing pre-commit hook
+    #[argh(switch)]
+    force: bool,
+}
+
+/// Serve `POST /mutilate` requests over HTTP, with the options given before this command as
+/// defaults that query parameters override.
+#[cfg(feature = "serve")]
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "serve")]
+struct ServeCommand {
+    /// the address to listen on
+    #[argh(option, default = "String::from(\"127.0.0.1:8080\")")]
+    http: String,
+    /// the maximum size of a document in bytes
+    #[argh(option, default = "typst_mutilate::serve::DEFAULT_MAX_BODY_SIZE")]
+    max_size: usize,
+    /// the number of requests to handle at the same time
+    #[argh(option, default = "typst_mutilate::serve::DEFAULT_WORKERS")]
+    workers: usize,
+}
+
+/// Manage wordlists.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "wordlist")]
+struct WordlistCommand {
+    #[argh(subcommand)]
+    command: WordlistSubcommand,
+}
+
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand)]
+enum WordlistSubcommand {
+    Build(BuildWordlist),
+}
+
+/// Build a wordlist from the words in a corpus of documents.
+#[derive(FromArgs, ArgsInfo)]
+#[argh(subcommand, name = "build")]
+struct BuildWordlist {
+    /// files or directories to collect words from; directories are searched recursively for
+    /// files of a known format
+    #[argh(positional)]
+    inputs: Vec<PathBuf>,
+    /// the file to write the wordlist to, instead of stdout
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// the minimum number of occurrences of a word
+    #[argh(option, default = "2")]
+    min_count: u64,
+    /// the minimum number of characters in a word
+    #[argh(option, default = "2")]
+    min_length: usize,
+    /// the maximum number of characters in a word
+    #[argh(option)]
+    max_length: Option<usize>,
+    /// write only the words, without their number of occurrences
+    #[argh(switch)]
+    no_counts: bool,
+}
+
+/// A wordlist, optionally restricted to a language.
+struct WordlistArg {
+    language: Option<Lang>,
+    path: PathBuf,
+}
+
+impl FromStr for WordlistArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((language, path)) = s.split_once('=') {
+            if let Some(language) = typst_mutilate::parse_language(language) {
+                return Ok(Self {
+                    language: Some(language),
+                    path: path.into(),
+                });
+            }
+        }
+        Ok(Self {
+            language: None,
+            path: s.into(),
+        })
+    }
+}
+
+/// An action applied to all nodes of a kind.
+struct KindAction {
+    kind: SyntaxKind,
+    action: NodeAction,
+}
+
+impl FromStr for KindAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, action) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `<kind>=<action>`, found `{s}`"))?;
+        let kind = typst_mutilate::parse_kind(kind)
+            .ok_or_else(|| format!("unknown syntax kind `{kind}`"))?;
+        Ok(Self {
+            kind,
+            action: action.parse()?,
+        })
+    }
+}
+
+/// A comma-separated list of syntax kinds.
+struct Kinds(Vec<SyntaxKind>);
+
+impl FromStr for Kinds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut kinds = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match name {
+                "comment" => kinds.extend([SyntaxKind::LineComment, SyntaxKind::BlockComment]),
+                _ => kinds.push(
+                    typst_mutilate::parse_kind(name)
+                        .ok_or_else(|| format!("unknown syntax kind `{name}`"))?,
+                ),
+            }
+        }
+        Ok(Self(kinds))
     }
+}
 
-    let mut context = build_context(&args)?;
+fn main() -> ExitCode {
+    let args = parse_args();
+    let verbosity = i32::from(args.verbose) - i32::from(args.quiet);
+    let color = args.color.enabled(io::stderr().is_terminal());
+    if let Err(err) = logger::init(verbosity, color) {
+        eprintln!("{} {err}", logger::label(log::Level::Error));
+        return ExitCode::FAILURE;
+    }
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{} {err}", logger::label(log::Level::Error));
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    let syntax = typst_syntax::parse(&code);
-    let errors = syntax.errors();
-    if !errors.is_empty() {
-        eprintln!("Syntax errors: {:?}", errors);
+/// Parse the command line like [`argh::from_env`], but accept repeated short switches like
+/// `-vv` and values like `--color=never`, which argh doesn't split.
+fn parse_args() -> Args {
+    let mut options_ended = false;
+    let strings: Vec<String> = std::env::args()
+        .flat_map(|arg| {
+            options_ended |= arg == "--";
+            let repeated = arg.len() > 2 && arg.bytes().skip(1).all(|b| b == b'v');
+            match arg.strip_prefix('-') {
+                _ if options_ended => vec![arg],
+                Some(switches) if repeated => vec!["-v".to_string(); switches.len()],
+                Some(long) if long.starts_with('-') && long.contains('=') => {
+                    let (name, value) = arg.split_once('=').unwrap();
+                    vec![name.to_string(), value.to_string()]
+                }
+                _ => vec![arg],
+            }
+        })
+        .collect();
+    let cmd = Path::new(&strings[0])
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&strings[0]);
+    let rest: Vec<&str> = strings[1..].iter().map(String::as_str).collect();
+    Args::from_args(&[cmd], &rest).unwrap_or_else(|early_exit| {
+        std::process::exit(match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                0
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {cmd} --help for more information.",
+                    early_exit.output
+                );
+                1
+            }
+        })
+    })
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    if args.list_languages {
+        let mut out = io::stdout().lock();
+        for (code, language) in typst_mutilate::supported_languages() {
+            writeln!(out, "{code}  {language:?}")?;
+        }
         return Ok(());
     }
+    if let Some(Command::Completions(completions)) = &args.command {
+        let mut out = io::stdout().lock();
+        completions::write(&mut out, completions.shell, &Args::get_args_info())?;
+        return Ok(out.flush()?);
+    }
+
+    let options = build_options(args)?;
+    match &args.command {
+        Some(Command::Mutilate(MutilateCommand {})) | None => {}
+        Some(Command::Check(check)) => {
+            let hook = HookCommand {
+                files: check.files.clone(),
+                check: true,
+                stage: false,
+            };
+            return self::hook(args, &hook, &options, true);
+        }
+        Some(Command::Verify(verify)) => return self::verify(args, verify),
+        Some(Command::Undo(undo)) => return self::undo(args, undo),
+        Some(Command::Selftest(selftest)) => return self::selftest(args, selftest, &options),
+        Some(Command::Reduce(reduce)) => return self::reduce(args, reduce, options),
+        Some(Command::Completions(_)) => {
+            unreachable!("completions are printed before building the options")
+        }
+        Some(Command::Map(map)) => return self::map(args, map, options),
+        Some(Command::Wordlist(WordlistCommand {
+            command: WordlistSubcommand::Build(build),
+        })) => return build_wordlist(args, build, &options),
+        Some(Command::Batch(batch)) => return self::batch(args, batch, &options),
+        Some(Command::Hook(hook)) => return self::hook(args, hook, &options, false),
+        Some(Command::InstallHook(install)) => return install_hook(install),
+        #[cfg(feature = "serve")]
+        Some(Command::Serve(serve)) => {
+            let server = typst_mutilate::serve::Server::bind(&serve.http, options)?
+                .max_body_size(serve.max_size)
+                .workers(serve.workers);
+            log::info!("listening on http://{}/mutilate", server.local_addr()?);
+            return server.run();
+        }
+    }
 
-    let mut output = Vec::new();
-    mutilate(&syntax, &mut context, &mut output)?;
+    if args.in_place.is_some() && args.stdin_filepath.is_some() {
+        return Err(Error::Unsupported(
+            "`--stdin-filepath` cannot be combined with `--in-place`".into(),
+        ));
+    }
+    if args.filter && (args.in_place.is_some() || args.stream) {
+        return Err(Error::Unsupported(
+            "`--filter` cannot be combined with `--in-place` or `--stream`".into(),
+        ));
+    }
+    if (args.compile.is_some() || args.metrics) && args.stream {
+        return Err(Error::Unsupported(
+            "`--compile` and `--metrics` cannot be combined with `--stream`".into(),
+        ));
+    }
+    if (args.preserve_lines || args.since.is_some()) && args.stream {
+        return Err(Error::Unsupported(
+            "`--preserve-lines` and `--since` cannot be combined with `--stream`".into(),
+        ));
+    }
+    if args.undo && args.in_place.is_none() {
+        return Err(Error::Unsupported(
+            "`--undo` only works with `--in-place`".into(),
+        ));
+    }
+    if args.interactive && (args.stream || args.filter) {
+        return Err(Error::Unsupported(
+            "`--interactive` cannot be combined with `--stream` or `--filter`".into(),
+        ));
+    }
+    if (args.newline.is_some() || args.encoding.is_some()) && args.stream {
+        return Err(Error::Unsupported(
+            "`--newline` and `--encoding` cannot be combined with `--stream`".into(),
+        ));
+    }
+    if args.dry_run && (args.stream || args.compile.is_some()) {
+        return Err(Error::Unsupported(
+            "`--dry-run` cannot be combined with `--stream` or `--compile`".into(),
+        ));
+    }
+    if args.stream {
+        if args.audit_log.is_some() || args.write_map_typ.is_some() {
+            return Err(Error::Unsupported(
+                "`--audit-log` and `--write-map-typ` cannot be combined with `--stream`".into(),
+            ));
+        }
+        let mut context = Context::new(&options)?;
+        set_filter(args, &mut context);
+        note_date_offset(args, &context);
+        return stream(args, &mut context);
+    }
+
+    let (code, encoding) = read_input(args)?;
+    let mut context = context_for(args, &code, options)?;
+    let mut audit_log = open_audit_log(args, &mut context)?;
+    let (file, mut edits) = plan_input(args, &code, &mut context)?;
+    let mut manual = None;
+    if args.interactive {
+        let mut answers = open_answers(args)?;
+        let color = args.color.enabled(io::stderr().is_terminal());
+        let reviewed = review::review(&mut answers, &file, &code, edits, args.review_by, color)?;
+        edits = reviewed.edits;
+        manual = Some(reviewed.manual);
+    }
+    if args.dry_run {
+        let mut out = io::stdout().lock();
+        let color = args.color.enabled(io::stdout().is_terminal());
+        diff::write(&mut out, &file, &code, &edits, color)?;
+        return Ok(out.flush()?);
+    }
+    if let Some(path) = &args.write_map_typ {
+        let mut map = ReplacementMap::new();
+        map.add(&code, &replacements(&context, &edits));
+        write_map(args, path, &map)?;
+    }
+    if let Some(log) = &mut audit_log {
+        let mut records = context.take_audit();
+        if let Some(manual) = &manual {
+            records = reviewed_records(&records, &edits, manual);
+        }
+        log.write(&file, &code, &records)?;
+        log.flush()?;
+    }
+    if args.preserve_lines {
+        edits = typst_mutilate::preserve_lines(&code, edits);
+    }
+    if let Some(newline) = args.newline {
+        edits = typst_mutilate::convert_newlines(&code, edits, newline);
+    }
+    let output = typst_mutilate::apply_edits(&code, &edits);
+    check_lines(args, &file, &code, &output)?;
+    if args.metrics {
+        let original = Metrics::measure(&code, &context);
+        let mutilated = Metrics::measure(&output, &context);
+        Metrics::write_comparison(&mut io::stderr().lock(), &original, &mutilated)?;
+    }
+
+    // The other files of the project aren't mutilated, so they must not end up in the PDF.
+    if args.compile.is_some() {
+        if let Some(other) = typst_mutilate::project_files(&output, input_mode(args)).first() {
+            return Err(Error::Unsupported(format!(
+                "{file} refers to `{other}`, which `--compile` would read unmutilated; \
+                 mutilate and compile the whole project with `batch --compile` instead"
+            )));
+        }
+    }
     if let Some(path) = &args.in_place {
-        std::fs::write(path, &output)?;
+        if args.undo {
+            typst_mutilate::record_undo(path, &code, &edits)?;
+        }
+        std::fs::write(path, encoding.encode(&output)?)?;
+        if let Some(pdf) = &args.compile {
+            compile_alone(&output, pdf)?;
+        }
+    } else if let Some(pdf) = &args.compile {
+        compile_alone(&output, pdf)?;
     } else {
-        std::io::stdout().write_all(&output)?;
+        std::io::stdout().write_all(&encoding.encode(&output)?)?;
     }
 
     Ok(())
 }
 
-struct Context {
-    rng: Xoshiro256PlusPlus,
-    aggressive: bool,
-    language: Lang,
-    by_length: BTreeMap<usize, Vec<EcoString>>,
-    by_hyphenation: BTreeMap<EcoVec<u8>, Vec<EcoString>>,
-}
-
-fn build_context(args: &Args) -> io::Result<Context> {
-    let rng = Xoshiro256PlusPlus::from_rng(rand::thread_rng()).unwrap();
-
-    let language = {
-        if args.language.len() != 2 {
-            panic!("Language is not two ascii characters long.");
-        }
-        let bytes = args.language.as_bytes();
-        Lang::from_iso([bytes[0], bytes[1]]).expect("language not supported")
-    };
-
-    let mut by_length: BTreeMap<usize, Vec<EcoString>> = BTreeMap::new();
-    let mut by_hyphenation: BTreeMap<EcoVec<u8>, Vec<EcoString>> = BTreeMap::new();
-    if let Some(path) = &args.wordlist {
-        let mut reader = BufReader::new(File::open(path)?);
-        let mut line = String::new();
-        while reader.read_line(&mut line)? != 0 {
-            let word = EcoString::from(line.trim_end());
-            by_length
-                .entry(word.chars().count())
-                .or_default()
-                .push(word.clone());
-            let hyphenation = hypher::hyphenate(&word, language)
-                .map(|syllable| syllable.chars().count().try_into().unwrap_or(u8::MAX))
-                .collect();
-            by_hyphenation.entry(hyphenation).or_default().push(word);
-            line.clear();
-        }
-    }
-
-    Ok(Context {
-        rng,
-        aggressive: args.aggressive,
-        language,
-        by_length,
-        by_hyphenation,
-    })
+/// Open the input of the answers of `--interactive`: stdin if the document is a file, and the
+/// terminal otherwise.
+fn open_answers(args: &Args) -> Result<Box<dyn io::BufRead>, Error> {
+    if args.in_place.is_some() {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let terminal = if cfg!(windows) { "CONIN$" } else { "/dev/tty" };
+    match File::open(terminal) {
+        Ok(file) => Ok(Box::new(BufReader::new(file))),
+        Err(_) => Err(Error::Unsupported(
+            "`--interactive` needs a terminal to read the answers from".into(),
+        )),
+    }
+}
+
+/// The audit records of the edits left after `--interactive`, where those with `manual`
+/// ranges are recorded with the strategy `manual`.
+fn reviewed_records(
+    records: &[AuditRecord],
+    edits: &[Edit],
+    manual: &[std::ops::Range<usize>],
+) -> Vec<AuditRecord> {
+    let mut reviewed = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let i = records.partition_point(|record| record.range.start < edit.range.start);
+        let record = records.get(i);
+        if manual.contains(&edit.range) {
+            reviewed.push(AuditRecord {
+                range: edit.range.clone(),
+                kind: record.and_then(|record| record.kind),
+                strategy: "manual",
+            });
+        } else if let Some(record) = record.filter(|record| record.range == edit.range) {
+            reviewed.push(record.clone());
+        }
+    }
+    reviewed
+}
+
+/// Read the document from `--in-place` or stdin, returning it with its encoding.
+fn read_input(args: &Args) -> io::Result<(String, Encoding)> {
+    let bytes = match &args.in_place {
+        Some(path) => std::fs::read(path)?,
+        None => {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+    let name = input_path(args).map_or("<stdin>".into(), |path| path.display().to_string());
+    decode(args, &name, bytes)
+}
+
+/// Read a document file, returning it with its encoding.
+fn read_document(args: &Args, path: &Path) -> io::Result<(String, Encoding)> {
+    decode(args, &path.display().to_string(), std::fs::read(path)?)
+}
+
+/// Decode a document with `--encoding` or the encoding it seems to have.
+fn decode(args: &Args, name: &str, bytes: Vec<u8>) -> io::Result<(String, Encoding)> {
+    let encoding = match args.encoding {
+        Some(encoding) => encoding,
+        None => {
+            let encoding = Encoding::detect(&bytes);
+            if encoding != Encoding::Utf8 {
+                log::info!("reading {name} as {encoding}, use `--encoding` to override");
+            }
+            encoding
+        }
+    };
+    Ok((encoding.decode(bytes)?, encoding))
+}
+
+/// Create the context for mutilating a single document.
+fn context_for(args: &Args, code: &str, options: MutilateOptions) -> Result<Context, Error> {
+    let options = if args.filter {
+        options.seed(filter_seed(code, args.seed))
+    } else {
+        options
+    };
+    #[cfg(feature = "detect")]
+    let options = match args.language {
+        Some(_) => options,
+        None => detect_language(code, options, !args.filter)?,
+    };
+    let mut context = Context::new(&options)?;
+    set_filter(args, &mut context);
+    note_date_offset(args, &context);
+    Ok(context)
 }
 
-fn mutilate<W: Write>(
-    syntax: &SyntaxNode,
+/// Plan the edits of a single document, returning the name of its file for messages and the
+/// edits.
+fn plan_input(
+    args: &Args,
+    code: &str,
     context: &mut Context,
-    output: &mut W,
-) -> io::Result<()> {
-    match syntax.kind() {
-        SyntaxKind::Text => mutilate_text(syntax.text(), context, output),
-        SyntaxKind::LineComment => {
-            write!(output, "//")?;
-            let content = &syntax.text()[2..];
-            mutilate_text(content, context, output)?;
-            Ok(())
-        }
-        SyntaxKind::BlockComment => {
-            write!(output, "/*")?;
-            let content = &syntax.text()[2..syntax.text().len() - 2];
-            mutilate_text(content, context, output)?;
-            write!(output, "*/")?;
-            Ok(())
-        }
-        SyntaxKind::Str if context.aggressive => {
-            write!(output, "\"")?;
-            let content = &syntax.text()[1..syntax.text().len() - 1];
-            mutilate_text(content, context, output)?;
-            write!(output, "\"")?;
-            Ok(())
-        }
-        SyntaxKind::Raw => {
-            let raw: ast::Raw = syntax.cast().unwrap();
-            let raw_text = syntax.clone().into_text();
-            let backticks = raw_text.split(|c| c != '`').next().unwrap();
-            write!(output, "{backticks}")?;
-
-            let mut text = raw_text
-                .trim_start_matches('`')
-                .strip_suffix(backticks)
-                .unwrap();
-            if let Some(lang) = raw.lang() {
-                let lang = lang.get().as_str();
-                text = text.strip_prefix(lang).unwrap();
-                write!(output, "{}", lang)?;
-            }
-
-            mutilate_text(text, context, output)?;
-            write!(output, "{backticks}")?;
-            Ok(())
-        }
-        SyntaxKind::Link => {
-            let (scheme, rest) = syntax.text().split_once(':').unwrap();
-            write!(output, "{scheme}:")?;
-            mutilate_text(rest, context, output)
-        }
-        SyntaxKind::ModuleInclude | SyntaxKind::ModuleImport => write_node(syntax, output),
-        _ if syntax.children().next().is_some() => {
-            for child in syntax.children() {
-                mutilate(child, context, output)?;
-            }
-            Ok(())
-        }
-        _ => write_node(syntax, output),
-    }
-}
-
-fn write_node<W: Write>(syntax: &SyntaxNode, output: &mut W) -> io::Result<()> {
-    if syntax.children().next().is_some() {
-        for child in syntax.children() {
-            write_node(child, output)?;
+) -> Result<(String, Vec<Edit>), Error> {
+    let file = match input_path(args) {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => "<stdin>".into(),
+    };
+    if args.since.is_some() {
+        let path = input_path(args).ok_or_else(|| {
+            Error::Unsupported(
+                "`--since` needs the path of the document, given by `-i` or `--stdin-filepath`"
+                    .into(),
+            )
+        })?;
+        context.set_changed_lines(changed_lines(args, path, code)?);
+    }
+    match typst_mutilate::plan_str_with(code, context) {
+        Err(Error::Syntax(_)) => Err(syntax_errors(&file, code, input_mode(args))),
+        result => Ok((file, result?)),
+    }
+}
+
+/// Write the replacements of a single document without writing the document.
+fn map(args: &Args, map: &MapCommand, options: MutilateOptions) -> Result<(), Error> {
+    let (code, _) = read_input(args)?;
+    let mut context = context_for(args, &code, options)?;
+    let (_, edits) = plan_input(args, &code, &mut context)?;
+    let mut replacements = ReplacementMap::new();
+    replacements.add(&code, &self::replacements(&context, &edits));
+    let salt = args.map_salt.as_ref().map(String::as_bytes);
+    match &map.output {
+        Some(path) => write_map(args, path, &replacements)?,
+        None => {
+            let mut out = io::stdout().lock();
+            replacements.write_typst(&mut out, salt)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Restore documents from their undo files.
+fn undo(args: &Args, undo: &UndoCommand) -> Result<(), Error> {
+    for path in &undo.files {
+        let (current, encoding) = read_document(args, path)?;
+        let original = typst_mutilate::restore_undo(path, &current)?;
+        std::fs::write(path, encoding.encode(&original)?)?;
+        std::fs::remove_file(typst_mutilate::undo_path(path))?;
+        log::info!("restored {}", path.display());
+    }
+    Ok(())
+}
+
+/// Check the invariants of mutilating the documents of a corpus.
+fn selftest(
+    args: &Args,
+    selftest: &SelftestCommand,
+    options: &MutilateOptions,
+) -> Result<(), Error> {
+    let mut files = Vec::new();
+    collect_files(&selftest.dir, None, &mut files)?;
+    let mut documents = Vec::new();
+    for path in files.into_iter().filter(|path| is_document(path)) {
+        let (format, mode) = file_format(args, &path);
+        let (code, _) = read_document(args, &path)?;
+        // Documents that don't parse can't be checked.
+        if format == Format::Typst && selftest::first_error(&code, mode).is_some() {
+            log::warn!("skipping {} due to syntax errors", path.display());
+            continue;
+        }
+        documents.push(selftest::Document {
+            path,
+            format,
+            mode,
+            code,
+        });
+    }
+
+    let mut out = io::stdout().lock();
+    let summary = selftest::run(
+        &mut out,
+        &documents,
+        options,
+        selftest.seeds,
+        selftest.tolerance,
+    )?;
+    out.flush()?;
+    if summary.failures > 0 {
+        return Err(Error::Unsupported(format!(
+            "{} of {} failed",
+            summary.failures,
+            counted(summary.runs, "run", "runs")
+        )));
+    }
+    log::info!(
+        "{} of {} passed",
+        counted(summary.runs, "run", "runs"),
+        counted(documents.len(), "document", "documents")
+    );
+    Ok(())
+}
+
+/// Reduce a document to a minimal one for which the check still succeeds.
+fn reduce(args: &Args, reduce: &ReduceCommand, options: MutilateOptions) -> Result<(), Error> {
+    let (code, encoding) = read_document(args, &reduce.file)?;
+    let (_, mode) = file_format(args, &reduce.file);
+    // The copy is next to the document, so that its imports resolve.
+    let mut name = format!(".typst-mutilate-reduce-{}", std::process::id());
+    if let Some(extension) = reduce.file.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    let path = reduce.file.with_file_name(name);
+    let command = if reduce.check.contains("{}") {
+        reduce
+            .check
+            .replace("{}", &shell_quote(&path.to_string_lossy()))
+    } else {
+        format!("{} {}", reduce.check, shell_quote(&path.to_string_lossy()))
+    };
+
+    let mut checks = 0;
+    let mut failure = None;
+    let mut check = |code: &str| {
+        checks += 1;
+        let result = encoding.encode(code).and_then(|bytes| {
+            std::fs::write(&path, bytes)?;
+            run_shell(&command)
+        });
+        match result {
+            Ok(success) => success,
+            Err(err) => {
+                failure.get_or_insert(err);
+                false
+            }
+        }
+    };
+    let result = if check(&code) {
+        let mut reduced = typst_mutilate::reduce(&code, mode, &mut check);
+        if reduce.mutilate {
+            let mutilated = typst_mutilate::mutilate_str_with(
+                &reduced,
+                &mut context_for(args, &reduced, options)?,
+            )?;
+            if check(&mutilated) {
+                reduced = mutilated;
+            } else {
+                log::warn!("the check fails on the mutilated document, writing it as it is");
+            }
         }
+        Ok(reduced)
     } else {
-        write!(output, "{}", syntax.text())?;
+        Err(Error::Unsupported(format!(
+            "`{command}` doesn't succeed on {}",
+            reduce.file.display()
+        )))
+    };
+    let _ = std::fs::remove_file(&path);
+    if let Some(err) = failure {
+        return Err(err.into());
+    }
+    let reduced = result?;
+    log::info!(
+        "reduced {} bytes to {} bytes with {checks} checks",
+        code.len(),
+        reduced.len()
+    );
+    let bytes = encoding.encode(&reduced)?;
+    match &reduce.output {
+        Some(path) => std::fs::write(path, bytes)?,
+        None => io::stdout().write_all(&bytes)?,
     }
     Ok(())
 }
 
-fn mutilate_text<W: Write>(text: &str, context: &mut Context, output: &mut W) -> io::Result<()> {
-    let mut remaining = text;
-    loop {
-        let split = |c: char| !c.is_alphanumeric();
-        let next_remaining = remaining.trim_start_matches(split);
-        let Some(word) = next_remaining.split(split).find(|s| !s.is_empty()) else {
-            break;
+/// Fail if `--preserve-lines` is given and a mutilated document doesn't have the lines of its
+/// original.
+fn check_lines(args: &Args, file: &str, code: &str, output: &str) -> Result<(), Error> {
+    if !args.preserve_lines {
+        return Ok(());
+    }
+    match typst_mutilate::changed_line(code, output) {
+        Some(line) => Err(Error::Unsupported(format!(
+            "mutilating {file} changed its lines from line {line} on, which `--preserve-lines` forbids"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// The lines of a document that changed since the revision given by `--since`, as `git diff`
+/// shows them, or `None` without `--since` or if the document is new since the revision.
+///
+/// The document is compared as it is given, which may differ from the file when it is read
+/// from stdin.
+fn changed_lines(args: &Args, path: &Path, code: &str) -> Result<Option<Vec<Range<usize>>>, Error> {
+    let Some(rev) = &args.since else {
+        return Ok(None);
+    };
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let git = |arguments: &[&str]| {
+        std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(arguments)
+            .stderr(std::process::Stdio::null())
+            .output()
+    };
+    let commit = format!("{rev}^{{commit}}");
+    if !git(&["rev-parse", "--verify", "--quiet", &commit])?
+        .status
+        .success()
+    {
+        return Err(Error::Unsupported(format!(
+            "`{rev}` is no revision of the git repository of {}",
+            path.display()
+        )));
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let old = git(&["show", &format!("{rev}:./{name}")])?;
+    if !old.status.success() {
+        log::info!(
+            "{} is new since {rev}, mutilating all of it",
+            path.display()
+        );
+        return Ok(None);
+    }
+
+    let base = std::env::temp_dir().join(format!(".typst-mutilate-since-{}", std::process::id()));
+    let (before, after) = (base.with_extension("old"), base.with_extension("new"));
+    let diff = std::fs::write(&before, &old.stdout)
+        .and_then(|()| std::fs::write(&after, code))
+        .and_then(|()| {
+            std::process::Command::new("git")
+                .args([
+                    "diff",
+                    "--no-index",
+                    "--no-color",
+                    "--no-ext-diff",
+                    "--unified=0",
+                ])
+                .arg(&before)
+                .arg(&after)
+                .output()
+        });
+    let _ = std::fs::remove_file(&before);
+    let _ = std::fs::remove_file(&after);
+    let diff = diff?;
+    // `git diff --no-index` exits with 1 if the files differ.
+    if !matches!(diff.status.code(), Some(0 | 1)) {
+        return Err(Error::Unsupported(format!(
+            "comparing {} with `{rev}` failed",
+            path.display()
+        )));
+    }
+    let lines = String::from_utf8_lossy(&diff.stdout)
+        .lines()
+        .filter_map(hunk_lines)
+        .collect();
+    Ok(Some(lines))
+}
+
+/// The lines of the new file that a hunk header of a diff like `@@ -3,2 +3,4 @@` covers.
+fn hunk_lines(header: &str) -> Option<Range<usize>> {
+    let new = header
+        .strip_prefix("@@ ")?
+        .split(' ')
+        .find_map(|part| part.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (new.parse().ok()?, 1),
+    };
+    Some(start..start + count)
+}
+
+/// Run a shell command, returning whether it succeeded, without showing its output.
+fn run_shell(command: &str) -> io::Result<bool> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = std::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = std::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell
+        .arg(command)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()?;
+    Ok(status.success())
+}
+
+/// Check the entries of an audit log against the original documents.
+fn verify(args: &Args, verify: &VerifyCommand) -> Result<(), Error> {
+    let log = std::fs::read_to_string(&verify.log)?;
+    let mut documents = std::collections::HashMap::new();
+    let (mut verified, mut mismatched) = (0, 0);
+    for (i, line) in log.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(entry) = AuditEntry::parse(line) else {
+            return Err(Error::Unsupported(format!(
+                "{}:{}: not an audit log entry",
+                verify.log.display(),
+                i + 1
+            )));
         };
-        let whitespace = &remaining[..remaining.len() - next_remaining.len()];
-        remaining = &next_remaining[word.len()..];
+        let path =
+            match &verify.original {
+                Some(path) => path.clone(),
+                None if entry.file == "<stdin>" => return Err(Error::Unsupported(
+                    "the log has entries of documents read from stdin, give them with `--original`"
+                        .into(),
+                )),
+                None => PathBuf::from(&entry.file),
+            };
+        if !documents.contains_key(&path) {
+            let (code, _) = read_document(args, &path)?;
+            documents.insert(path.clone(), code);
+        }
+        if entry.matches(&documents[&path], verify.salt.as_bytes()) {
+            verified += 1;
+        } else {
+            log::error!(
+                "{}:{}: {} bytes {}..{} don't match",
+                verify.log.display(),
+                i + 1,
+                path.display(),
+                entry.range.start,
+                entry.range.end
+            );
+            if let Some(format) = args.annotations {
+                let file = path.to_string_lossy();
+                let annotation = Annotation::at(
+                    &file,
+                    &documents[&path],
+                    entry.range.start,
+                    "mismatch",
+                    format!(
+                        "the entry on line {} of {} doesn't match",
+                        i + 1,
+                        verify.log.display()
+                    ),
+                );
+                format.write(&mut io::stdout().lock(), &annotation)?;
+            }
+            mismatched += 1;
+        }
+    }
+
+    if mismatched > 0 {
+        return Err(Error::Unsupported(format!(
+            "{mismatched} of {} {}",
+            counted(verified + mismatched, "entry", "entries"),
+            if mismatched == 1 {
+                "doesn't match"
+            } else {
+                "don't match"
+            }
+        )));
+    }
+    log::info!("verified {}", counted(verified, "entry", "entries"));
+    Ok(())
+}
+
+/// The mode of the document given by `--mode` or the extension of its path.
+fn input_mode(args: &Args) -> Mode {
+    let extension = input_path(args).and_then(|path| path.extension()?.to_str());
+    args.mode
+        .or_else(|| Mode::from_extension(extension?))
+        .unwrap_or(Mode::Markup)
+}
 
-        write!(output, "{whitespace}")?;
-        mutilate_word(word, context, output)?;
+/// The path of the document, given by `--in-place` or `--stdin-filepath`.
+fn input_path(args: &Args) -> Option<&Path> {
+    args.in_place.as_deref().or(args.stdin_filepath.as_deref())
+}
+
+/// Report the syntax errors of a typst document with their positions, returning the error to
+/// fail with.
+fn syntax_errors(file: &str, code: &str, mode: Mode) -> Error {
+    let syntax = match mode {
+        Mode::Markup => typst_syntax::parse(code),
+        Mode::Code => typst_syntax::parse_code(code),
+        Mode::Math => typst_syntax::parse_math(code),
+    };
+    let mut errors = Vec::new();
+    find_errors(&typst_syntax::LinkedNode::new(&syntax), &mut errors);
+    for (offset, message) in &errors {
+        let before = &code[..*offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        let label = logger::label(log::Level::Error);
+        eprintln!("{file}:{line}:{column}: {label} {message}");
+    }
+    Error::Unsupported(format!(
+        "{file} has {}",
+        counted(errors.len(), "syntax error", "syntax errors")
+    ))
+}
+
+/// Collect the offsets and messages of the error nodes in a tree.
+fn find_errors(node: &typst_syntax::LinkedNode, errors: &mut Vec<(usize, String)>) {
+    if !node.erroneous() {
+        return;
+    }
+    if node.kind() == SyntaxKind::Error {
+        for error in node.errors() {
+            errors.push((node.offset(), error.message.to_string()));
+        }
+        return;
+    }
+    for child in node.children() {
+        find_errors(&child, errors);
+    }
+}
+
+/// The edits that replace text rather than move parts of the document there.
+fn replacements(context: &Context, edits: &[Edit]) -> Vec<Edit> {
+    edits
+        .iter()
+        .filter(|edit| {
+            context
+                .moved()
+                .binary_search_by_key(&edit.range.start, |range| range.start)
+                .is_err()
+        })
+        .cloned()
+        .collect()
+}
+
+/// Write the replacements to the `--write-map-typ` file.
+fn write_map(args: &Args, path: &Path, map: &ReplacementMap) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    map.write_typst(&mut out, args.map_salt.as_ref().map(String::as_bytes))?;
+    out.flush()
+}
+
+/// Compile a document that refers to no other files in a directory of its own, so that nothing
+/// of the project it was read from ends up in the PDF.
+fn compile_alone(code: &str, pdf: &Path) -> Result<(), Error> {
+    let root = std::env::temp_dir().join(format!("typst-mutilate-compile-{}", std::process::id()));
+    std::fs::create_dir_all(&root)?;
+    let path = root.join("main.typ");
+    let result = std::fs::write(&path, code)
+        .map_err(Error::from)
+        .and_then(|()| compile(&path, pdf, Some(&root)));
+    let _ = std::fs::remove_dir_all(&root);
+    result
+}
+
+/// Compile a document with the typst compiler, which is `$TYPST` or `typst` on the path.
+fn compile(input: &Path, pdf: &Path, root: Option<&Path>) -> Result<(), Error> {
+    let typst = std::env::var_os("TYPST").unwrap_or_else(|| "typst".into());
+    let mut command = std::process::Command::new(&typst);
+    command.arg("compile");
+    if let Some(root) = root {
+        command.arg("--root").arg(root);
+    }
+    let status = command.arg(input).arg(pdf).status().map_err(|err| {
+        if err.kind() == io::ErrorKind::NotFound {
+            Error::Unsupported(format!(
+                "the typst compiler `{}` wasn't found, install it or set `TYPST`",
+                typst.to_string_lossy()
+            ))
+        } else {
+            Error::Io(err)
+        }
+    })?;
+    if !status.success() {
+        return Err(Error::Unsupported(format!(
+            "compiling the mutilated {} failed",
+            input.display()
+        )));
     }
-    write!(output, "{remaining}")?;
     Ok(())
 }
 
-/// The minimum number of words that have to be available in a list in order to choose an item.
-const MINIMUM_WORD_COUNT: usize = 16;
+/// Use the language of the document, telling the user what was chosen if `notes` are enabled.
+#[cfg(feature = "detect")]
+fn detect_language(
+    code: &str,
+    options: MutilateOptions,
+    notes: bool,
+) -> Result<MutilateOptions, Error> {
+    match typst_mutilate::detect_language(code, &options)? {
+        Some(language) => {
+            if notes {
+                log::info!("detected {language:?} text, use `--language` to override");
+            }
+            Ok(options.language(language))
+        }
+        None => {
+            if notes {
+                log::info!("could not detect a language with a wordlist, assuming English");
+            }
+            Ok(options)
+        }
+    }
+}
+
+/// A count with the singular or plural of a noun, like `1 file` or `2 files`.
+fn counted(count: usize, singular: &str, plural: &str) -> String {
+    format!("{count} {}", if count == 1 { singular } else { plural })
+}
 
-const CHARSET_TEXT: &[char] = &[
-    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
-    't', 'u', 'v', 'w', 'x', 'y', 'z', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L',
-    'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
-];
-const CHARSET_DIGITS: &[char] = &['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+/// The seed for `--filter`, derived from the contents of the document and `--seed`.
+fn filter_seed(code: &str, seed: Option<u64>) -> u64 {
+    let hash = typst_mutilate::content_hash(code.as_bytes());
+    u64::from_str_radix(&hash[..16], 16).unwrap() ^ seed.unwrap_or(0)
+}
 
-fn mutilate_word<W: Write>(word: &str, context: &mut Context, output: &mut W) -> io::Result<()> {
-    let length = word.chars().count();
-    if word.chars().all(|c| c.is_numeric()) {
-        for _ in 0..length {
-            let digit = CHARSET_DIGITS.choose(&mut context.rng).unwrap();
-            write!(output, "{digit}")?;
+/// Apply the `--node-action` overrides to a context.
+fn set_filter(args: &Args, context: &mut Context) {
+    if !args.node_action.is_empty() || !args.kinds.is_empty() || !args.skip_kinds.is_empty() {
+        let mut filter = KindFilter::new();
+        for Kinds(kinds) in &args.kinds {
+            filter.only(kinds.iter().copied());
+        }
+        for kind in args.skip_kinds.iter().flat_map(|Kinds(kinds)| kinds) {
+            filter.set(*kind, NodeAction::Copy);
+        }
+        for KindAction { kind, action } in &args.node_action {
+            filter.set(*kind, *action);
+        }
+        context.set_filter(filter);
+    }
+}
+
+/// Tell the user how far dates are moved if the offset was chosen at random.
+fn note_date_offset(args: &Args, context: &Context) {
+    if args.date_offset.is_none() && !args.filter {
+        if let Some(days) = context.date_offset() {
+            log::info!("shifting dates by {days} days, use `--date-offset` to choose");
+        }
+    }
+}
+
+/// Count the words in a corpus and write the frequent ones as a wordlist.
+fn build_wordlist(
+    args: &Args,
+    build: &BuildWordlist,
+    options: &MutilateOptions,
+) -> Result<(), Error> {
+    let mut counter = WordCounter::new(options);
+    set_filter(args, counter.context_mut());
+
+    let mut files = Vec::new();
+    for input in &build.inputs {
+        if input.is_dir() {
+            let mut found = Vec::new();
+            collect_files(input, None, &mut found)?;
+            files.extend(found.into_iter().filter(|path| is_document(path)));
+        } else {
+            files.push(input.clone());
+        }
+    }
+
+    for path in &files {
+        let (format, mode) = file_format(args, path);
+        let (code, _) = read_document(args, path)?;
+        match counter.add(&code, format, mode) {
+            Ok(()) => {}
+            Err(Error::Syntax(_)) => {
+                log::warn!("skipping {} due to syntax errors", path.display());
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    let mut out: Box<dyn Write> = match &build.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    for (word, count) in counter.words() {
+        let length = word.chars().count();
+        if count < build.min_count
+            || length < build.min_length
+            || build.max_length.is_some_and(|max| length > max)
+        {
+            continue;
+        }
+        if build.no_counts {
+            writeln!(out, "{word}")?;
+        } else {
+            writeln!(out, "{word}\t{count}")?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Open the audit log if one is requested and enable auditing in the context.
+fn open_audit_log(
+    args: &Args,
+    context: &mut Context,
+) -> Result<Option<AuditLog<BufWriter<File>>>, Error> {
+    let Some(path) = &args.audit_log else {
+        return Ok(None);
+    };
+    // A random salt would have to be kept somewhere else than in the log, where it would make
+    // the hashes easy to reverse for short words, so it is up to the user.
+    let Some(salt) = args.audit_salt.clone() else {
+        return Err(Error::Unsupported(
+            "`--audit-log` requires `--audit-salt`, which is needed to verify the log".into(),
+        ));
+    };
+    let file = File::options().create(true).append(true).open(path)?;
+    context.enable_audit();
+    Ok(Some(AuditLog::new(BufWriter::new(file), salt)))
+}
+
+/// How to interpret a file, guessed from its extension unless `--format` or `--mode` is given.
+fn file_format(args: &Args, path: &Path) -> (Format, Mode) {
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let format = args
+        .format
+        .or_else(|| Format::from_extension(extension?))
+        .unwrap_or(Format::Plain);
+    let mode = args
+        .mode
+        .or_else(|| Mode::from_extension(extension?))
+        .unwrap_or(Mode::Markup);
+    (format, mode)
+}
+
+/// Whether a file is a document of a known format.
+fn is_document(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| Format::from_extension(ext.to_str()?))
+        .is_some()
+}
+
+/// Find the entrypoint of the package among the files of a batch run in its `typst.toml` and
+/// choose the new names of the bindings of its typst files.
+fn analyze_package(
+    args: &Args,
+    files: &[(PathBuf, PathBuf)],
+    context: &mut Context,
+) -> Result<Package, Error> {
+    let mut sources = Vec::new();
+    let mut entrypoint = None;
+    for (path, relative) in files {
+        if relative == Path::new("typst.toml") {
+            let toml = std::fs::read_to_string(path)?;
+            entrypoint = Package::entrypoint(&toml).map(PathBuf::from);
+        } else if path.extension().is_some_and(|ext| ext == "typ") {
+            let (code, _) = decode(args, &path.display().to_string(), std::fs::read(path)?)?;
+            sources.push((relative.as_path(), code));
+        }
+    }
+    let Some(entrypoint) = entrypoint else {
+        return Err(Error::Unsupported(
+            "`--package` needs a `typst.toml` with an `entrypoint` in the inputs".into(),
+        ));
+    };
+    let sources = sources.iter().map(|(path, code)| (*path, code.as_str()));
+    let package = Package::new(sources, &entrypoint, context)?;
+    log::debug!("renaming {} bindings", package.renames().count());
+    Ok(package)
+}
+
+/// Recursively find the files in a directory, skipping hidden entries and `exclude`.
+fn collect_files(dir: &Path, exclude: Option<&Path>, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            if exclude.is_some_and(|exclude| path.canonicalize().is_ok_and(|path| path == exclude))
+            {
+                continue;
+            }
+            collect_files(&path, exclude, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Mutilate all documents of the inputs into the output directory, skipping files that haven't
+/// changed since the last run according to the manifest in the output directory.
+fn batch(args: &Args, batch: &BatchCommand, options: &MutilateOptions) -> Result<(), Error> {
+    std::fs::create_dir_all(&batch.output)?;
+    let output_dir = batch.output.canonicalize()?;
+    let manifest_path = batch.output.join(".typst-mutilate-manifest");
+    let mut manifest = Manifest::load(&manifest_path)?;
+    let settings = settings_fingerprint(args)?;
+
+    // Pairs of input files and their path relative to the output directory.
+    let mut files = Vec::new();
+    for input in &batch.inputs {
+        if input.is_dir() {
+            let mut found = Vec::new();
+            collect_files(input, Some(&output_dir), &mut found)?;
+            for path in found {
+                let relative = path.strip_prefix(input).unwrap_or(&path).to_owned();
+                files.push((path, relative));
+            }
+        } else {
+            let name = input.file_name().unwrap_or_default();
+            files.push((input.clone(), PathBuf::from(name)));
         }
-        return Ok(());
     }
 
-    // Find a word with the same hyphenation pattern.
-    let hyphenation: EcoVec<u8> = hypher::hyphenate(word, context.language)
-        .map(|syllable| syllable.chars().count().try_into().unwrap_or(u8::MAX))
+    // Projects ship the names of their authors in metadata, which is scrubbed consistently
+    // with the documents.
+    let mut options = options.clone().scrub_metadata(true);
+    if batch.package {
+        options = options.aggressive(true);
+    }
+    let mut context = Context::new(&options)?;
+    set_filter(args, &mut context);
+    note_date_offset(args, &context);
+    if batch.package {
+        let package = analyze_package(args, &files, &mut context)?;
+        context.set_package(package);
+    }
+    let mut audit_log = open_audit_log(args, &mut context)?;
+    let mut map = ReplacementMap::new();
+    let (mut processed, mut skipped, mut failed) = (0, 0, 0);
+    let mut progress = Progress::new(files.len());
+    for (path, relative) in &files {
+        progress.show(relative, context.stats());
+        let target = batch.output.join(relative);
+        let input = std::fs::read(path)?;
+        let package_manifest = path.file_name().is_some_and(|name| name == "typst.toml");
+        let document = package_manifest || is_document(path);
+        let settings = if document { settings.as_str() } else { "copy" };
+        // The new names of the bindings of a package depend on all of its files, and the
+        // changed lines on the revision.
+        let force = batch.force || batch.package || args.since.is_some();
+        if !force && manifest.is_fresh(relative, &input, settings, &target) {
+            skipped += 1;
+            continue;
+        }
+
+        let output = if document {
+            let (code, encoding) = decode(args, &path.display().to_string(), input.clone())?;
+            let before = context.stats();
+            let result = if package_manifest {
+                typst_mutilate::mutilate_package_manifest(&code, &mut context)
+            } else {
+                let (format, mode) = file_format(args, path);
+                context.set_format(format);
+                context.set_mode(mode);
+                context.set_changed_lines(changed_lines(args, path, &code)?);
+                typst_mutilate::plan_str_with(&code, &mut context).map(|mut edits| {
+                    map.add(&code, &replacements(&context, &edits));
+                    if args.preserve_lines {
+                        edits = typst_mutilate::preserve_lines(&code, edits);
+                    }
+                    if let Some(newline) = args.newline {
+                        edits = typst_mutilate::convert_newlines(&code, edits, newline);
+                    }
+                    typst_mutilate::apply_edits(&code, &edits)
+                })
+            };
+            let records = context.take_audit();
+            match result {
+                Ok(output) => {
+                    check_lines(args, &path.to_string_lossy(), &code, &output)?;
+                    if let Some(log) = &mut audit_log {
+                        log.write(&path.to_string_lossy(), &code, &records)?;
+                    }
+                    let stats = context.stats();
+                    log::debug!(
+                        "mutilated {}: {} words, {} random fallbacks",
+                        path.display(),
+                        stats.words - before.words,
+                        stats.fallbacks - before.fallbacks
+                    );
+                    encoding.encode(&output)?
+                }
+                Err(Error::Syntax(_)) => {
+                    progress.clear();
+                    log::warn!("skipping {} due to syntax errors", path.display());
+                    failed += 1;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            }
+        } else {
+            input.clone()
+        };
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, &output)?;
+        manifest.record(relative, &input, settings, &output);
+        processed += 1;
+    }
+
+    progress.clear();
+
+    let relatives: HashSet<_> = files
+        .iter()
+        .map(|(_, relative)| relative.as_path())
         .collect();
-    if let Some(words) = context.by_hyphenation.get(&hyphenation) {
-        if words.len() >= MINIMUM_WORD_COUNT {
-            if let Some(word) = words.choose(&mut context.rng) {
-                return write!(output, "{word}");
+    manifest.retain(|path| relatives.contains(path));
+    manifest.save(&manifest_path)?;
+    if let Some(path) = &args.write_map_typ {
+        write_map(args, path, &map)?;
+    }
+    if let Some(log) = &mut audit_log {
+        log.flush()?;
+    }
+
+    log::info!(
+        "mutilated {}, skipped {}",
+        counted(processed, "file", "files"),
+        counted(skipped, "unchanged file", "unchanged files")
+    );
+    if failed > 0 {
+        return Err(Error::Unsupported(format!(
+            "{} could not be mutilated due to syntax errors",
+            counted(failed, "file", "files")
+        )));
+    }
+    if let Some(pdf) = &batch.compile {
+        compile(&batch.output.join(&batch.entry), pdf, Some(&batch.output))?;
+    }
+    Ok(())
+}
+
+/// A progress bar on stderr, which is only shown on terminals when notes are shown but not the
+/// more detailed messages, which would interrupt it.
+struct Progress {
+    total: usize,
+    done: usize,
+    enabled: bool,
+}
+
+impl Progress {
+    /// The width of the bar in characters.
+    const WIDTH: usize = 24;
+    /// The maximum number of characters of the current file that are shown.
+    const PATH_WIDTH: usize = 40;
+
+    fn new(total: usize) -> Self {
+        let enabled = log::log_enabled!(log::Level::Info) && !log::log_enabled!(log::Level::Debug);
+        Self {
+            total,
+            done: 0,
+            enabled: enabled && io::stderr().is_terminal(),
+        }
+    }
+
+    /// Show that `path` is being processed, counting the previous file as done.
+    fn show(&mut self, path: &Path, stats: typst_mutilate::Stats) {
+        if !self.enabled {
+            return;
+        }
+        let filled = Self::WIDTH * self.done / self.total.max(1);
+        let path = path.to_string_lossy();
+        let skip = path.chars().count().saturating_sub(Self::PATH_WIDTH);
+        let path: String = match skip {
+            0 => path.into_owned(),
+            _ => std::iter::once('…')
+                .chain(path.chars().skip(skip + 1))
+                .collect(),
+        };
+        let fallbacks = match stats.words {
+            0 => 0.0,
+            words => 100.0 * stats.fallbacks as f64 / words as f64,
+        };
+        eprint!(
+            "\r\x1b[2K[{}{}] {}/{} {path}, {fallbacks:.1}% random fallbacks",
+            "=".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            self.done,
+            self.total,
+        );
+        self.done += 1;
+    }
+
+    /// Remove the bar, to print other messages or when done.
+    fn clear(&self) {
+        if self.enabled {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+/// Mutilate the files passed by a git hook in place, or with `--check` only report the files
+/// that would change, with a `diff` of their changes if requested.
+fn hook(
+    args: &Args,
+    hook: &HookCommand,
+    options: &MutilateOptions,
+    diff: bool,
+) -> Result<(), Error> {
+    let mut context = Context::new(options)?;
+    set_filter(args, &mut context);
+    let color = args.color.enabled(io::stdout().is_terminal());
+    let annotate = args.annotations.filter(|_| diff || hook.check);
+    if annotate.is_some() {
+        // The records show which words were replaced with random characters.
+        context.enable_audit();
+    }
+    let mut changed = Vec::new();
+    let mut broken = 0;
+    for path in &hook.files {
+        let (format, mode) = file_format(args, path);
+        context.set_format(format);
+        context.set_mode(mode);
+        let (code, encoding) = read_document(args, path)?;
+        context.set_changed_lines(changed_lines(args, path, &code)?);
+        let mut edits = match typst_mutilate::plan_str_with(&code, &mut context) {
+            Err(Error::Syntax(_)) => {
+                // Report the file and go on, so that the other files are still checked.
+                syntax_errors(&path.to_string_lossy(), &code, mode);
+                broken += 1;
+                continue;
+            }
+            result => result?,
+        };
+        let records = context.take_audit();
+        // Diffs only show the replacements, not the converted line breaks.
+        let replacements = edits.clone();
+        if args.preserve_lines {
+            edits = typst_mutilate::preserve_lines(&code, edits);
+        }
+        if let Some(newline) = args.newline {
+            edits = typst_mutilate::convert_newlines(&code, edits, newline);
+        }
+        let output = typst_mutilate::apply_edits(&code, &edits);
+        check_lines(args, &path.to_string_lossy(), &code, &output)?;
+        if output == code {
+            continue;
+        }
+        if let Some(annotate) = annotate {
+            let file = path.to_string_lossy();
+            let mut annotations = vec![Annotation::at(
+                &file,
+                &code,
+                edits.first().map_or(0, |edit| edit.range.start),
+                "change",
+                format!(
+                    "would be mutilated with {}",
+                    counted(replacements.len(), "replacement", "replacements")
+                ),
+            )];
+            if format == Format::Typst && selftest::first_error(&code, mode).is_none() {
+                if let Some(offset) = selftest::first_error(&output, mode) {
+                    let offset = selftest::original_offset(&code, &edits, offset);
+                    annotations.push(Annotation::at(
+                        &file,
+                        &code,
+                        offset,
+                        "parse",
+                        "the mutilated document doesn't parse here",
+                    ));
+                }
+            }
+            for leak in selftest::leaks(&code, &replacements, context.segmentation()) {
+                annotations.push(Annotation::at(
+                    &file,
+                    &code,
+                    leak.range.start,
+                    "leak",
+                    "the replacement keeps one of the words it replaces",
+                ));
+            }
+            for record in records.iter().filter(|record| record.strategy == "random") {
+                annotations.push(Annotation::at(
+                    &file,
+                    &code,
+                    record.range.start,
+                    "pool",
+                    "no wordlist entry fits, so the word was replaced with random characters",
+                ));
             }
+            annotations.sort_by_key(|annotation| (annotation.line, annotation.column));
+            let mut out = io::stdout().lock();
+            for annotation in &annotations {
+                annotate.write(&mut out, annotation)?;
+            }
+            out.flush()?;
+        } else if diff {
+            let mut out = io::stdout().lock();
+            diff::write(
+                &mut out,
+                &path.to_string_lossy(),
+                &code,
+                &replacements,
+                color,
+            )?;
+            out.flush()?;
+        } else if hook.check {
+            eprintln!("{} would be mutilated", path.display());
+        } else {
+            if args.undo {
+                typst_mutilate::record_undo(path, &code, &edits)?;
+            }
+            std::fs::write(path, encoding.encode(&output)?)?;
         }
+        changed.push(path);
     }
 
-    if let Some(words) = context.by_length.get(&length) {
-        if words.len() >= MINIMUM_WORD_COUNT {
-            if let Some(word) = words.choose(&mut context.rng) {
-                return write!(output, "{word}");
+    let mut failures = Vec::new();
+    if hook.stage && !hook.check {
+        if !changed.is_empty() {
+            let status = std::process::Command::new("git")
+                .arg("add")
+                .arg("--")
+                .args(&changed)
+                .status()?;
+            if !status.success() {
+                return Err(Error::Unsupported(
+                    "staging the mutilated files failed".into(),
+                ));
             }
+            log::info!(
+                "mutilated and staged {}",
+                counted(changed.len(), "file", "files")
+            );
         }
+    } else if !changed.is_empty() {
+        let verb = match (hook.check, changed.len()) {
+            (true, _) => "would change",
+            (false, 1) => "was mutilated",
+            (false, _) => "were mutilated",
+        };
+        failures.push(format!(
+            "{} {verb}",
+            counted(changed.len(), "file", "files")
+        ));
+    }
+    if broken > 0 {
+        let verb = if broken == 1 { "has" } else { "have" };
+        failures.push(format!(
+            "{} {verb} syntax errors",
+            counted(broken, "file", "files")
+        ));
+    }
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Unsupported(failures.join(", ")))
+}
+
+/// The marker in pre-commit hooks written by `install-hook`.
+const HOOK_MARKER: &str = "# Installed by `typst-mutilate install-hook`.";
+
+/// Write a pre-commit hook that runs the `hook` command with the options of this invocation.
+fn install_hook(install: &InstallHookCommand) -> Result<(), Error> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks/pre-commit"])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::Unsupported("not in a git repository".into()));
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end());
+    if let Ok(existing) = std::fs::read_to_string(&path) {
+        if !install.force && !existing.contains(HOOK_MARKER) {
+            return Err(Error::Unsupported(format!(
+                "{} already exists, use `--force` to replace it",
+                path.display()
+            )));
+        }
+    }
+
+    // Repeat the options before this command, then pass the staged documents to `hook`.
+    let mut command = vec![shell_quote(&std::env::current_exe()?.to_string_lossy())];
+    command.extend(
+        std::env::args()
+            .skip(1)
+            .take_while(|arg| arg != "install-hook")
+            .map(|arg| shell_quote(&arg)),
+    );
+    command.push("hook".into());
+    command.push(if install.check { "--check" } else { "--stage" }.into());
+    let script = format!(
+        "#!/bin/sh\n\
+         {HOOK_MARKER}\n\
+         git diff --cached --name-only --diff-filter=ACMR -z -- '*.typ' | xargs -0 {}\n",
+        command.join(" ")
+    );
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    log::info!("installed {}", path.display());
+    Ok(())
+}
+
+/// Quote a word for POSIX shells.
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@+,".contains(c));
+    if plain {
+        word.into()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
     }
+}
 
-    for _ in 0..length {
-        write!(output, "{}", CHARSET_TEXT.choose(&mut context.rng).unwrap())?;
+/// A hash of everything that affects the output of a batch run: the version, the arguments
+/// and the contents of the wordlists and pattern files.
+fn settings_fingerprint(args: &Args) -> io::Result<String> {
+    let mut settings = Vec::new();
+    settings.extend_from_slice(env!("CARGO_PKG_VERSION").as_bytes());
+    for arg in std::env::args_os().skip(1).filter(|arg| arg != "--force") {
+        settings.push(0);
+        settings.extend_from_slice(arg.as_encoded_bytes());
+    }
+    let files = args
+        .wordlist
+        .iter()
+        .map(|wordlist| &wordlist.path)
+        .chain(&args.hyphenation_patterns);
+    for path in files {
+        settings.push(0);
+        settings.extend_from_slice(typst_mutilate::content_hash(&std::fs::read(path)?).as_bytes());
     }
+    Ok(typst_mutilate::content_hash(&settings))
+}
+
+/// Mutilate the input block by block instead of reading it into memory at once.
+fn stream(args: &Args, context: &mut Context) -> Result<(), Error> {
+    let Some(path) = &args.in_place else {
+        let stdout = BufWriter::new(io::stdout().lock());
+        return typst_mutilate::mutilate_stream(io::stdin().lock(), context, stdout);
+    };
 
+    // Write to a temporary file next to the input, which replaces it once everything is done.
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".mutilate-tmp");
+    let temporary = path.with_file_name(file_name);
+
+    let input = BufReader::new(File::open(path)?);
+    let output = BufWriter::new(File::create(&temporary)?);
+    let result = typst_mutilate::mutilate_stream(input, context, output);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+        return result;
+    }
+    std::fs::rename(&temporary, path)?;
     Ok(())
 }
+
+fn build_options(args: &Args) -> Result<MutilateOptions, Error> {
+    let language = match &args.language {
+        Some(tag) => typst_mutilate::parse_language(tag).ok_or_else(|| {
+            Error::Unsupported(format!(
+                "language `{tag}` is not supported for hyphenation, see `--list-languages`"
+            ))
+        })?,
+        None => Lang::English,
+    };
+
+    let mut options = MutilateOptions::new()
+        .language(language)
+        .aggressive(args.aggressive)
+        .alt_text(args.alt_text)
+        .preserve_suffixes(args.preserve_suffixes)
+        .guess_word_languages(args.guess_word_language)
+        .scrub_pii(args.scrub_pii)
+        .scrub_metadata(args.scrub_metadata)
+        .names_only(args.names_only)
+        .shift_dates(args.shift_dates)
+        .numbers(args.numbers.unwrap_or_default())
+        .segmentation(args.segmentation.unwrap_or_default())
+        .normalize_output(args.normalize_output.unwrap_or_default())
+        .fold_wordlist_case(!args.case_sensitive_wordlist)
+        .sort_wordlists(args.stable);
+    if let Some(path) = &args.hyphenation_patterns {
+        options = options.hyphenation_patterns(Patterns::load(path)?);
+    }
+    if !args.no_hyphenation_cache && !args.stable {
+        if let Some(dir) = typst_mutilate::default_cache_dir() {
+            options = options.hyphenation_cache(dir.join("hyphenation"));
+        }
+    }
+    for WordlistArg { language, path } in &args.wordlist {
+        options = match language {
+            Some(language) => options.language_wordlist(*language, path.clone()),
+            None => options.wordlist(path.clone()),
+        };
+    }
+    #[cfg(feature = "remote")]
+    if let Some(url) = &args.wordlist_url {
+        // The wordlist behind a URL may change, unless it is pinned by its checksum.
+        if args.stable && args.wordlist_sha256.is_none() {
+            return Err(Error::Unsupported(
+                "`--stable` requires `--wordlist-sha256` with `--wordlist-url`".into(),
+            ));
+        }
+        let cached = typst_mutilate::remote::fetch_wordlist(
+            url,
+            args.wordlist_sha256.as_deref(),
+            args.refresh_wordlist,
+        )?;
+        if let Some(err) = &cached.download_error {
+            log::warn!("failed to refresh wordlist, using cached copy: {err}");
+        }
+        options = options.wordlist(cached.path);
+    }
+    if !args.strategy.is_empty() {
+        let wordlist = args
+            .strategy
+            .iter()
+            .any(|strategy| matches!(strategy, Strategy::Hyphenation | Strategy::Length));
+        if args.strategy.contains(&Strategy::Sentences) && !wordlist {
+            log::warn!(
+                "the words outside of generated sentences are replaced with random letters, add \
+                 `--strategy hyphenation` to replace them with words of the wordlist"
+            );
+        }
+        options = options.strategies(args.strategy.iter().copied());
+    }
+    for word in &args.protect {
+        options = options.protect(word.as_str());
+    }
+    if args.preserve_lines && (args.shrink.is_some() || args.shuffle.is_some()) {
+        return Err(Error::Unsupported(
+            "`--preserve-lines` cannot be combined with `--shrink` or `--shuffle`".into(),
+        ));
+    }
+    if let Some(fraction) = args.shrink {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(Error::Unsupported(
+                "`--shrink` must be between 0 and 1".into(),
+            ));
+        }
+        options = options.shrink(fraction);
+    }
+    if let Some(unit) = args.shuffle {
+        options = options.shuffle(unit);
+    }
+    if let Some(quotes) = args.quotes {
+        options = options.quotes(quotes);
+    }
+    for pattern in &args.section {
+        options = options.section(pattern.clone());
+    }
+    for pattern in &args.exclude_section {
+        options = options.exclude_section(pattern.clone());
+    }
+    for selector in &args.select {
+        options = options.select(selector.clone());
+    }
+    for selector in &args.keep {
+        options = options.keep(selector.clone());
+    }
+    if let Some(raw) = args.raw {
+        options = options.raw(raw);
+    }
+    if args.regenerate_paragraphs {
+        if args.names_only {
+            return Err(Error::Unsupported(
+                "`--regenerate-paragraphs` cannot be combined with `--names-only`".into(),
+            ));
+        }
+        options = options.regenerate_paragraphs(true);
+    }
+    if let Some(days) = args.date_offset {
+        options = options.date_offset(days);
+    }
+    if let Some(path) = &args.names {
+        let names = std::fs::read_to_string(path)?;
+        // Names like `Jane Doe` are matched word by word.
+        for name in names.split(|c: char| !c.is_alphanumeric()) {
+            if !name.is_empty() {
+                options = options.name(name);
+            }
+        }
+    }
+    if let Some(seed) = args.seed.or(args.stable.then_some(0)) {
+        options = options.seed(seed);
+    }
+    let extension = input_path(args).and_then(|path| path.extension()?.to_str());
+    let format = args.format.or_else(|| Format::from_extension(extension?));
+    if args.compile.is_some() && format.is_some_and(|format| format != Format::Typst) {
+        return Err(Error::Unsupported(
+            "`--compile` only supports typst documents".into(),
+        ));
+    }
+    if let Some(format) = format {
+        options = options.format(format);
+    }
+    if let Some(mode) = args.mode.or_else(|| Mode::from_extension(extension?)) {
+        options = options.mode(mode);
+    }
+    Ok(options)
+}
@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::cache::hex;
+
+/// Records from which input and with which settings each output of a batch run was produced,
+/// so that later runs can skip files that haven't changed.
+///
+/// The manifest is a text file with one line per output, containing the hashes of the input,
+/// the settings and the output, followed by the path of the output.
+#[derive(Debug, Default)]
+pub struct Manifest {
+    entries: HashMap<PathBuf, Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    input: String,
+    settings: String,
+    output: String,
+}
+
+impl Manifest {
+    /// Load a manifest, which is empty if the file doesn't exist.
+    ///
+    /// Malformed lines are ignored, which only means that their files are processed again.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut entries = HashMap::new();
+        for line in text.lines() {
+            let mut fields = line.splitn(4, '\t');
+            let (Some(input), Some(settings), Some(output), Some(path)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let entry = Entry {
+                input: input.into(),
+                settings: settings.into(),
+                output: output.into(),
+            };
+            entries.insert(PathBuf::from(path), entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Write the manifest to a file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut paths: Vec<_> = self.entries.keys().collect();
+        paths.sort();
+        let mut text = String::new();
+        for path in paths {
+            let entry = &self.entries[path];
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                entry.input,
+                entry.settings,
+                entry.output,
+                path.display()
+            ));
+        }
+        std::fs::write(path, text)
+    }
+
+    /// Whether the output at `path` was produced from the same input with the same settings and
+    /// wasn't modified since.
+    ///
+    /// The path is used as the key of the output, while `output` is where the output is read
+    /// from to check it.
+    pub fn is_fresh(&self, path: &Path, input: &[u8], settings: &str, output: &Path) -> bool {
+        let Some(entry) = self.entries.get(path) else {
+            return false;
+        };
+        entry.settings == settings
+            && entry.input == content_hash(input)
+            && std::fs::read(output).is_ok_and(|bytes| entry.output == content_hash(&bytes))
+    }
+
+    /// Record that the output at `path` was produced from `input` with the given settings.
+    pub fn record(&mut self, path: &Path, input: &[u8], settings: &str, output: &[u8]) {
+        let entry = Entry {
+            input: content_hash(input),
+            settings: settings.into(),
+            output: content_hash(output),
+        };
+        self.entries.insert(path.to_owned(), entry);
+    }
+
+    /// Forget about outputs for which `keep` returns `false`, like outputs of deleted inputs.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Path) -> bool) {
+        self.entries.retain(|path, _| keep(path));
+    }
+}
+
+/// The hex-encoded SHA-256 hash of some bytes, as used in manifests.
+pub fn content_hash(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
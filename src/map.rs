@@ -0,0 +1,88 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use ecow::EcoString;
+use sha2::{Digest, Sha256};
+
+use crate::{cache::hex, Edit};
+
+/// The replacement of every original text, collected from the edits of documents.
+///
+/// Where the same text was replaced differently, the first replacement is kept.
+#[derive(Debug, Default, Clone)]
+pub struct ReplacementMap {
+    entries: Vec<(EcoString, EcoString)>,
+    seen: HashSet<EcoString>,
+}
+
+impl ReplacementMap {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the replacements of the `edits` of `code`.
+    ///
+    /// Edits that remove text, which drop whole parts of a document, are no replacements and
+    /// left out, as should be those that move parts, listed by [`Context::moved`].
+    ///
+    /// [`Context::moved`]: crate::Context::moved
+    pub fn add(&mut self, code: &str, edits: &[Edit]) {
+        for edit in edits.iter().filter(|edit| !edit.replacement.is_empty()) {
+            let original = EcoString::from(&code[edit.range.clone()]);
+            if self.seen.insert(original.clone()) {
+                self.entries.push((original, edit.replacement.clone()));
+            }
+        }
+    }
+
+    /// Write the map as a typst file defining a `replacements` dictionary.
+    ///
+    /// With a `salt`, the keys are the hex-encoded SHA-256 hashes of the salt followed by the
+    /// original text, so that the file doesn't reveal the originals but they can still be
+    /// looked up.
+    pub fn write_typst(&self, out: &mut impl Write, salt: Option<&[u8]>) -> io::Result<()> {
+        writeln!(out, "// Generated by typst-mutilate.")?;
+        if self.entries.is_empty() {
+            return writeln!(out, "#let replacements = (:)");
+        }
+        writeln!(out, "#let replacements = (")?;
+        for (original, replacement) in &self.entries {
+            let key = match salt {
+                Some(salt) => hex(&Sha256::new()
+                    .chain_update(salt)
+                    .chain_update(original.as_bytes())
+                    .finalize()),
+                None => original.to_string(),
+            };
+            writeln!(
+                out,
+                "  {}: {},",
+                typst_string(&key),
+                typst_string(replacement)
+            )?;
+        }
+        writeln!(out, ")")
+    }
+}
+
+/// Quote and escape a string for typst code.
+fn typst_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,36 @@
+use std::io;
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+use crate::{plan_text, Context, Edit};
+
+/// Compute the edits that mutilate the prose in a Markdown document.
+///
+/// Code blocks, inline code, link destinations, HTML, math and front matter are copied
+/// verbatim, as is any text whose source is spelled differently than it reads, like entities.
+pub(crate) fn plan_markdown(
+    code: &str,
+    context: &mut Context,
+    edits: &mut Vec<Edit>,
+) -> io::Result<()> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_MATH
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+
+    let mut verbatim_depth = 0usize;
+    for (event, range) in Parser::new_ext(code, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::MetadataBlock(_)) => verbatim_depth += 1,
+            Event::End(TagEnd::CodeBlock | TagEnd::MetadataBlock(_)) => verbatim_depth -= 1,
+            Event::Text(text) if verbatim_depth == 0 && code[range.clone()] == *text => {
+                plan_text(&text, range.start, context, edits)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
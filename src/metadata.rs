@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use typst_syntax::{LinkedNode, SyntaxKind};
+
+/// Names of arguments, fields and variables that hold information about the authors.
+const AUTHOR_FIELDS: &[&str] = &["author", "authors", "email", "affiliation", "affiliations"];
+
+/// How a field of a package manifest is scrubbed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Field {
+    /// The names of the authors: every word is a name.
+    Authors,
+    /// Prose: every word is replaced.
+    Description,
+    /// A URL: every word after the scheme is a name, so that account names match the authors.
+    Url,
+}
+
+/// The ranges of `let` bindings, named arguments and dictionary fields with information about
+/// the authors, like `authors: ("Jane Doe",)` or `#let author = "Jane Doe"`.
+pub(crate) fn author_regions(node: &LinkedNode, regions: &mut Vec<Range<usize>>) {
+    let is_author_field = |child: LinkedNode| {
+        child.kind() == SyntaxKind::Ident && AUTHOR_FIELDS.contains(&child.text().as_str())
+    };
+    let author = match node.kind() {
+        SyntaxKind::Named => node.children().next().is_some_and(is_author_field),
+        SyntaxKind::LetBinding => node
+            .children()
+            .find(|child| child.kind() != SyntaxKind::Let && !child.kind().is_trivia())
+            .is_some_and(is_author_field),
+        _ => false,
+    };
+    if author {
+        regions.push(node.range());
+        return;
+    }
+    for child in node.children() {
+        author_regions(&child, regions);
+    }
+}
+
+/// The contents of the strings in the `authors`, `description`, `repository` and `homepage`
+/// fields of the `[package]` table of a `typst.toml` package manifest.
+pub(crate) fn package_fields(toml: &str) -> Vec<(Range<usize>, Field)> {
+    let mut fields = Vec::new();
+    let mut table = "";
+    // The field whose array value continues on the next line, and the nesting depth.
+    let mut open: Option<Field> = None;
+    let mut depth = 0;
+    let mut offset = 0;
+    for line in toml.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if let Some(field) = open {
+            scan_value(line, start, field, &mut depth, &mut fields);
+            if depth == 0 {
+                open = None;
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            table = trimmed.trim_matches(['[', ']']).trim();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let field = match key.trim() {
+            _ if table != "package" => continue,
+            "authors" => Field::Authors,
+            "description" => Field::Description,
+            "repository" | "homepage" => Field::Url,
+            _ => continue,
+        };
+        scan_value(value, start + key.len() + 1, field, &mut depth, &mut fields);
+        if depth > 0 {
+            open = Some(field);
+        }
+    }
+    fields
+}
+
+/// The `entrypoint` of the `[package]` table of a `typst.toml` package manifest.
+pub(crate) fn package_entrypoint(toml: &str) -> Option<&str> {
+    let mut table = "";
+    for line in toml.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            table = trimmed.trim_matches(['[', ']']).trim();
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) if table == "package" && key.trim() == "entrypoint" => {
+                let value = value.split('#').next().unwrap_or_default().trim();
+                return Some(value.trim_matches(['"', '\'']));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Find the contents of the strings in part of a TOML value, which starts at `offset`, and
+/// follow the nesting of arrays.
+fn scan_value(
+    value: &str,
+    offset: usize,
+    field: Field,
+    depth: &mut usize,
+    fields: &mut Vec<(Range<usize>, Field)>,
+) {
+    let mut chars = value.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let mut escaped = false;
+                let end = chars.by_ref().find(|&(_, d)| {
+                    let end = d == c && !escaped;
+                    escaped = c == '"' && d == '\\' && !escaped;
+                    end
+                });
+                let end = end.map_or(value.trim_end().len(), |(end, _)| end);
+                fields.push((offset + i + 1..offset + end, field));
+            }
+            '[' => *depth += 1,
+            ']' => *depth = depth.saturating_sub(1),
+            '#' => break,
+            _ => {}
+        }
+    }
+}
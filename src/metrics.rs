@@ -0,0 +1,218 @@
+use std::io::{self, Write};
+
+use hypher::Lang;
+use typst_syntax::{LinkedNode, SyntaxKind};
+
+use crate::{scan, Context, Format, Mode, Segmentation};
+
+/// Statistics of the prose of a document, to compare a mutilated document with its original.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of words.
+    pub words: usize,
+    /// The number of characters of all words.
+    pub characters: usize,
+    /// The number of syllables of all words.
+    pub syllables: usize,
+    /// The number of words of every sentence, sorted.
+    pub sentences: Vec<usize>,
+    /// The number of lines the paragraphs would take if they were broken into lines of
+    /// [`LINE_WIDTH`](Self::LINE_WIDTH) characters.
+    pub lines: usize,
+}
+
+impl Metrics {
+    /// The number of characters of the lines that [`lines`](Self::lines) are estimated for.
+    pub const LINE_WIDTH: usize = 70;
+
+    /// Measure the prose of a document, interpreted in the format, mode and language of the
+    /// context.
+    ///
+    /// In typst documents, the text of markup counts, split into paragraphs at paragraph
+    /// breaks; other documents are measured as they are, split at blank lines.
+    pub fn measure(code: &str, context: &Context) -> Self {
+        let mut paragraphs = Vec::new();
+        match context.format {
+            Format::Typst => {
+                let syntax = match context.mode {
+                    Mode::Markup => typst_syntax::parse(code),
+                    Mode::Code => typst_syntax::parse_code(code),
+                    Mode::Math => typst_syntax::parse_math(code),
+                };
+                collect(&LinkedNode::new(&syntax), &mut paragraphs);
+            }
+            _ => paragraphs.extend(
+                code.replace("\r\n", "\n")
+                    .split("\n\n")
+                    .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" ")),
+            ),
+        }
+
+        let mut metrics = Self::default();
+        for paragraph in &paragraphs {
+            metrics.add(paragraph, context.language, context.segmentation);
+        }
+        metrics.sentences.sort_unstable();
+        metrics
+    }
+
+    /// Add a paragraph whose whitespace was collapsed.
+    fn add(&mut self, paragraph: &str, language: Lang, segmentation: Segmentation) {
+        let mut sentence = 0;
+        let mut line = 0;
+        for chunk in paragraph.split_whitespace() {
+            // Break lines greedily, before chunks that don't fit anymore.
+            let width = scan::char_count(chunk);
+            if line == 0 || line + 1 + width > Self::LINE_WIDTH {
+                self.lines += 1;
+                line = width;
+            } else {
+                line += 1 + width;
+            }
+
+            for (_, word) in scan::Words::new(chunk, segmentation) {
+                self.words += 1;
+                self.characters += scan::char_count(word);
+                self.syllables += hypher::hyphenate(word, language).count();
+                sentence += 1;
+            }
+            if chunk
+                .trim_end_matches([')', ']', '"', '\'', '’', '”', '»'])
+                .ends_with(['.', '!', '?', '…'])
+                && sentence > 0
+            {
+                self.sentences.push(sentence);
+                sentence = 0;
+            }
+        }
+        if sentence > 0 {
+            self.sentences.push(sentence);
+        }
+    }
+
+    /// The average number of characters of a word.
+    pub fn average_word_length(&self) -> f64 {
+        ratio(self.characters, self.words)
+    }
+
+    /// The average number of syllables of a word.
+    pub fn syllables_per_word(&self) -> f64 {
+        ratio(self.syllables, self.words)
+    }
+
+    /// The number of words of the sentence at a share of the sorted sentences, like `0.5` for
+    /// the median.
+    pub fn sentence_length(&self, quantile: f64) -> usize {
+        if self.sentences.is_empty() {
+            return 0;
+        }
+        let index = ((self.sentences.len() - 1) as f64 * quantile).round() as usize;
+        self.sentences[index]
+    }
+
+    /// Write a table comparing the metrics of an original document with those of its
+    /// mutilated version.
+    pub fn write_comparison(
+        out: &mut impl Write,
+        original: &Metrics,
+        mutilated: &Metrics,
+    ) -> io::Result<()> {
+        let distribution = |metrics: &Metrics| {
+            [0.25, 0.5, 0.75, 1.0]
+                .map(|quantile| metrics.sentence_length(quantile).to_string())
+                .join("/")
+        };
+        let rows = [
+            (
+                "words".to_string(),
+                original.words.to_string(),
+                mutilated.words.to_string(),
+            ),
+            (
+                "average word length".into(),
+                format!("{:.2}", original.average_word_length()),
+                format!("{:.2}", mutilated.average_word_length()),
+            ),
+            (
+                "syllables per word".into(),
+                format!("{:.2}", original.syllables_per_word()),
+                format!("{:.2}", mutilated.syllables_per_word()),
+            ),
+            (
+                "sentences".into(),
+                original.sentences.len().to_string(),
+                mutilated.sentences.len().to_string(),
+            ),
+            (
+                "sentence length (quartiles)".into(),
+                distribution(original),
+                distribution(mutilated),
+            ),
+            (
+                format!("lines of {} characters", Self::LINE_WIDTH),
+                original.lines.to_string(),
+                mutilated.lines.to_string(),
+            ),
+        ];
+        let width = rows
+            .iter()
+            .map(|(name, _, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            out,
+            "{:width$}  {:>12}  {:>12}",
+            "", "original", "mutilated"
+        )?;
+        for (name, before, after) in rows {
+            writeln!(out, "{name:width$}  {before:>12}  {after:>12}")?;
+        }
+        Ok(())
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Collect the paragraphs of the markup of a node and its descendants, with their whitespace
+/// collapsed.
+fn collect(node: &LinkedNode, paragraphs: &mut Vec<String>) {
+    if node.kind() == SyntaxKind::Markup
+        && node
+            .parent()
+            .is_none_or(|parent| parent.kind() == SyntaxKind::ContentBlock)
+    {
+        let mut paragraph = String::new();
+        for child in node.children() {
+            if child.kind() == SyntaxKind::Parbreak {
+                paragraphs.push(std::mem::take(&mut paragraph));
+            } else {
+                prose(&child, &mut paragraph);
+            }
+        }
+        paragraphs.push(paragraph);
+    }
+    for child in node.children() {
+        collect(&child, paragraphs);
+    }
+}
+
+/// Append the text and spaces of a node of a paragraph, but not of the content blocks in it,
+/// which are collected separately.
+fn prose(node: &LinkedNode, paragraph: &mut String) {
+    match node.kind() {
+        SyntaxKind::Text | SyntaxKind::SmartQuote => paragraph.push_str(node.text()),
+        SyntaxKind::Space | SyntaxKind::Linebreak => paragraph.push(' '),
+        SyntaxKind::ContentBlock => {}
+        _ => {
+            for child in node.children() {
+                prose(&child, paragraph);
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use hypher::Lang;
+
+/// The minimum number of characters left for the stem when a suffix is split off.
+const MINIMUM_STEM_LENGTH: usize = 3;
+
+/// Split a common inflectional suffix off a word, like `walk` and `ing` for `walking`.
+///
+/// Returns the whole word as the stem if it has no known suffix.
+pub(crate) fn split_suffix(word: &str, language: Lang) -> (&str, &str) {
+    let length = word.chars().count();
+    for suffix in suffixes(language) {
+        let suffix_length = suffix.chars().count();
+        if length < suffix_length + MINIMUM_STEM_LENGTH {
+            continue;
+        }
+        let split = word
+            .char_indices()
+            .nth(length - suffix_length)
+            .map_or(word.len(), |(i, _)| i);
+        let (stem, end) = word.split_at(split);
+        if end.to_lowercase() == *suffix && !stem.chars().any(char::is_numeric) {
+            return (stem, end);
+        }
+    }
+    (word, "")
+}
+
+/// The suffixes of a language, longest first.
+fn suffixes(language: Lang) -> &'static [&'static str] {
+    match language {
+        Lang::English => &[
+            "ings", "ness", "ing", "ers", "est", "ed", "es", "er", "ly", "s",
+        ],
+        Lang::German => &[
+            "ungen", "heit", "keit", "lich", "isch", "ung", "ern", "ten", "en", "er", "es", "em",
+            "st", "te", "e", "t",
+        ],
+        Lang::French => &[
+            "ements", "ement", "ions", "ées", "ent", "ez", "er", "es", "ée", "és", "é", "s", "e",
+        ],
+        Lang::Spanish => &[
+            "mente", "iendo", "ando", "ados", "adas", "ado", "ada", "es", "os", "as", "s",
+        ],
+        Lang::Italian => &[
+            "mente", "zione", "zioni", "ando", "endo", "are", "ere", "ire",
+        ],
+        Lang::Portuguese => &["mente", "ções", "ção", "ando", "endo", "ados", "adas", "s"],
+        Lang::Dutch => &["ingen", "heid", "ing", "en", "er", "st", "te", "de"],
+        _ => &[],
+    }
+}
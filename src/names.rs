@@ -0,0 +1,87 @@
+use std::collections::{HashMap, HashSet};
+
+use ecow::EcoString;
+
+/// Titles that precede names, after which a period doesn't end the sentence.
+const HONORIFICS: &[&str] = &[
+    "Mr", "Mrs", "Ms", "Miss", "Mx", "Dr", "Prof", "Sir", "Dame", "Lord", "Lady", "Herr", "Frau",
+    "Fr", "Hr", "Mme", "Mlle", "Sr", "Sra", "Srta", "Don", "Doña", "Sig",
+];
+
+/// Decides which words are likely proper nouns, following the text in document order.
+pub(crate) struct NameDetector {
+    /// Words that are always names, in lowercase.
+    names: HashSet<EcoString>,
+    /// The replacement of every name seen so far by its lowercase form, so that names are
+    /// replaced consistently.
+    replacements: HashMap<EcoString, EcoString>,
+    sentence_start: bool,
+    after_honorific: bool,
+}
+
+impl NameDetector {
+    pub(crate) fn new(names: &HashSet<EcoString>) -> Self {
+        Self {
+            names: names.iter().map(|name| name.to_lowercase()).collect(),
+            replacements: HashMap::new(),
+            sentence_start: true,
+            after_honorific: false,
+        }
+    }
+
+    /// Note that a block starts or ends, like a paragraph or a heading.
+    pub(crate) fn block_boundary(&mut self) {
+        self.sentence_start = true;
+        self.after_honorific = false;
+    }
+
+    /// Note the text between two words.
+    pub(crate) fn gap(&mut self, gap: &str) {
+        let ends_sentence = gap.contains(['.', '!', '?', ':']) || gap.contains("\n\n");
+        if ends_sentence && !(self.after_honorific && gap.trim() == ".") {
+            self.sentence_start = true;
+        }
+    }
+
+    /// Decide whether the next word is a name.
+    ///
+    /// Words that were names before, or that are in the list of names, are names regardless
+    /// of their position and case.
+    pub(crate) fn is_name(&mut self, word: &str) -> bool {
+        let key = word.to_lowercase();
+        let capitalized =
+            word.chars().next().is_some_and(char::is_uppercase) && word.chars().nth(1).is_some();
+        let name = self.names.contains(key.as_str())
+            || self.replacements.contains_key(key.as_str())
+            || self.after_honorific
+            || (capitalized && !self.sentence_start);
+        self.sentence_start = false;
+        self.after_honorific = HONORIFICS.contains(&word);
+        name && !self.after_honorific
+    }
+
+    /// The replacement chosen earlier for a name, capitalized like the name.
+    pub(crate) fn replacement(&self, name: &str) -> Option<EcoString> {
+        let replacement = self.replacements.get(name.to_lowercase().as_str())?;
+        Some(capitalize_like(replacement, name))
+    }
+
+    /// Remember the replacement of a name and capitalize it like the name.
+    pub(crate) fn remember(&mut self, name: &str, replacement: EcoString) -> EcoString {
+        let capitalized = capitalize_like(&replacement, name);
+        self.replacements
+            .insert(name.to_lowercase().into(), replacement);
+        capitalized
+    }
+}
+
+/// Uppercase the first letter of `word` if `like` starts with an uppercase letter.
+fn capitalize_like(word: &str, like: &str) -> EcoString {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) if like.starts_with(char::is_uppercase) => {
+            first.to_uppercase().chain(chars).collect()
+        }
+        _ => word.into(),
+    }
+}
@@ -0,0 +1,854 @@
+use std::{collections::HashSet, fmt, path::PathBuf, str::FromStr, sync::Arc};
+
+use ecow::EcoString;
+use hypher::Lang;
+
+use crate::{HeadingPattern, Patterns, Selector};
+
+/// Settings that control how a document is mutilated.
+///
+/// ```
+/// # use typst_mutilate::{hypher::Lang, MutilateOptions};
+/// let options = MutilateOptions::new()
+///     .language(Lang::German)
+///     .aggressive(true)
+///     .protect("Typst")
+///     .seed(42);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MutilateOptions {
+    pub(crate) language: Lang,
+    pub(crate) wordlists: Vec<(Option<Lang>, WordlistSource)>,
+    pub(crate) fold_wordlist_case: bool,
+    pub(crate) sort_wordlists: bool,
+    pub(crate) hyphenation_cache: Option<PathBuf>,
+    pub(crate) hyphenation_patterns: Option<Arc<Patterns>>,
+    pub(crate) aggressive: bool,
+    pub(crate) alt_text: bool,
+    pub(crate) preserve_suffixes: bool,
+    pub(crate) guess_word_languages: bool,
+    pub(crate) scrub_pii: bool,
+    pub(crate) scrub_metadata: bool,
+    pub(crate) names_only: bool,
+    pub(crate) names: HashSet<EcoString>,
+    pub(crate) shift_dates: bool,
+    pub(crate) date_offset: Option<i64>,
+    pub(crate) shrink: Option<f64>,
+    pub(crate) shuffle: Option<Shuffle>,
+    pub(crate) quotes: Option<Quotes>,
+    pub(crate) sections: Vec<HeadingPattern>,
+    pub(crate) excluded_sections: Vec<HeadingPattern>,
+    pub(crate) selected: Vec<Selector>,
+    pub(crate) kept: Vec<Selector>,
+    pub(crate) raw: RawMode,
+    pub(crate) regenerate_paragraphs: bool,
+    pub(crate) strategies: Vec<Strategy>,
+    pub(crate) numbers: NumberMode,
+    pub(crate) segmentation: Segmentation,
+    pub(crate) normalize_output: Normalization,
+    pub(crate) protected: HashSet<EcoString>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) format: Format,
+    pub(crate) mode: Mode,
+}
+
+impl MutilateOptions {
+    /// Create the default options: English, the bundled wordlist, not aggressive, all strategies
+    /// but [`Strategy::Sentences`].
+    pub fn new() -> Self {
+        Self {
+            language: Lang::English,
+            wordlists: Vec::new(),
+            fold_wordlist_case: true,
+            sort_wordlists: false,
+            hyphenation_cache: None,
+            hyphenation_patterns: None,
+            aggressive: false,
+            alt_text: false,
+            preserve_suffixes: false,
+            guess_word_languages: false,
+            scrub_pii: false,
+            scrub_metadata: false,
+            names_only: false,
+            names: HashSet::new(),
+            shift_dates: false,
+            date_offset: None,
+            shrink: None,
+            shuffle: None,
+            quotes: None,
+            sections: Vec::new(),
+            excluded_sections: Vec::new(),
+            selected: Vec::new(),
+            kept: Vec::new(),
+            raw: RawMode::Words,
+            regenerate_paragraphs: false,
+            strategies: Strategy::DEFAULT.to_vec(),
+            numbers: NumberMode::Digits,
+            segmentation: Segmentation::Unicode,
+            normalize_output: Normalization::Preserve,
+            protected: HashSet::new(),
+            seed: None,
+            format: Format::Typst,
+            mode: Mode::Markup,
+        }
+    }
+
+    /// The language used to hyphenate words.
+    pub fn language(mut self, language: Lang) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Add a source of replacement words.
+    ///
+    /// Multiple wordlists are merged, ignoring duplicate words. Without a wordlist, the
+    /// [bundled wordlist](crate::bundled_wordlist) of the language is used if there is one.
+    pub fn wordlist(mut self, wordlist: impl Into<WordlistSource>) -> Self {
+        self.wordlists.push((None, wordlist.into()));
+        self
+    }
+
+    /// Add a source of replacement words for text in a specific language.
+    ///
+    /// Text in this language, for example after `#set text(lang: "de")`, only draws from the
+    /// wordlists of its language.
+    pub fn language_wordlist(
+        mut self,
+        language: Lang,
+        wordlist: impl Into<WordlistSource>,
+    ) -> Self {
+        self.wordlists.push((Some(language), wordlist.into()));
+        self
+    }
+
+    /// Whether wordlist entries that only differ in case, like `Apple` and `apple`, are
+    /// considered duplicates, in which case only the first one is kept.
+    ///
+    /// This is enabled by default.
+    pub fn fold_wordlist_case(mut self, fold: bool) -> Self {
+        self.fold_wordlist_case = fold;
+        self
+    }
+
+    /// Sort the words of the wordlists instead of keeping their order.
+    ///
+    /// Replacements then only depend on the seed and the set of words, not on the order in
+    /// which the wordlists list them, so that reordered or merged wordlists give the same
+    /// output. Of several words that only differ in case, the first in sorted order is kept.
+    pub fn sort_wordlists(mut self, sort: bool) -> Self {
+        self.sort_wordlists = sort;
+        self
+    }
+
+    /// A directory in which the hyphenation patterns of the words of wordlist files are cached,
+    /// like a subdirectory of [`default_cache_dir`](crate::default_cache_dir).
+    ///
+    /// Hyphenating large wordlists takes a while, so this speeds up later runs with the same
+    /// wordlist considerably.
+    pub fn hyphenation_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.hyphenation_cache = Some(dir.into());
+        self
+    }
+
+    /// Hyphenate with custom patterns instead of the built-in patterns of the language.
+    ///
+    /// This applies to the words of the document and of the wordlists, in all languages.
+    pub fn hyphenation_patterns(mut self, patterns: Patterns) -> Self {
+        self.hyphenation_patterns = Some(Arc::new(patterns));
+        self
+    }
+
+    /// Whether to replace elements that are more likely to change behavior, like strings.
+    pub fn aggressive(mut self, aggressive: bool) -> Self {
+        self.aggressive = aggressive;
+        self
+    }
+
+    /// Also replace the strings given as `alt` or `description` arguments, like the
+    /// alternative text of images, without replacing all strings as in aggressive mode.
+    ///
+    /// They often describe the content of figures, while other strings, like paths and
+    /// numbering patterns, are needed to compile the document.
+    pub fn alt_text(mut self, alt_text: bool) -> Self {
+        self.alt_text = alt_text;
+        self
+    }
+
+    /// Keep common inflectional suffixes of the language, like `-ing` in English, and only
+    /// replace the stem, so that the grammar of the result stays plausible.
+    pub fn preserve_suffixes(mut self, preserve: bool) -> Self {
+        self.preserve_suffixes = preserve;
+        self
+    }
+
+    /// Guess the language of each word, so that foreign words in a different script, or with
+    /// the `detect` feature also in the same script, are hyphenated and replaced according to
+    /// their own language.
+    ///
+    /// The languages of language-specific wordlists are preferred, followed by English.
+    pub fn guess_word_languages(mut self, guess: bool) -> Self {
+        self.guess_word_languages = guess;
+        self
+    }
+
+    /// Replace phone numbers, IBANs, card numbers, national ID numbers and other long digit
+    /// runs in text, strings and comments with fakes of the same format, even where the text
+    /// would otherwise be kept.
+    pub fn scrub_pii(mut self, scrub: bool) -> Self {
+        self.scrub_pii = scrub;
+        self
+    }
+
+    /// Replace the information about the authors in typst documents, like the strings in
+    /// `author` and `authors` arguments, fields and `let` bindings, even where strings are
+    /// otherwise kept, and every name the same way.
+    pub fn scrub_metadata(mut self, scrub: bool) -> Self {
+        self.scrub_metadata = scrub;
+        self
+    }
+
+    /// Keep ordinary prose and only replace likely proper nouns, like capitalized words in the
+    /// middle of a sentence, words after titles like `Dr.` and the words given to [`name`].
+    /// Every name is replaced the same way throughout the document.
+    ///
+    /// [`name`]: Self::name
+    pub fn names_only(mut self, names_only: bool) -> Self {
+        self.names_only = names_only;
+        self
+    }
+
+    /// Treat a word as a name wherever it occurs, for [`names_only`].
+    ///
+    /// [`names_only`]: Self::names_only
+    pub fn name(mut self, word: impl Into<EcoString>) -> Self {
+        self.names.insert(word.into());
+        self
+    }
+
+    /// Move every recognized date, like `2024-01-31`, `31 January 2024` or a `datetime` call,
+    /// by the same random number of days instead of replacing its digits, so that the order
+    /// of and the intervals between the dates stay the same.
+    pub fn shift_dates(mut self, shift: bool) -> Self {
+        self.shift_dates = shift;
+        self
+    }
+
+    /// Move dates by this number of days instead of a random one, implying [`shift_dates`].
+    ///
+    /// [`shift_dates`]: Self::shift_dates
+    pub fn date_offset(mut self, days: i64) -> Self {
+        self.shift_dates = true;
+        self.date_offset = Some(days);
+        self
+    }
+
+    /// Drop about this share of the paragraphs, list items and table rows of typst documents,
+    /// to make them smaller while keeping the same kinds of content.
+    ///
+    /// Parts with headings, labels or definitions are kept, as is at least one paragraph,
+    /// item or row of every group, so that the document still compiles. The share must be
+    /// between 0 and 1, or [`Context::new`](crate::Context::new) fails.
+    pub fn shrink(mut self, fraction: f64) -> Self {
+        self.shrink = Some(fraction);
+        self
+    }
+
+    /// Also move the paragraphs, sections or sentences of typst documents to random positions
+    /// among their siblings, keeping each of them intact.
+    ///
+    /// Headings, lists, figures and parts with definitions or rules stay in place, and nothing
+    /// moves past them, so that the document still compiles.
+    pub fn shuffle(mut self, unit: Shuffle) -> Self {
+        self.shuffle = Some(unit);
+        self
+    }
+
+    /// Keep the quotations of typst documents or only replace them: the content of `quote`
+    /// calls, including block quotes, and text between double quotes.
+    pub fn quotes(mut self, quotes: Quotes) -> Self {
+        self.quotes = Some(quotes);
+        self
+    }
+
+    /// Only mutilate the sections of typst documents whose headings match the pattern, from
+    /// after the heading up to the next heading of the same or a lower depth; can be called
+    /// repeatedly to select more sections.
+    pub fn section(mut self, pattern: HeadingPattern) -> Self {
+        self.sections.push(pattern);
+        self
+    }
+
+    /// Keep the sections of typst documents whose headings match the pattern, even inside the
+    /// sections selected with [`section`](Self::section); can be called repeatedly.
+    pub fn exclude_section(mut self, pattern: HeadingPattern) -> Self {
+        self.excluded_sections.push(pattern);
+        self
+    }
+
+    /// Only mutilate the elements of typst documents that the selector matches, like
+    /// `figure caption`; can be called repeatedly to select more elements.
+    pub fn select(mut self, selector: Selector) -> Self {
+        self.selected.push(selector);
+        self
+    }
+
+    /// Keep the elements of typst documents that the selector matches, like
+    /// `heading.where(level: 1)`, even inside the elements selected with
+    /// [`select`](Self::select); can be called repeatedly.
+    pub fn keep(mut self, selector: Selector) -> Self {
+        self.kept.push(selector);
+        self
+    }
+
+    /// How the code in raw text of typst documents is replaced.
+    ///
+    /// This is ignored if only names are replaced.
+    pub fn raw(mut self, raw: RawMode) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    /// Replace every paragraph of running text in typst documents with generated text of as
+    /// many words of about the same lengths, in sentences of random lengths, instead of
+    /// replacing its words one by one.
+    ///
+    /// This keeps nothing of the order of the words and the sentences, and is faster for huge
+    /// documents, as words aren't hyphenated. Markup in the paragraphs, like `*strong*` text,
+    /// is replaced too, while paragraphs with headings, lists, figures, labels or definitions,
+    /// and fragments without sentence punctuation like most table cells, keep the replacement
+    /// of single words. It has no effect with [`names_only`].
+    ///
+    /// [`names_only`]: Self::names_only
+    pub fn regenerate_paragraphs(mut self, regenerate: bool) -> Self {
+        self.regenerate_paragraphs = regenerate;
+        self
+    }
+
+    /// The strategies to try, in order, when choosing a replacement for a word.
+    ///
+    /// They replace the default [`Strategy::Hyphenation`], [`Strategy::Length`] and
+    /// [`Strategy::Random`], so without one of the first two no word of the wordlist is used. If
+    /// none of them yields a replacement, random characters are used.
+    pub fn strategies(mut self, strategies: impl IntoIterator<Item = Strategy>) -> Self {
+        self.strategies = strategies.into_iter().collect();
+        self
+    }
+
+    /// How to replace numbers.
+    pub fn numbers(mut self, numbers: NumberMode) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// How text is split into words.
+    pub fn segmentation(mut self, segmentation: Segmentation) -> Self {
+        self.segmentation = segmentation;
+        self
+    }
+
+    /// The Unicode normalization form of replacements.
+    ///
+    /// Words are always analyzed in NFC, so that decomposed words, as often pasted on macOS,
+    /// are hyphenated and measured like their composed equivalents.
+    pub fn normalize_output(mut self, normalization: Normalization) -> Self {
+        self.normalize_output = normalization;
+        self
+    }
+
+    /// Keep a word unchanged wherever it occurs.
+    pub fn protect(mut self, word: impl Into<EcoString>) -> Self {
+        self.protected.insert(word.into());
+        self
+    }
+
+    /// Seed the random number generator to get reproducible output.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// How the input is interpreted.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// How a typst document is parsed.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl Default for MutilateOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where replacement words come from.
+#[derive(Debug, Clone)]
+pub enum WordlistSource {
+    /// A file containing one word per line.
+    Path(PathBuf),
+    /// Words that are already in memory.
+    Words(Vec<EcoString>),
+}
+
+impl From<PathBuf> for WordlistSource {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<Vec<EcoString>> for WordlistSource {
+    fn from(words: Vec<EcoString>) -> Self {
+        Self::Words(words)
+    }
+}
+
+/// A way of choosing the replacement for a word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Pick a word from the wordlist with the same syllable lengths.
+    Hyphenation,
+    /// Pick a word from the wordlist with the same number of characters.
+    Length,
+    /// Generate random characters.
+    Random,
+    /// Replace whole sentences of running text in typst documents with generated sentences of
+    /// as many words, made of simple clauses like "the old dogs see the red houses".
+    ///
+    /// Only sentences of plain text are replaced, and only in English and German; the words of
+    /// other sentences are replaced by the other strategies.
+    Sentences,
+}
+
+impl Strategy {
+    /// Every strategy.
+    pub const ALL: &'static [Strategy] = &[
+        Strategy::Sentences,
+        Strategy::Hyphenation,
+        Strategy::Length,
+        Strategy::Random,
+    ];
+    const DEFAULT: &'static [Strategy] =
+        &[Strategy::Hyphenation, Strategy::Length, Strategy::Random];
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hyphenation" => Ok(Self::Hyphenation),
+            "length" => Ok(Self::Length),
+            "random" => Ok(Self::Random),
+            "sentences" => Ok(Self::Sentences),
+            _ => Err(format!(
+                "unknown strategy `{s}`, expected one of `hyphenation`, `length`, `random`, \
+                 `sentences`"
+            )),
+        }
+    }
+}
+
+impl Strategy {
+    /// The name of the strategy, as accepted by [`FromStr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hyphenation => "hyphenation",
+            Self::Length => "length",
+            Self::Random => "random",
+            Self::Sentences => "sentences",
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How numbers are replaced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberMode {
+    /// Replace every digit with a random digit.
+    #[default]
+    Digits,
+    /// Keep the order of magnitude and the precision, so that `4,217.50` becomes something
+    /// like `6,893.20` and `0.05` something like `0.08`: zeros before the first significant
+    /// digit of each number are kept, and that digit stays nonzero.
+    Magnitude,
+}
+
+impl FromStr for NumberMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "digits" => Ok(Self::Digits),
+            "magnitude" => Ok(Self::Magnitude),
+            _ => Err(format!(
+                "unknown number mode `{s}`, expected one of `digits`, `magnitude`"
+            )),
+        }
+    }
+}
+
+/// How text is split into the words that are replaced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Segmentation {
+    /// The words of Unicode word segmentation (UAX #29), which keeps contractions like
+    /// `don't`, identifiers like `snake_case`, numbers like `3.14` and words with combining
+    /// marks and joiners together.
+    #[default]
+    Unicode,
+    /// Runs of alphanumeric characters, split at every other character.
+    Alphanumeric,
+}
+
+impl Segmentation {
+    /// The words of a text, with their byte offsets.
+    pub fn words(self, text: &str) -> impl Iterator<Item = (usize, &str)> {
+        crate::scan::Words::new(text, self)
+    }
+}
+
+impl FromStr for Segmentation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unicode" => Ok(Self::Unicode),
+            "alphanumeric" => Ok(Self::Alphanumeric),
+            _ => Err(format!(
+                "unknown segmentation `{s}`, expected one of `unicode`, `alphanumeric`"
+            )),
+        }
+    }
+}
+
+/// The Unicode normalization form of replacements.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    /// Decompose the replacements of decomposed words, which aren't in NFC, and leave the
+    /// others as they are.
+    #[default]
+    Preserve,
+    /// Canonical composition, NFC.
+    Nfc,
+    /// Canonical decomposition, NFD.
+    Nfd,
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(Self::Preserve),
+            "nfc" => Ok(Self::Nfc),
+            "nfd" => Ok(Self::Nfd),
+            _ => Err(format!(
+                "unknown normalization `{s}`, expected one of `preserve`, `nfc`, `nfd`"
+            )),
+        }
+    }
+}
+
+/// The parts of typst documents that are shuffled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shuffle {
+    /// Paragraphs of running text between the headings, lists and other blocks.
+    Paragraphs,
+    /// Sections with their headings, among the sections of the same depth in the same parent
+    /// section.
+    Sections,
+    /// Sentences within their paragraph of running text, with the markup elements in them.
+    Sentences,
+}
+
+impl FromStr for Shuffle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "paragraphs" => Ok(Self::Paragraphs),
+            "sections" => Ok(Self::Sections),
+            "sentences" => Ok(Self::Sentences),
+            _ => Err(format!(
+                "unknown shuffle unit `{s}`, expected one of `paragraphs`, `sections`, \
+                 `sentences`"
+            )),
+        }
+    }
+}
+
+/// How the code in raw text is replaced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawMode {
+    /// Replace its words like those of prose.
+    #[default]
+    Words,
+    /// Replace every identifier and number with random characters of the same kinds, keeping
+    /// the brackets, punctuation and whitespace, so that every line keeps its length and
+    /// indentation.
+    Filler,
+}
+
+impl FromStr for RawMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "words" => Ok(Self::Words),
+            "filler" => Ok(Self::Filler),
+            _ => Err(format!(
+                "unknown raw mode `{s}`, expected one of `words`, `filler`"
+            )),
+        }
+    }
+}
+
+/// What happens to the quotations of typst documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quotes {
+    /// Keep quotations, like excerpts that serve as evidence.
+    Keep,
+    /// Only replace quotations, like copyrighted excerpts, and keep everything else.
+    Only,
+}
+
+impl FromStr for Quotes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "only" => Ok(Self::Only),
+            _ => Err(format!(
+                "unknown quotation handling `{s}`, expected one of `keep`, `only`"
+            )),
+        }
+    }
+}
+
+/// How the input is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// A typst document.
+    Typst,
+    /// Plain text, in which every word is replaced.
+    Plain,
+    /// A Markdown document, in which code, link destinations and front matter are kept.
+    Markdown,
+    /// A LaTeX document, in which commands, environments and math are kept.
+    Latex,
+}
+
+impl Format {
+    /// Guess the format from a file extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "typ" | "typc" => Some(Self::Typst),
+            "txt" => Some(Self::Plain),
+            "md" | "markdown" => Some(Self::Markdown),
+            "tex" | "ltx" => Some(Self::Latex),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "typst" => Ok(Self::Typst),
+            "plain" => Ok(Self::Plain),
+            "markdown" => Ok(Self::Markdown),
+            "latex" => Ok(Self::Latex),
+            _ => Err(format!(
+                "unknown format `{s}`, expected one of `typst`, `plain`, `markdown`, `latex`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Typst => "typst",
+            Self::Plain => "plain",
+            Self::Markdown => "markdown",
+            Self::Latex => "latex",
+        })
+    }
+}
+
+/// How a typst document is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Markup, like a regular `.typ` file.
+    Markup,
+    /// Code, like a `.typc` file.
+    Code,
+    /// Math, like the contents of an equation.
+    Math,
+}
+
+impl Mode {
+    /// Guess the mode from a file extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "typ" => Some(Self::Markup),
+            "typc" => Some(Self::Code),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markup" => Ok(Self::Markup),
+            "code" => Ok(Self::Code),
+            "math" => Ok(Self::Math),
+            _ => Err(format!(
+                "unknown mode `{s}`, expected one of `markup`, `code`, `math`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Markup => "markup",
+            Self::Code => "code",
+            Self::Math => "math",
+        })
+    }
+}
+
+/// The line breaks of a mutilated document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Newline {
+    /// `\n`, as on Unix.
+    Lf,
+    /// `\r\n`, as on Windows.
+    Crlf,
+    /// The line breaks of the platform.
+    Native,
+}
+
+impl Newline {
+    /// The line break.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+            Self::Native if cfg!(windows) => "\r\n",
+            Self::Native => "\n",
+        }
+    }
+}
+
+impl FromStr for Newline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lf" => Ok(Self::Lf),
+            "crlf" => Ok(Self::Crlf),
+            "native" => Ok(Self::Native),
+            _ => Err(format!(
+                "unknown newline `{s}`, expected one of `lf`, `crlf`, `native`"
+            )),
+        }
+    }
+}
+
+/// Look up the hyphenation language for a language tag.
+///
+/// Accepts ISO 639-1 codes like `de`, ISO 639-2 and 639-3 codes like `deu` and BCP 47 tags
+/// like `de-AT`, whose subtags after the language are ignored.
+pub fn parse_language(tag: &str) -> Option<Lang> {
+    let primary = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+    let code = match primary.len() {
+        2 => primary.as_str(),
+        3 => {
+            THREE_LETTER_CODES
+                .iter()
+                .find(|(codes, _)| codes.contains(&primary.as_str()))?
+                .1
+        }
+        _ => return None,
+    };
+    let &[a, b] = code.as_bytes() else {
+        return None;
+    };
+    Lang::from_iso([a, b])
+}
+
+/// Every language that can be hyphenated, with its ISO 639-1 code, sorted by the code.
+pub fn supported_languages() -> impl Iterator<Item = (&'static str, Lang)> {
+    let mut codes: Vec<_> = THREE_LETTER_CODES.iter().map(|&(_, code)| code).collect();
+    codes.sort_unstable();
+    codes
+        .into_iter()
+        .filter_map(|code| Some((code, parse_language(code)?)))
+}
+
+/// The ISO 639-3 code of a language, like `deu`.
+#[cfg(feature = "detect")]
+pub(crate) fn three_letter_code(language: Lang) -> Option<&'static str> {
+    THREE_LETTER_CODES
+        .iter()
+        .find(|(_, code)| parse_language(code) == Some(language))
+        .map(|(codes, _)| codes[0])
+}
+
+/// The ISO 639-2 and 639-3 codes of the languages that can be hyphenated, with their ISO 639-1
+/// code.
+const THREE_LETTER_CODES: &[(&[&str], &str)] = &[
+    (&["afr"], "af"),
+    (&["sqi", "alb"], "sq"),
+    (&["bel"], "be"),
+    (&["bul"], "bg"),
+    (&["cat"], "ca"),
+    (&["hrv"], "hr"),
+    (&["ces", "cze"], "cs"),
+    (&["dan"], "da"),
+    (&["nld", "dut"], "nl"),
+    (&["eng"], "en"),
+    (&["est", "ekk"], "et"),
+    (&["fin"], "fi"),
+    (&["fra", "fre"], "fr"),
+    (&["kat", "geo"], "ka"),
+    (&["deu", "ger"], "de"),
+    (&["ell", "gre"], "el"),
+    (&["hun"], "hu"),
+    (&["isl", "ice"], "is"),
+    (&["ita"], "it"),
+    (&["kur", "kmr"], "ku"),
+    (&["lat"], "la"),
+    (&["lit"], "lt"),
+    (&["mon", "khk"], "mn"),
+    (&["nor", "nob", "nno"], "no"),
+    (&["pol"], "pl"),
+    (&["por"], "pt"),
+    (&["rus"], "ru"),
+    (&["srp"], "sr"),
+    (&["slk", "slo"], "sk"),
+    (&["slv"], "sl"),
+    (&["spa"], "es"),
+    (&["swe"], "sv"),
+    (&["tur"], "tr"),
+    (&["tuk"], "tk"),
+    (&["ukr"], "uk"),
+];
@@ -0,0 +1,339 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use ecow::EcoString;
+use typst_syntax::{ast, LinkedNode, SyntaxKind};
+
+use crate::{metadata, Context, Edit, Mode};
+
+/// Words that can't be the names of bindings.
+const KEYWORDS: &[&str] = &[
+    "none", "auto", "true", "false", "not", "and", "or", "let", "set", "show", "context", "if",
+    "else", "for", "in", "while", "break", "continue", "return", "import", "include", "as",
+];
+
+/// The bindings of the typst files of a package, of which the internal ones are renamed in all
+/// files alike, while the names that the entrypoint exports stay unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct Package {
+    /// The new names of the internal bindings.
+    renames: HashMap<EcoString, EcoString>,
+}
+
+/// The paths of the files of its project that a typst document imports, includes or loads, like
+/// `chapter.typ` of `#include "chapter.typ"` or the path of an image, without packages.
+pub fn project_files(code: &str, mode: Mode) -> Vec<EcoString> {
+    let syntax = match mode {
+        Mode::Markup => typst_syntax::parse(code),
+        Mode::Code => typst_syntax::parse_code(code),
+        Mode::Math => typst_syntax::parse_math(code),
+    };
+    let mut files = Vec::new();
+    collect_files(&LinkedNode::new(&syntax), &mut files);
+    files
+}
+
+fn collect_files(node: &LinkedNode, files: &mut Vec<EcoString>) {
+    let source = match node.kind() {
+        SyntaxKind::ModuleImport => Some(node.cast::<ast::ModuleImport>().unwrap().source()),
+        SyntaxKind::ModuleInclude => Some(node.cast::<ast::ModuleInclude>().unwrap().source()),
+        _ => None,
+    };
+    match source {
+        Some(ast::Expr::Str(path)) if !path.get().starts_with('@') => files.push(path.get()),
+        _ if node.kind() == SyntaxKind::Str && (crate::loads(node) || bibliography(node)) => {
+            files.push(node.cast::<ast::Str>().unwrap().get());
+        }
+        _ => {}
+    }
+    for child in node.children() {
+        collect_files(&child, files);
+    }
+}
+
+/// Whether a string is an argument of a `bibliography` call.
+fn bibliography(node: &LinkedNode) -> bool {
+    node.parent()
+        .filter(|parent| parent.kind() == SyntaxKind::Args)
+        .and_then(LinkedNode::parent)
+        .and_then(|call| call.cast::<ast::FuncCall>())
+        .is_some_and(|call| {
+            matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "bibliography")
+        })
+}
+
+/// The names that a file of a package binds, imports and uses.
+#[derive(Debug, Default)]
+struct Module {
+    /// The names of `let` bindings, parameters and loop variables.
+    bound: HashSet<EcoString>,
+    /// The names imported from other files of the package.
+    imported: HashSet<EcoString>,
+    /// The names imported from other packages.
+    external: HashSet<EcoString>,
+    /// The files of the package whose whole scope is imported.
+    wildcards: Vec<PathBuf>,
+    /// The names of identifiers.
+    used: HashSet<EcoString>,
+    /// The names of named arguments, dictionary keys and fields, which may belong to typst's
+    /// own functions.
+    keys: HashSet<EcoString>,
+}
+
+impl Package {
+    /// Analyze the typst files of a package, given by their paths relative to its root, and
+    /// choose new names for the bindings that the `entrypoint` doesn't export, which are all
+    /// names it binds or imports.
+    ///
+    /// A name is only renamed if every file that uses it binds or imports it and no file uses
+    /// it as the name of a named argument, dictionary key or field, so that the names of
+    /// typst's own functions and of other packages and everything that is accessed as a field
+    /// stay unchanged.
+    pub fn new<'a>(
+        files: impl IntoIterator<Item = (&'a Path, &'a str)>,
+        entrypoint: &Path,
+        context: &mut Context,
+    ) -> io::Result<Self> {
+        let mut modules = HashMap::new();
+        for (path, code) in files {
+            let syntax = typst_syntax::parse(code);
+            let path = normalize(path);
+            let mut module = Module::default();
+            module.scan(&LinkedNode::new(&syntax), &path);
+            modules.insert(path, module);
+        }
+        let known: HashMap<&PathBuf, HashSet<EcoString>> = modules
+            .keys()
+            .map(|path| (path, known(&modules, path, &mut HashSet::new())))
+            .collect();
+        let exported = known
+            .get(&normalize(entrypoint))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut candidates: Vec<&EcoString> = modules
+            .values()
+            .flat_map(|module| &module.bound)
+            .filter(|name| !exported.contains(*name) && name.as_str() != "_")
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates.retain(|name| {
+            modules.iter().all(|(path, module)| {
+                !module.keys.contains(*name)
+                    && !module.external.contains(*name)
+                    && (!module.used.contains(*name) || known[path].contains(*name))
+            })
+        });
+
+        let mut taken: HashSet<EcoString> = modules
+            .values()
+            .flat_map(|module| module.used.iter().chain(&module.keys))
+            .cloned()
+            .collect();
+        let mut renames = HashMap::new();
+        for name in candidates {
+            let fake = fake_name(name, &taken, context)?;
+            taken.insert(fake.clone());
+            renames.insert(name.clone(), fake);
+        }
+        Ok(Self { renames })
+    }
+
+    /// Read the entrypoint from the source of a `typst.toml` package manifest.
+    pub fn entrypoint(toml: &str) -> Option<&str> {
+        metadata::package_entrypoint(toml)
+    }
+
+    /// The new names of the internal bindings by their original names.
+    pub fn renames(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.renames
+            .iter()
+            .map(|(name, fake)| (name.as_str(), fake.as_str()))
+    }
+
+    /// Compute the edits that rename the internal bindings in a file of the package.
+    pub(crate) fn plan(&self, node: &LinkedNode, edits: &mut Vec<Edit>) {
+        if matches!(node.kind(), SyntaxKind::Ident | SyntaxKind::MathIdent) {
+            if let Some(fake) = self.renames.get(node.text()) {
+                edits.push(Edit {
+                    range: node.range(),
+                    replacement: fake.clone(),
+                });
+            }
+            return;
+        }
+        for child in node.children() {
+            self.plan(&child, edits);
+        }
+    }
+}
+
+impl Module {
+    /// Collect the names of a node of the file at `path` and its descendants.
+    fn scan(&mut self, node: &LinkedNode, path: &Path) {
+        match node.kind() {
+            SyntaxKind::Ident | SyntaxKind::MathIdent => {
+                self.used.insert(node.text().clone());
+            }
+            SyntaxKind::LetBinding => {
+                let binding: ast::LetBinding = node.cast().unwrap();
+                self.bind(binding.kind().bindings());
+            }
+            SyntaxKind::ForLoop => {
+                let for_loop: ast::ForLoop = node.cast().unwrap();
+                self.bind(for_loop.pattern().bindings());
+            }
+            SyntaxKind::Params => {
+                let params: ast::Params = node.cast().unwrap();
+                for param in params.children() {
+                    match param {
+                        ast::Param::Pos(pattern) => self.bind(pattern.bindings()),
+                        ast::Param::Named(named) => {
+                            self.keys.insert(named.name().get().clone());
+                        }
+                        ast::Param::Spread(spread) => self.bind(spread.sink_ident()),
+                    }
+                }
+            }
+            SyntaxKind::Named => {
+                let named: ast::Named = node.cast().unwrap();
+                self.keys.insert(named.name().get().clone());
+            }
+            SyntaxKind::FieldAccess => {
+                let access: ast::FieldAccess = node.cast().unwrap();
+                self.keys.insert(access.field().get().clone());
+            }
+            SyntaxKind::ModuleImport => self.import(node.cast().unwrap(), path),
+            _ => {}
+        }
+        for child in node.children() {
+            self.scan(&child, path);
+        }
+    }
+
+    fn bind<'a>(&mut self, idents: impl IntoIterator<Item = ast::Ident<'a>>) {
+        self.bound
+            .extend(idents.into_iter().map(|ident| ident.get().clone()));
+    }
+
+    /// Collect the names that an import binds.
+    fn import(&mut self, import: ast::ModuleImport, path: &Path) {
+        let ast::Expr::Str(source) = import.source() else {
+            return;
+        };
+        let source = source.get();
+        let file = (!source.starts_with('@')).then(|| resolve(path, &source));
+        let mut names = Vec::new();
+        match import.imports() {
+            Some(ast::Imports::Items(items)) => {
+                for item in items.iter() {
+                    names.push(item.original_name().get().clone());
+                    names.push(item.bound_name().get().clone());
+                }
+            }
+            Some(ast::Imports::Wildcard) => self.wildcards.extend(file.clone()),
+            None => {}
+        }
+        // Without items, an import binds the name of the file or package, like `as` does.
+        match (import.new_name(), import.imports()) {
+            (Some(name), _) => names.push(name.get().clone()),
+            (None, None) => {
+                let name = source.rsplit('/').next().unwrap_or_default();
+                names.push(name.split([':', '.']).next().unwrap_or_default().into());
+            }
+            (None, Some(_)) => {}
+        }
+        match file {
+            Some(_) => self.imported.extend(names),
+            None => self.external.extend(names),
+        }
+    }
+}
+
+/// The names that the file at `path` binds or imports, including everything in the scope of
+/// the files whose whole scope it imports.
+fn known(
+    modules: &HashMap<PathBuf, Module>,
+    path: &Path,
+    visited: &mut HashSet<PathBuf>,
+) -> HashSet<EcoString> {
+    let mut names = HashSet::new();
+    let Some(module) = modules.get(path) else {
+        return names;
+    };
+    if !visited.insert(path.to_owned()) {
+        return names;
+    }
+    names.extend(module.bound.iter().cloned());
+    names.extend(module.imported.iter().cloned());
+    for wildcard in &module.wildcards {
+        names.extend(known(modules, wildcard, visited));
+    }
+    names
+}
+
+/// A new name for a binding with every word of it replaced, which is no keyword and isn't used
+/// in the package yet.
+fn fake_name(
+    name: &str,
+    taken: &HashSet<EcoString>,
+    context: &mut Context,
+) -> io::Result<EcoString> {
+    let mut fake = EcoString::new();
+    let mut start = 0;
+    for (i, c) in name.char_indices().chain([(name.len(), '-')]) {
+        if c.is_alphanumeric() {
+            continue;
+        }
+        if start < i {
+            let replacement = crate::choose_replacement(&name[start..i], context)?;
+            fake.extend(
+                replacement
+                    .chars()
+                    .filter(|&c| typst_syntax::is_id_continue(c) && c != '-'),
+            );
+        }
+        if i < name.len() {
+            fake.push(c);
+        }
+        start = i + c.len_utf8();
+    }
+    if !typst_syntax::is_ident(&fake) {
+        fake = "binding".into();
+    }
+    let mut unique = fake.clone();
+    let mut n = 2;
+    while KEYWORDS.contains(&unique.as_str()) || taken.contains(&unique) {
+        unique = ecow::eco_format!("{fake}{n}");
+        n += 1;
+    }
+    Ok(unique)
+}
+
+/// The path of the file that an import in the file at `path` refers to, relative to the root
+/// of the package.
+fn resolve(path: &Path, source: &str) -> PathBuf {
+    match source.strip_prefix('/') {
+        Some(absolute) => normalize(Path::new(absolute)),
+        None => normalize(&path.parent().unwrap_or(Path::new("")).join(source)),
+    }
+}
+
+/// A relative path without `.` and `..` components.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(part) => normalized.push(part),
+            _ => {}
+        }
+    }
+    normalized
+}
@@ -0,0 +1,277 @@
+use std::{collections::HashMap, io, path::Path};
+
+use ecow::EcoVec;
+use sha2::{Digest, Sha256};
+
+/// Hyphenation patterns in the format of TeX's `\patterns` or of the binary tries of hypher,
+/// for languages that the built-in hyphenation doesn't support.
+///
+/// Words are hyphenated using Liang's algorithm, the same one TeX uses. Exceptions from a
+/// `\hyphenation` block take precedence over the patterns.
+#[derive(Debug)]
+pub struct Patterns {
+    /// The levels between the letters of each pattern, by its letters.
+    patterns: HashMap<String, Vec<u8>>,
+    /// The syllable lengths of words whose hyphenation is given explicitly.
+    exceptions: HashMap<String, EcoVec<u8>>,
+    /// The number of letters in the longest pattern.
+    max_len: usize,
+    /// The SHA-256 hash of the source, which identifies the patterns in caches.
+    pub(crate) digest: [u8; 32],
+}
+
+/// The minimum number of characters before the first and after the last hyphen, like TeX's
+/// `\lefthyphenmin` and `\righthyphenmin`.
+const LEFT_MIN: usize = 2;
+const RIGHT_MIN: usize = 2;
+
+impl Patterns {
+    /// Parse patterns from the source of a TeX pattern file.
+    ///
+    /// The patterns are read from the `\patterns{...}` group, or from the whole file if there is
+    /// none, and exceptions from the `\hyphenation{...}` group. Comments are ignored.
+    pub fn parse(source: &str) -> Self {
+        let code: String = source
+            .lines()
+            .map(|line| line.split('%').next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut patterns = HashMap::new();
+        let mut max_len = 0;
+        for pattern in group(&code, "\\patterns")
+            .unwrap_or(&code)
+            .split_whitespace()
+        {
+            let mut letters = String::new();
+            let mut levels = vec![0];
+            for c in pattern.chars() {
+                match c.to_digit(10) {
+                    Some(level) => *levels.last_mut().unwrap() = level as u8,
+                    None => {
+                        letters.extend(c.to_lowercase());
+                        levels.push(0);
+                    }
+                }
+            }
+            if !letters.is_empty() {
+                max_len = max_len.max(letters.chars().count());
+                patterns.insert(letters, levels);
+            }
+        }
+
+        let mut exceptions = HashMap::new();
+        for exception in group(&code, "\\hyphenation")
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            let syllables: EcoVec<u8> = exception
+                .split('-')
+                .map(|syllable| syllable.chars().count().try_into().unwrap_or(u8::MAX))
+                .collect();
+            exceptions.insert(exception.replace('-', "").to_lowercase(), syllables);
+        }
+
+        Self {
+            patterns,
+            exceptions,
+            max_len,
+            digest: Sha256::digest(source).into(),
+        }
+    }
+
+    /// Decode patterns from a binary trie in the format that hypher embeds for its languages,
+    /// like the `.bin` files generated by its build or the `.bincode` files of typst.
+    ///
+    /// Tries have no exceptions.
+    pub fn from_trie(data: &[u8]) -> io::Result<Self> {
+        let root = data
+            .get(..4)
+            .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()) as usize)
+            .ok_or_else(invalid_trie)?;
+        let mut patterns = HashMap::new();
+        let mut max_len = 0;
+        let mut path = Vec::new();
+        let mut visits = 0;
+        walk(data, root, &mut path, &mut visits, &mut |path, levels| {
+            let Ok(letters) = std::str::from_utf8(path) else {
+                return;
+            };
+            let mut points = vec![0; letters.chars().count() + 1];
+            for (offset, level) in levels {
+                if let Some(prefix) = letters.get(..offset) {
+                    points[prefix.chars().count()] = level;
+                }
+            }
+            max_len = max_len.max(letters.chars().count());
+            patterns.insert(letters.to_owned(), points);
+        })?;
+        Ok(Self {
+            patterns,
+            exceptions: HashMap::new(),
+            max_len,
+            digest: Sha256::digest(data).into(),
+        })
+    }
+
+    /// Load patterns from a TeX pattern file, or from a binary trie if the file ends in `.bin`
+    /// or `.bincode`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        if path
+            .extension()
+            .is_some_and(|ext| ext == "bin" || ext == "bincode")
+        {
+            return Self::from_trie(&std::fs::read(path)?);
+        }
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Compute the number of characters in each syllable of `word`.
+    pub(crate) fn hyphenate(&self, word: &str) -> EcoVec<u8> {
+        // Lowercase character by character, so that positions still match the word.
+        let chars: Vec<char> = word
+            .chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect();
+        let lowercase: String = chars.iter().collect();
+        if let Some(syllables) = self.exceptions.get(&lowercase) {
+            return syllables.clone();
+        }
+
+        let dotted: Vec<char> = std::iter::once('.')
+            .chain(chars.iter().copied())
+            .chain(std::iter::once('.'))
+            .collect();
+        let mut points = vec![0u8; dotted.len() + 1];
+        let mut key = String::new();
+        for start in 0..dotted.len() {
+            key.clear();
+            for &c in dotted[start..].iter().take(self.max_len) {
+                key.push(c);
+                if let Some(levels) = self.patterns.get(&key) {
+                    for (offset, &level) in levels.iter().enumerate() {
+                        let point = &mut points[start + offset];
+                        *point = (*point).max(level);
+                    }
+                }
+            }
+        }
+
+        // The point before the character at `i` of the word is at `i + 1` in the dotted word.
+        let mut syllables = EcoVec::new();
+        let mut last = 0;
+        for i in LEFT_MIN..=chars.len().saturating_sub(RIGHT_MIN) {
+            if points[i + 1] % 2 == 1 {
+                syllables.push((i - last).try_into().unwrap_or(u8::MAX));
+                last = i;
+            }
+        }
+        syllables.push((chars.len() - last).try_into().unwrap_or(u8::MAX));
+        syllables
+    }
+}
+
+/// The longest path through a trie, which no pattern is as long as.
+const MAX_TRIE_DEPTH: usize = 64;
+
+/// The most nodes visited in a trie, which keeps broken tries with shared nodes from taking
+/// forever.
+const MAX_TRIE_VISITS: usize = 1 << 22;
+
+/// Visit the patterns of the node of a trie at `addr` and its descendants, with the bytes of
+/// the path to them and the byte offsets and levels of their points.
+fn walk(
+    data: &[u8],
+    addr: usize,
+    path: &mut Vec<u8>,
+    visits: &mut usize,
+    visit: &mut impl FnMut(&[u8], Vec<(usize, u8)>),
+) -> io::Result<()> {
+    *visits += 1;
+    if path.len() > MAX_TRIE_DEPTH || *visits > MAX_TRIE_VISITS {
+        return Err(invalid_trie());
+    }
+    let node = data.get(addr..).ok_or_else(invalid_trie)?;
+    let byte = |pos: usize| node.get(pos).copied().ok_or_else(invalid_trie);
+    let header = byte(0)?;
+    let has_levels = header >> 7 != 0;
+    let stride = usize::from((header >> 5) & 3);
+    let mut count = usize::from(header & 31);
+    let mut pos = 1;
+    if count == 31 {
+        count = usize::from(byte(pos)?);
+        pos += 1;
+    }
+
+    if has_levels {
+        let offset = usize::from(byte(pos)?) << 4 | usize::from(byte(pos + 1)?) >> 4;
+        let len = usize::from(byte(pos + 1)? & 15);
+        let packed = data.get(offset..offset + len).ok_or_else(invalid_trie)?;
+        let mut distance = 0;
+        let levels = packed
+            .iter()
+            .map(|&packed| {
+                distance += usize::from(packed / 10);
+                (distance, packed % 10)
+            })
+            .collect();
+        visit(path, levels);
+        pos += 2;
+    }
+
+    let transitions = node.get(pos..pos + count).ok_or_else(invalid_trie)?;
+    let targets = node
+        .get(pos + count..pos + count + stride * count)
+        .ok_or_else(invalid_trie)?;
+    for (i, &b) in transitions.iter().enumerate() {
+        let target = &targets[i * stride..(i + 1) * stride];
+        let delta = match *target {
+            [a] => isize::from(a as i8),
+            [a, b] => isize::from(i16::from_be_bytes([a, b])),
+            [a, b, c] => {
+                (usize::from(a) << 16 | usize::from(b) << 8 | usize::from(c)) as isize - (1 << 23)
+            }
+            _ => return Err(invalid_trie()),
+        };
+        let next = usize::try_from(addr as isize + delta).map_err(|_| invalid_trie())?;
+        path.push(b);
+        walk(data, next, path, visits, visit)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+fn invalid_trie() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid hyphenation trie")
+}
+
+/// The contents of the group after a command, like `\patterns{...}`.
+fn group<'a>(code: &'a str, command: &str) -> Option<&'a str> {
+    let start = code.find(command)? + command.len();
+    let rest = code[start..].trim_start().strip_prefix('{')?;
+    Some(&rest[..rest.find('}').unwrap_or(rest.len())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tex_patterns() {
+        let patterns = Patterns::parse("\\patterns{a1b}\n\\hyphenation{ab-ab}");
+        assert_eq!(patterns.hyphenate("xxabyy").as_slice(), [3, 3]);
+        assert_eq!(patterns.hyphenate("abab").as_slice(), [2, 2]);
+    }
+
+    #[test]
+    fn trie() {
+        // The root at 5 leads through `a` and `b` to a node with the level 1 before `b`,
+        // which is stored at 4.
+        let data = [
+            0, 0, 0, 5, 11, 0x21, b'a', 3, 0x21, b'b', 3, 0x80, 0x00, 0x41,
+        ];
+        let patterns = Patterns::from_trie(&data).unwrap();
+        assert_eq!(patterns.hyphenate("xxabyy").as_slice(), [3, 3]);
+        assert!(Patterns::from_trie(&data[..12]).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use std::ops::Range;
+
+use ecow::EcoString;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// A kind of personally identifiable information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Iban,
+    /// A national insurance number of the United Kingdom, like `QQ 12 34 56 C`.
+    InsuranceNumber,
+    /// A social security number of the United States, like `123-45-6789`.
+    SocialSecurityNumber,
+    /// A digit run that passes the Luhn check, like a credit card number.
+    CardNumber,
+    PhoneNumber,
+    /// A long run of digits, which is likely an identifier of some sort.
+    DigitRun,
+}
+
+/// The number of digits in a phone number.
+const PHONE_DIGITS: Range<usize> = 7..16;
+/// The number of digits in a card number.
+const CARD_DIGITS: Range<usize> = 13..20;
+/// The minimum number of digits in a run without separators to be considered an identifier.
+const DIGIT_RUN_MIN: usize = 9;
+
+/// Finds identifiers in text and replaces them with fakes of the same format.
+pub(crate) struct Scrubber {
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Scrubber {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x5c2b_9e1d_7f4a_6083);
+        Self { rng }
+    }
+
+    /// Find the identifiers in `text` and choose their replacements.
+    ///
+    /// The ranges are sorted and disjoint.
+    pub(crate) fn scrub(&mut self, text: &str) -> Vec<(Range<usize>, EcoString)> {
+        let bytes = text.as_bytes();
+        let mut found = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let may_start = matches!(bytes[i], b'0'..=b'9' | b'A'..=b'Z' | b'+' | b'(');
+            if may_start && !follows_word_char(text, i) {
+                if let Some((end, kind)) = find_at(bytes, i) {
+                    let fake = self.fake(&text[i..end], kind);
+                    found.push((i..end, fake));
+                    i = end;
+                    continue;
+                }
+            }
+            i += text[i..].chars().next().unwrap().len_utf8();
+        }
+        found
+    }
+
+    /// Generate a fake identifier with the same format as `original`.
+    fn fake(&mut self, original: &str, kind: Kind) -> EcoString {
+        let mut fake: Vec<u8> = original.bytes().collect();
+        for i in 0..fake.len() {
+            let keep = match kind {
+                // The country code.
+                Kind::Iban => i < 2,
+                // Trunk prefixes, like the zero in `(0)30` or `0171`.
+                Kind::PhoneNumber => fake[i] == b'0' && (i == 0 || !fake[i - 1].is_ascii_digit()),
+                _ => false,
+            };
+            if keep {
+                continue;
+            }
+            fake[i] = match fake[i] {
+                b'0'..=b'9' => self.rng.gen_range(b'0'..=b'9'),
+                // The suffix of insurance numbers is one of the first four letters.
+                b'A'..=b'Z' if kind == Kind::InsuranceNumber && i == fake.len() - 1 => {
+                    self.rng.gen_range(b'A'..=b'D')
+                }
+                b'A'..=b'Z' => self.rng.gen_range(b'A'..=b'Z'),
+                b'a'..=b'z' => self.rng.gen_range(b'a'..=b'z'),
+                other => other,
+            };
+        }
+
+        // Keep the checksums valid, so that the fakes look like the real thing to validators.
+        match kind {
+            Kind::Iban => {
+                let check = 98 - iban_remainder(&fake, false);
+                fake[2] = b'0' + (check / 10) as u8;
+                fake[3] = b'0' + (check % 10) as u8;
+            }
+            Kind::CardNumber => {
+                let last = fake.iter().rposition(u8::is_ascii_digit).unwrap();
+                fake[last] = b'0';
+                fake[last] = b'0' + ((10 - luhn_sum(&fake) % 10) % 10) as u8;
+            }
+            _ => {}
+        }
+
+        // Only ASCII bytes were replaced by ASCII bytes.
+        String::from_utf8(fake).unwrap().into()
+    }
+}
+
+/// Matches an identifier at the start of the bytes, returning its length.
+type Matcher = fn(&[u8]) -> Option<usize>;
+
+/// Find an identifier starting at byte `start`, returning where it ends and its kind.
+fn find_at(bytes: &[u8], start: usize) -> Option<(usize, Kind)> {
+    let matchers: [(Matcher, Kind); 6] = [
+        (match_iban, Kind::Iban),
+        (match_insurance_number, Kind::InsuranceNumber),
+        (match_social_security_number, Kind::SocialSecurityNumber),
+        (match_card_number, Kind::CardNumber),
+        (match_phone_number, Kind::PhoneNumber),
+        (match_digit_run, Kind::DigitRun),
+    ];
+    let rest = &bytes[start..];
+    matchers.into_iter().find_map(|(matcher, kind)| {
+        let len = matcher(rest)?;
+        let end = start + len;
+        let at_boundary = !bytes.get(end).is_some_and(u8::is_ascii_alphanumeric);
+        at_boundary.then_some((end, kind))
+    })
+}
+
+/// Whether the character before byte `index` belongs to a word.
+fn follows_word_char(text: &str, index: usize) -> bool {
+    text[..index]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric)
+}
+
+/// Match two uppercase letters, two check digits and the account number, which may be split
+/// into groups by single spaces, if the checksum is valid.
+fn match_iban(bytes: &[u8]) -> Option<usize> {
+    let head = bytes.get(..4)?;
+    if !head[..2].iter().all(u8::is_ascii_uppercase) || !head[2..].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    // Try the longest candidate first, as the next word may look like part of the number.
+    let mut ends = Vec::new();
+    let mut len = 4;
+    let mut chars = 4;
+    loop {
+        match bytes.get(len) {
+            Some(b) if b.is_ascii_uppercase() || b.is_ascii_digit() => {
+                len += 1;
+                chars += 1;
+            }
+            Some(b' ') if bytes.get(len + 1).is_some_and(u8::is_ascii_alphanumeric) => {
+                ends.push((len, chars));
+                len += 1;
+            }
+            _ => {
+                // A space followed by a word isn't part of the number.
+                if bytes[len - 1] != b' ' {
+                    ends.push((len, chars));
+                }
+                break;
+            }
+        }
+        if chars > 34 {
+            break;
+        }
+    }
+    ends.into_iter()
+        .rev()
+        .filter(|&(_, chars)| (15..=34).contains(&chars))
+        .filter(|&(len, _)| !bytes.get(len).is_some_and(u8::is_ascii_alphanumeric))
+        .find(|&(len, _)| iban_remainder(&bytes[..len], true) == 1)
+        .map(|(len, _)| len)
+}
+
+/// The remainder of the IBAN modulo 97, optionally counting the check digits.
+///
+/// A valid IBAN has a remainder of 1 including the check digits, and the check digits are
+/// 98 minus the remainder without them.
+fn iban_remainder(iban: &[u8], with_check: bool) -> u32 {
+    let compact: Vec<u8> = iban.iter().copied().filter(|&b| b != b' ').collect();
+    let (head, account) = compact.split_at(4);
+    let check = if with_check {
+        [head[2], head[3]]
+    } else {
+        [b'0', b'0']
+    };
+    let rearranged = account.iter().chain(&head[..2]).chain(&check);
+    rearranged.fold(0, |remainder, &b| {
+        if b.is_ascii_digit() {
+            (remainder * 10 + u32::from(b - b'0')) % 97
+        } else {
+            (remainder * 100 + u32::from(b - b'A') + 10) % 97
+        }
+    })
+}
+
+/// Match `AA999999A`, optionally with the digits in pairs separated by spaces.
+fn match_insurance_number(bytes: &[u8]) -> Option<usize> {
+    let mut len = 0;
+    let mut take = |pred: fn(&u8) -> bool, count: usize, spaced: bool| {
+        if spaced && bytes.get(len) == Some(&b' ') {
+            len += 1;
+        }
+        let run = bytes.get(len..len + count)?;
+        run.iter().all(pred).then(|| len += count)
+    };
+    take(u8::is_ascii_uppercase, 2, false)?;
+    let spaced = bytes.get(2) == Some(&b' ');
+    for _ in 0..3 {
+        take(u8::is_ascii_digit, 2, spaced)?;
+    }
+    take(|b| (b'A'..=b'D').contains(b), 1, spaced)?;
+    Some(len)
+}
+
+/// Match `999-99-9999`.
+fn match_social_security_number(bytes: &[u8]) -> Option<usize> {
+    let shape = b"999-99-9999";
+    let candidate = bytes.get(..shape.len())?;
+    let matches = candidate.iter().zip(shape).all(|(b, s)| match s {
+        b'9' => b.is_ascii_digit(),
+        _ => b == s,
+    });
+    matches.then_some(shape.len())
+}
+
+/// Match digits that are optionally grouped by single spaces or hyphens and pass the Luhn
+/// check.
+fn match_card_number(bytes: &[u8]) -> Option<usize> {
+    if !bytes.first()?.is_ascii_digit() {
+        return None;
+    }
+    let (len, digits) = grouped_digits(bytes, b" -", 1);
+    (CARD_DIGITS.contains(&digits) && luhn_sum(&bytes[..len]).is_multiple_of(10)).then_some(len)
+}
+
+/// Match an international number, or a national number with a trunk prefix, which may be
+/// grouped by spaces, hyphens, dots, slashes and parentheses.
+fn match_phone_number(bytes: &[u8]) -> Option<usize> {
+    let start = match bytes {
+        [b'+', b'0'..=b'9', ..] => 1,
+        [b'0', b'0'..=b'9', ..] | [b'(', b'0'..=b'9', ..] => 0,
+        _ => return None,
+    };
+    let (len, digits) = grouped_digits(&bytes[start..], b" -./()", 2);
+    PHONE_DIGITS.contains(&digits).then_some(start + len)
+}
+
+/// Match a run of digits without separators.
+fn match_digit_run(bytes: &[u8]) -> Option<usize> {
+    let len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    (len >= DIGIT_RUN_MIN).then_some(len)
+}
+
+/// The length of the digits at the start of `bytes` that are separated by runs of at most
+/// `max_run` of the `separators`, ending at a digit, and the number of digits in it.
+fn grouped_digits(bytes: &[u8], separators: &[u8], max_run: usize) -> (usize, usize) {
+    let mut len = 0;
+    let mut digits = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = bytes[i..]
+            .iter()
+            .take_while(|b| separators.contains(b))
+            .count();
+        // A space after punctuation other than a closing parenthesis ends a sentence or
+        // clause, not a group.
+        let separator = &bytes[i..i + run];
+        let spaces = separator.iter().filter(|&&b| b == b' ').count();
+        let clause_end = run > 1 && separator[run - 1] == b' ' && separator[0] != b')';
+        if run > max_run || spaces > 1 || clause_end {
+            break;
+        }
+        i += run;
+        if !bytes.get(i).is_some_and(u8::is_ascii_digit) {
+            break;
+        }
+        while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+            digits += 1;
+        }
+        len = i;
+    }
+    (len, digits)
+}
+
+/// The sum of the Luhn algorithm over the digits in `bytes`, which is divisible by 10 for
+/// valid numbers.
+fn luhn_sum(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .rev()
+        .filter(|b| b.is_ascii_digit())
+        .enumerate()
+        .map(|(i, &b)| {
+            let digit = u32::from(b - b'0');
+            match i % 2 {
+                0 => digit,
+                _ if digit > 4 => digit * 2 - 9,
+                _ => digit * 2,
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iban_followed_by_word() {
+        let text = b"DE89 3704 0044 0532 0130 00 here.";
+        assert_eq!(find_at(text, 0), Some((27, Kind::Iban)));
+    }
+
+    #[test]
+    fn compact_iban() {
+        assert_eq!(match_iban(b"DE89370400440532013000."), Some(22));
+        assert_eq!(match_iban(b"DE88370400440532013000"), None);
+    }
+
+    #[test]
+    fn fakes_keep_checksums() {
+        let text = "Pay DE89 3704 0044 0532 0130 00 with 4111 1111 1111 1111.";
+        let found = Scrubber::new(Some(1)).scrub(text);
+        assert_eq!(found.len(), 2);
+        let (range, iban) = &found[0];
+        assert_eq!(&text[range.clone()], "DE89 3704 0044 0532 0130 00");
+        assert!(iban.starts_with("DE") && iban != "DE89 3704 0044 0532 0130 00");
+        assert_eq!(iban_remainder(iban.as_bytes(), true), 1);
+        let (range, card) = &found[1];
+        assert_eq!(&text[range.clone()], "4111 1111 1111 1111");
+        assert_eq!(luhn_sum(card.as_bytes()) % 10, 0);
+    }
+}
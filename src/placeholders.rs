@@ -0,0 +1,159 @@
+use std::ops::Range;
+
+/// The conversions of `printf`-like placeholders, like the `s` of `%s`.
+const CONVERSIONS: &str = "sdifFeEgGxXocrau";
+
+/// The parts of the source of a string literal, without its quotes, that must be kept: escape
+/// sequences and placeholders of format strings, like `{}`, `{name}`, `%s`, `%5.2f`, `#{expr}`
+/// and `\(`…`\)`.
+///
+/// The ranges are sorted and disjoint. Braces only count as a placeholder if there is no
+/// whitespace in them, so that prose in braces is still replaced.
+pub(crate) fn spans(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let end = match bytes[i] {
+            // An escaped backslash followed by a parenthesis opens an interpolation up to the
+            // matching `\\)`.
+            b'\\' if text[i..].starts_with("\\\\(") => {
+                text[i + 3..].find("\\\\)").map(|end| i + 3 + end + 3)
+            }
+            b'\\' => escape(text, i),
+            b'#' if bytes.get(i + 1) == Some(&b'{') => closing_brace(text, i + 1),
+            b'{' => {
+                closing_brace(text, i).filter(|&end| !text[i..end].contains(char::is_whitespace))
+            }
+            b'%' => conversion(text, i + 1),
+            _ => None,
+        };
+        match end {
+            Some(end) => {
+                spans.push(i..end);
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    spans
+}
+
+/// The escape sequences of the source of a string literal without its quotes, sorted.
+pub(crate) fn escapes(text: &str) -> Vec<Range<usize>> {
+    let mut escapes = Vec::new();
+    let mut i = 0;
+    while let Some(start) = text[i..].find('\\').map(|start| i + start) {
+        let end = escape(text, start).unwrap_or(text.len());
+        escapes.push(start..end);
+        i = end;
+    }
+    escapes
+}
+
+/// The end of the escape sequence that starts with the backslash at `start`.
+fn escape(text: &str, start: usize) -> Option<usize> {
+    if text[start..].starts_with("\\u{") {
+        return text[start..].find('}').map(|end| start + end + 1);
+    }
+    text[start + 1..]
+        .chars()
+        .next()
+        .map(|c| start + 1 + c.len_utf8())
+}
+
+/// The end of the braces that open at `start`, with nested braces in them.
+fn closing_brace(text: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start + i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The end of a `printf`-like placeholder after its `%`: a `%`, or an optional `(name)`,
+/// flags, width and precision followed by a conversion.
+fn conversion(text: &str, start: usize) -> Option<usize> {
+    let rest = &text[start..];
+    if rest.starts_with('%') {
+        return Some(start + 1);
+    }
+    let mut i = 0;
+    if let Some(named) = rest.strip_prefix('(') {
+        let name = named.find(')')?;
+        if !named[..name]
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_')
+        {
+            return None;
+        }
+        i = name + 2;
+    }
+    i += rest[i..]
+        .find(|c: char| !matches!(c, '-' | '+' | '#' | '0'..='9' | '.'))
+        .unwrap_or(rest.len() - i);
+    let c = rest[i..].chars().next()?;
+    CONVERSIONS.contains(c).then_some(start + i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The kept parts of the source of a string literal.
+    fn kept(text: &str) -> Vec<&str> {
+        spans(text).into_iter().map(|span| &text[span]).collect()
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            kept(r"Hello {name}, you have %d new %(kind)s and {} more"),
+            ["{name}", "%d", "%(kind)s", "{}"]
+        );
+        assert_eq!(
+            kept("%5.2f%% of #{total} done"),
+            ["%5.2f", "%%", "#{total}"]
+        );
+        assert_eq!(kept(r"Sum: \\(a + b\\) ok"), [r"\\(a + b\\)"]);
+    }
+
+    #[test]
+    fn prose_stays() {
+        // Braces with spaces in them, words after a percent sign and unclosed braces are text.
+        assert!(kept("{in other words} or 50% more, {unclosed").is_empty());
+        assert!(kept("%(not a name)s").is_empty());
+    }
+
+    #[test]
+    fn kept_in_strings() {
+        let code = r#"#let message = "Hello {name}, there are %d new items\n""#;
+        let options = crate::MutilateOptions::new().seed(1).aggressive(true);
+        let output = crate::mutilate_str(code, &options).unwrap();
+        assert!(output.starts_with(r#"#let message = ""#));
+        for part in ["{name}, ", " %d ", r#"\n""#] {
+            assert!(output.contains(part), "{output} lost {part}");
+        }
+        assert!(!output.contains("Hello") && !output.contains("items"));
+    }
+
+    #[test]
+    fn escape_sequences() {
+        let text = r#"Tab\tquote\" and \u{1F600} end"#;
+        let escapes: Vec<&str> = escapes(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(escapes, [r"\t", r#"\""#, r"\u{1F600}"]);
+        assert_eq!(kept(text), escapes);
+    }
+}
@@ -0,0 +1,44 @@
+//! Python bindings, enabled by the `python` feature.
+
+use std::path::PathBuf;
+
+use pyo3::{
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+};
+
+use crate::{Error, MutilateOptions};
+
+/// Replace all words in a typst document with random garbage.
+#[pyfunction]
+#[pyo3(signature = (text, language = "en", wordlist = None, seed = None, aggressive = false))]
+fn mutilate(
+    text: &str,
+    language: &str,
+    wordlist: Option<PathBuf>,
+    seed: Option<u64>,
+    aggressive: bool,
+) -> PyResult<String> {
+    let language = crate::parse_language(language)
+        .ok_or_else(|| PyValueError::new_err(format!("language `{language}` is not supported")))?;
+
+    let mut options = MutilateOptions::new()
+        .language(language)
+        .aggressive(aggressive);
+    if let Some(wordlist) = wordlist {
+        options = options.wordlist(wordlist);
+    }
+    if let Some(seed) = seed {
+        options = options.seed(seed);
+    }
+
+    crate::mutilate_str(text, &options).map_err(|err| match err {
+        Error::Io(err) => PyOSError::new_err(err.to_string()),
+        Error::Syntax(_) | Error::Unsupported(_) => PyValueError::new_err(err.to_string()),
+    })
+}
+
+#[pymodule]
+fn typst_mutilate(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(mutilate, module)?)
+}
@@ -0,0 +1,84 @@
+use std::ops::Range;
+
+use typst_syntax::{ast, LinkedNode, SyntaxKind};
+
+use crate::{Edit, Quotes};
+
+/// Keep only the edits inside or outside the quotations of a typst document.
+///
+/// With [`Quotes::Keep`], edits that touch a quotation are dropped, with [`Quotes::Only`] all
+/// edits that aren't entirely inside one.
+pub(crate) fn filter(root: &LinkedNode, quotes: Quotes, mut edits: Vec<Edit>) -> Vec<Edit> {
+    let mut quoted = Vec::new();
+    collect(root, &mut quoted);
+    edits.retain(|edit| match quotes {
+        Quotes::Keep => !quoted
+            .iter()
+            .any(|quote| quote.start < edit.range.end && edit.range.start < quote.end),
+        Quotes::Only => quoted
+            .iter()
+            .any(|quote| quote.start <= edit.range.start && edit.range.end <= quote.end),
+    });
+    edits
+}
+
+/// Collect the quotations in a node and its descendants: the positional arguments of `quote`
+/// calls, which include block quotes, and the text between double quotes in markup.
+fn collect(node: &LinkedNode, quoted: &mut Vec<Range<usize>>) {
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        if matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "quote") {
+            let args = node
+                .children()
+                .find(|child| child.kind() == SyntaxKind::Args);
+            for arg in args.iter().flat_map(LinkedNode::children) {
+                if arg.cast::<ast::Expr>().is_some() {
+                    quoted.push(arg.range());
+                }
+            }
+        }
+    }
+    if node.kind() == SyntaxKind::Markup {
+        quotations(node, quoted);
+    }
+    for child in node.children() {
+        collect(&child, quoted);
+    }
+}
+
+/// Collect the text between double quotes among the children of markup, straight ones that
+/// become smart quotes and typographic ones like `“`, `„` and `«`.
+///
+/// A quotation that isn't closed in the same markup is ignored.
+fn quotations(markup: &LinkedNode, quoted: &mut Vec<Range<usize>>) {
+    let mut open: Option<usize> = None;
+    for child in markup.children() {
+        let mut marks = Vec::new();
+        match child.kind() {
+            SyntaxKind::SmartQuote if child.text() == "\"" => {
+                marks.push((child.offset(), '"'));
+            }
+            SyntaxKind::Text => {
+                for (i, c) in child.text().char_indices() {
+                    if matches!(c, '“' | '”' | '„' | '«' | '»') {
+                        marks.push((child.offset() + i, c));
+                    }
+                }
+            }
+            _ => {}
+        }
+        for (start, c) in marks {
+            // Straight quotes and `“`, which closes German quotations but opens English ones,
+            // close an open quotation and open one otherwise.
+            let (opens, closes) = match c {
+                '„' | '«' => (true, false),
+                '”' | '»' => (false, true),
+                _ => (true, true),
+            };
+            match open.take() {
+                Some(from) if closes => quoted.push(from..start),
+                _ if opens => open = Some(start + c.len_utf8()),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::ops::Range;
+
+use typst_syntax::{LinkedNode, SyntaxKind};
+
+/// The text of comments that mark regions to redact.
+const MARKER: &str = "REDACT";
+
+/// Regions of a typst document that the author marked to be replaced in any case.
+#[derive(Debug, Default)]
+pub(crate) struct Annotations {
+    /// The marked byte ranges, sorted.
+    pub(crate) regions: Vec<Range<usize>>,
+    /// The marker comments themselves, which are kept.
+    pub(crate) markers: Vec<Range<usize>>,
+}
+
+/// Find the `// REDACT` and `/* REDACT */ [...]` annotations in a document.
+///
+/// A line comment marks the line it ends, or the next line if it is on a line of its own.
+/// A block comment marks the brackets that follow it.
+pub(crate) fn annotations(node: &LinkedNode, code: &str) -> Annotations {
+    let mut annotations = Annotations::default();
+    find(node, code, &mut annotations);
+    annotations.regions.sort_by_key(|region| region.start);
+
+    // Merge overlapping regions, like two annotations of the same line.
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for region in annotations.regions.drain(..) {
+        match merged.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => merged.push(region),
+        }
+    }
+    annotations.regions = merged;
+    annotations
+}
+
+fn find(node: &LinkedNode, code: &str, annotations: &mut Annotations) {
+    let text = node.text();
+    let range = node.range();
+    match node.kind() {
+        SyntaxKind::LineComment if text[2..].trim() == MARKER => {
+            let line_start = code[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            let region = if code[line_start..range.start].trim().is_empty() {
+                let next_start = (range.end + 1).min(code.len());
+                let next_end = code[next_start..]
+                    .find('\n')
+                    .map_or(code.len(), |i| next_start + i);
+                next_start..next_end
+            } else {
+                line_start..range.start
+            };
+            annotations.regions.push(region);
+            annotations.markers.push(range);
+        }
+        SyntaxKind::BlockComment
+            if text
+                .strip_prefix("/*")
+                .and_then(|inner| inner.strip_suffix("*/"))
+                .is_some_and(|inner| inner.trim() == MARKER) =>
+        {
+            if let Some(region) = bracketed(code, range.end) {
+                annotations.regions.push(region);
+            }
+            annotations.markers.push(range);
+        }
+        _ => {
+            for child in node.children() {
+                find(&child, code, annotations);
+            }
+        }
+    }
+}
+
+/// The range inside the brackets that follow `start` after whitespace, if any.
+fn bracketed(code: &str, start: usize) -> Option<Range<usize>> {
+    let open = start + code[start..].len() - code[start..].trim_start().len();
+    if !code[open..].starts_with('[') {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in code[open..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + 1..open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Range,
+};
+
+use typst_syntax::{LinkedNode, SyntaxKind, SyntaxNode};
+
+use crate::{Edit, Mode};
+
+/// Shrink a typst document while `test` keeps accepting it, like a bug reproducer that still
+/// crashes the compiler.
+///
+/// Subtrees are removed, replaced by one of their children and text is shortened, larger parts
+/// first, until no such change is accepted anymore. Unless the document already has syntax
+/// errors, only changes that keep it free of them are tested. `test` is assumed to accept
+/// `code` itself.
+pub fn reduce(code: &str, mode: Mode, mut test: impl FnMut(&str) -> bool) -> String {
+    let mut code = code.to_owned();
+    let strict = !parse(&code, mode).erroneous();
+    // The hashes of the candidates that were tested already, which often come up again.
+    let mut tested = HashSet::new();
+    tested.insert(hash(&code));
+    loop {
+        let mut progress = false;
+        let mut candidates = candidates(&code, mode);
+        let mut i = 0;
+        while let Some(candidate) = candidates.get(i) {
+            i += 1;
+            let reduced = crate::apply_edits(&code, std::slice::from_ref(candidate));
+            if !tested.insert(hash(&reduced)) || (strict && parse(&reduced, mode).erroneous()) {
+                continue;
+            }
+            if test(&reduced) {
+                log::debug!(
+                    "removed {} bytes at {}, {} bytes left",
+                    code.len() - reduced.len(),
+                    candidate.range.start,
+                    reduced.len()
+                );
+                code = reduced;
+                progress = true;
+                // Go on at the same position in the candidates of the smaller document, as the
+                // ones before it were mostly tried already; the next pass tries them again.
+                candidates = self::candidates(&code, mode);
+                i -= 1;
+            }
+        }
+        if !progress {
+            return code;
+        }
+    }
+}
+
+/// The changes that make a document smaller, largest first.
+fn candidates(code: &str, mode: Mode) -> Vec<Edit> {
+    let syntax = parse(code, mode);
+    let mut edits = Vec::new();
+    collect(&LinkedNode::new(&syntax), code, &mut edits);
+    edits.sort_by_key(|edit| {
+        std::cmp::Reverse(edit.range.len().saturating_sub(edit.replacement.len()))
+    });
+    edits.retain(|edit| edit.range.len() > edit.replacement.len());
+    edits
+}
+
+fn collect(node: &LinkedNode, code: &str, edits: &mut Vec<Edit>) {
+    let children: Vec<LinkedNode> = node.children().collect();
+    for (i, child) in children.iter().enumerate() {
+        if matches!(child.kind(), SyntaxKind::Space | SyntaxKind::Parbreak) {
+            // Whitespace is removed or collapsed to a single space or line break.
+            let collapsed = if child.text().contains('\n') {
+                "\n"
+            } else {
+                " "
+            };
+            edits.push(remove(child.range()));
+            edits.push(Edit {
+                range: child.range(),
+                replacement: collapsed.into(),
+            });
+            continue;
+        }
+        if child.kind().is_trivia() || child.kind().is_grouping() {
+            continue;
+        }
+        let range = child.range();
+        edits.push(remove(range.clone()));
+        // Arguments and items go with their comma.
+        if let Some(comma) = children[i + 1..]
+            .iter()
+            .find(|next| !next.kind().is_trivia())
+            .filter(|next| next.kind() == SyntaxKind::Comma)
+        {
+            let end = children[i + 1..]
+                .iter()
+                .skip_while(|next| next.offset() <= comma.offset())
+                .find(|next| next.kind() != SyntaxKind::Space)
+                .map_or(comma.range().end, |next| next.offset());
+            edits.push(remove(range.start..end));
+        }
+        hoist(child, code, edits);
+        if child.kind() == SyntaxKind::Text {
+            shorten(child, edits);
+        }
+        collect(child, code, edits);
+    }
+}
+
+/// Replace a node with one of its children, like `*text*` with `text`.
+fn hoist(node: &LinkedNode, code: &str, edits: &mut Vec<Edit>) {
+    for child in node.children() {
+        if child.kind().is_trivia() || child.kind().is_grouping() || child.len() == node.len() {
+            continue;
+        }
+        if matches!(
+            child.kind(),
+            SyntaxKind::Markup
+                | SyntaxKind::Code
+                | SyntaxKind::ContentBlock
+                | SyntaxKind::CodeBlock
+                | SyntaxKind::Math
+        ) || descends_to_expression(node.kind())
+        {
+            edits.push(Edit {
+                range: node.range(),
+                replacement: code[child.range()].into(),
+            });
+        }
+    }
+}
+
+/// Whether a node is an expression whose parts are expressions that can replace it.
+fn descends_to_expression(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Binary
+            | SyntaxKind::Unary
+            | SyntaxKind::Parenthesized
+            | SyntaxKind::Conditional
+            | SyntaxKind::FieldAccess
+            | SyntaxKind::FuncCall
+    )
+}
+
+/// Shorten text by removing one of its words, or to its first word or character.
+fn shorten(node: &LinkedNode, edits: &mut Vec<Edit>) {
+    let text = node.text();
+    let start = node.offset();
+    let mut words = text.split_inclusive(char::is_whitespace).peekable();
+    let mut offset = 0;
+    while let Some(word) = words.next() {
+        if words.peek().is_some() || offset > 0 {
+            edits.push(remove(start + offset..start + offset + word.len()));
+        }
+        offset += word.len();
+    }
+    let first = text.split_whitespace().next().unwrap_or_default();
+    let initial = first
+        .char_indices()
+        .nth(1)
+        .map_or(first, |(end, _)| &first[..end]);
+    for shorter in [first, initial] {
+        if !shorter.is_empty() && shorter.len() < text.len() {
+            edits.push(Edit {
+                range: node.range(),
+                replacement: shorter.into(),
+            });
+        }
+    }
+}
+
+fn remove(range: Range<usize>) -> Edit {
+    Edit {
+        range,
+        replacement: "".into(),
+    }
+}
+
+fn parse(code: &str, mode: Mode) -> SyntaxNode {
+    match mode {
+        Mode::Markup => typst_syntax::parse(code),
+        Mode::Code => typst_syntax::parse_code(code),
+        Mode::Math => typst_syntax::parse_math(code),
+    }
+}
+
+fn hash(code: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    code.hash(&mut hasher);
+    hasher.finish()
+}
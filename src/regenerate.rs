@@ -0,0 +1,132 @@
+use std::{io, ops::Range};
+
+use ecow::EcoString;
+use rand::{seq::SliceRandom, Rng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use typst_syntax::{LinkedNode, SyntaxKind};
+
+use crate::{scan, shrink, Segmentation};
+
+/// The range of the number of words of generated sentences.
+const SENTENCE_LENGTHS: Range<usize> = 4..16;
+
+/// Replaces whole paragraphs of typst documents with generated text.
+pub(crate) struct Regenerator {
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Regenerator {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x51c8_0e3a_f6b4_29d7);
+        Self { rng }
+    }
+
+    /// Generate a paragraph from the original `text`, with as many words as it, about as long,
+    /// and as many lines.
+    ///
+    /// The words are replaced by `replace` in random order and grouped into sentences of
+    /// random lengths, so that neither their order nor the sentence boundaries of the original
+    /// remain.
+    pub(crate) fn generate(
+        &mut self,
+        text: &str,
+        segmentation: Segmentation,
+        mut replace: impl FnMut(&str) -> io::Result<EcoString>,
+    ) -> io::Result<String> {
+        let mut words: Vec<&str> = scan::Words::new(text, segmentation)
+            .map(|(_, word)| word)
+            .collect();
+        words.shuffle(&mut self.rng);
+        let lines = text.matches('\n').count() + 1;
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+
+        let mut generated = String::with_capacity(text.len());
+        let mut sentence = 0;
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                // Spread the words evenly over the lines.
+                let line_break = i * lines / words.len() != (i - 1) * lines / words.len();
+                generated.push_str(if line_break { newline } else { " " });
+            }
+            let replacement = replace(word)?;
+            let numeric = scan::is_numeric(&replacement);
+            if sentence == 0 {
+                sentence = self.rng.gen_range(SENTENCE_LENGTHS);
+                let mut chars = replacement.chars();
+                generated.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+                generated.push_str(chars.as_str());
+            } else {
+                generated.push_str(&replacement);
+            }
+            sentence -= 1;
+            // A number with a period at the start of a line would start an enumeration.
+            if (sentence == 0 || i + 1 == words.len()) && !numeric {
+                generated.push('.');
+                sentence = 0;
+            }
+        }
+        Ok(generated)
+    }
+}
+
+/// The paragraphs of running text among the children of markup, from their first to their
+/// last node that isn't a space.
+///
+/// Paragraphs with headings, lists, figures, labels, definitions and the like are left to the
+/// replacement of single words, as are those without words or sentence punctuation, like the
+/// fragments in table cells, and the markup of headings, list items and the like, which isn't
+/// made of paragraphs.
+pub(crate) fn paragraphs(markup: &LinkedNode, segmentation: Segmentation) -> Vec<Range<usize>> {
+    if markup
+        .parent()
+        .is_some_and(|parent| parent.kind() != SyntaxKind::ContentBlock)
+    {
+        return Vec::new();
+    }
+    let children: Vec<LinkedNode> = markup.children().collect();
+    children
+        .split(|child| child.kind() == SyntaxKind::Parbreak)
+        .filter(|nodes| nodes.iter().all(shrink::prose))
+        .filter_map(|nodes| {
+            let mut content = nodes.iter().filter(|node| node.kind() != SyntaxKind::Space);
+            let first = content.next()?;
+            let last = content.next_back().unwrap_or(first);
+            let texts: Vec<EcoString> = nodes
+                .iter()
+                .map(|node| node.get().clone().into_text())
+                .collect();
+            let words = texts
+                .iter()
+                .any(|text| scan::Words::new(text, segmentation).next().is_some());
+            let punctuated = texts.iter().any(|text| text.contains(['.', '!', '?', '…']));
+            (words && punctuated).then(|| first.offset()..last.offset() + last.len())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of the paragraphs of every markup in `code`.
+    fn paragraph_texts(code: &str) -> Vec<&str> {
+        fn collect(node: &LinkedNode, ranges: &mut Vec<Range<usize>>) {
+            if node.kind() == SyntaxKind::Markup {
+                ranges.extend(paragraphs(node, Segmentation::Unicode));
+            }
+            for child in node.children() {
+                collect(&child, ranges);
+            }
+        }
+        let root = typst_syntax::parse(code);
+        let mut ranges = Vec::new();
+        collect(&LinkedNode::new(&root), &mut ranges);
+        ranges.into_iter().map(|range| &code[range]).collect()
+    }
+
+    #[test]
+    fn only_sentences() {
+        let code = "#table([a], [b c], [Done.])\n\nSome text\n\nThe fox jumps. It runs!\n";
+        assert_eq!(paragraph_texts(code), ["The fox jumps. It runs!", "Done."]);
+    }
+}
@@ -0,0 +1,34 @@
+use hypher::Lang;
+use typst_syntax::{ast, SyntaxNode};
+
+/// The language set by a `set text(lang: ..)` rule.
+pub(crate) fn set_rule_language(node: &SyntaxNode) -> Option<Lang> {
+    let rule: ast::SetRule = node.cast()?;
+    if !is_text(rule.target()) {
+        return None;
+    }
+    lang_arg(rule.args())
+}
+
+/// The language of the content passed to a `text(lang: ..)[..]` call.
+pub(crate) fn call_language(node: &SyntaxNode) -> Option<Lang> {
+    let call: ast::FuncCall = node.cast()?;
+    if !is_text(call.callee()) {
+        return None;
+    }
+    lang_arg(call.args())
+}
+
+fn is_text(expr: ast::Expr) -> bool {
+    matches!(expr, ast::Expr::Ident(ident) if ident.as_str() == "text")
+}
+
+fn lang_arg(args: ast::Args) -> Option<Lang> {
+    args.items().find_map(|arg| match arg {
+        ast::Arg::Named(named) if named.name().as_str() == "lang" => match named.expr() {
+            ast::Expr::Str(lang) => crate::parse_language(&lang.get()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
@@ -0,0 +1,88 @@
+//! Downloading wordlists, enabled by the `remote` feature.
+
+use std::{
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::cache::hex;
+
+/// A downloaded wordlist in the cache.
+#[derive(Debug)]
+pub struct CachedWordlist {
+    /// Where the wordlist is stored.
+    pub path: PathBuf,
+    /// Why refreshing the wordlist failed, if the previously cached copy is used instead.
+    pub download_error: Option<io::Error>,
+}
+
+/// Download a wordlist, or reuse the copy downloaded by an earlier call.
+///
+/// Wordlists are cached in `$XDG_CACHE_HOME/typst-mutilate/wordlists`. With `refresh`, the
+/// wordlist is downloaded again, falling back to the cached copy if that fails. If `sha256`
+/// is given, the wordlist must have this hex-encoded SHA-256 checksum.
+pub fn fetch_wordlist(
+    url: &str,
+    sha256: Option<&str>,
+    refresh: bool,
+) -> io::Result<CachedWordlist> {
+    let directory = crate::default_cache_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory found"))?
+        .join("wordlists");
+    let path = directory.join(format!("{}.txt", hex(&Sha256::digest(url))));
+
+    let cached = match std::fs::read(&path) {
+        Ok(bytes) => verify(&bytes, sha256).is_ok(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+        Err(err) => return Err(err),
+    };
+    if cached && !refresh {
+        return Ok(CachedWordlist {
+            path,
+            download_error: None,
+        });
+    }
+
+    let bytes = match download(url).and_then(|bytes| verify(&bytes, sha256).map(|()| bytes)) {
+        Ok(bytes) => bytes,
+        Err(err) if cached => {
+            return Ok(CachedWordlist {
+                path,
+                download_error: Some(err),
+            })
+        }
+        Err(err) => return Err(err),
+    };
+
+    std::fs::create_dir_all(&directory)?;
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, bytes)?;
+    std::fs::rename(&temporary, &path)?;
+    Ok(CachedWordlist {
+        path,
+        download_error: None,
+    })
+}
+
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn verify(bytes: &[u8], sha256: Option<&str>) -> io::Result<()> {
+    let Some(expected) = sha256 else {
+        return Ok(());
+    };
+    let actual = hex(&Sha256::digest(bytes));
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("wordlist checksum mismatch: expected {expected}, found {actual}"),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Write},
+};
+
+use ecow::{EcoString, EcoVec};
+use hypher::Lang;
+use rand::seq::SliceRandom;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::{
+    wordlist::{IndexSettings, WordIndex},
+    MutilateOptions, NumberMode, Patterns, Strategy,
+};
+
+/// Chooses the replacement for a single word.
+///
+/// Implement this to plug domain-specific generators into the mutilation process.
+pub trait Replacer {
+    /// Write a replacement for `word` to `out`.
+    fn replace(&mut self, word: &str, info: &WordInfo, out: &mut dyn Write) -> io::Result<()>;
+
+    /// The strategy that chose the last replacement, if the replacer uses strategies.
+    ///
+    /// This is only used for audit logs.
+    fn last_strategy(&self) -> Option<Strategy> {
+        None
+    }
+
+    /// Forget the words replaced so far and adopt the seed and replacement options of
+    /// `options`, to replace the words of an unrelated document as if the replacer were new.
+    ///
+    /// Replacers that keep nothing between words don't need to do anything.
+    fn reset(&mut self, _options: &MutilateOptions) {}
+}
+
+/// Properties of a word that is about to be replaced.
+#[derive(Debug, Clone)]
+pub struct WordInfo<'a> {
+    /// The number of characters in the word.
+    pub length: usize,
+    /// The number of characters in each syllable of the word.
+    pub hyphenation: &'a [u8],
+    /// Whether the word is a number, made of numeric characters and the separators between
+    /// them, like `1,000`.
+    pub numeric: bool,
+    /// The language the word was hyphenated with.
+    pub language: Lang,
+}
+
+/// Compute the number of characters in each syllable of `word`.
+///
+/// Custom patterns take precedence over the built-in patterns of the language.
+pub(crate) fn hyphenation(word: &str, language: Lang, patterns: Option<&Patterns>) -> EcoVec<u8> {
+    if let Some(patterns) = patterns {
+        return patterns.hyphenate(word);
+    }
+    hypher::hyphenate(word, language)
+        .map(|syllable| syllable.chars().count().try_into().unwrap_or(u8::MAX))
+        .collect()
+}
+
+/// The minimum number of words that have to be available in a list in order to choose an item.
+const MINIMUM_WORD_COUNT: usize = 16;
+
+/// The number of the last words chosen from a wordlist that aren't chosen again, at most half
+/// of the words that are available.
+const RECENT_WORDS: usize = 8;
+
+/// How often a word is drawn from a wordlist before settling for one that was chosen recently
+/// or for another word.
+const ATTEMPTS: usize = 8;
+
+const CHARSET_TEXT: &[char] = &[
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L',
+    'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+const CHARSET_DIGITS: &[char] = &['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+
+/// The built-in replacer, which picks words from a wordlist with a matching hyphenation
+/// pattern or length and falls back to random characters.
+pub struct WordlistReplacer {
+    rng: Xoshiro256PlusPlus,
+    strategies: Vec<Strategy>,
+    numbers: NumberMode,
+    /// The words of the wordlists that aren't assigned to a language.
+    shared: WordIndex,
+    /// The words of the wordlists assigned to a language, and of bundled wordlists.
+    languages: HashMap<Lang, WordIndex>,
+    settings: IndexSettings,
+    last_strategy: Strategy,
+    /// The last words that were chosen from a wordlist, the most recent one last.
+    recent: VecDeque<EcoString>,
+    /// The word that every word chosen from a wordlist was first chosen for, so that small
+    /// buckets don't replace many different words with the same one.
+    sources: HashMap<EcoString, EcoString>,
+}
+
+impl WordlistReplacer {
+    /// Build the replacer, loading the wordlists.
+    pub fn new(options: &MutilateOptions) -> io::Result<Self> {
+        let rng = crate::Context::substream(options.seed, 0);
+
+        let settings = IndexSettings::new(options);
+        let mut shared = WordIndex::new(options.language, settings.clone());
+        let mut languages: HashMap<Lang, WordIndex> = HashMap::new();
+        for (language, wordlist) in &options.wordlists {
+            match language {
+                Some(language) => languages
+                    .entry(*language)
+                    .or_insert_with(|| WordIndex::new(*language, settings.clone()))
+                    .load(wordlist)?,
+                None => shared.load(wordlist)?,
+            }
+        }
+
+        Ok(Self {
+            rng,
+            strategies: options.strategies.clone(),
+            numbers: options.numbers,
+            shared,
+            languages,
+            settings,
+            last_strategy: Strategy::Random,
+            recent: VecDeque::with_capacity(RECENT_WORDS + 1),
+            sources: HashMap::new(),
+        })
+    }
+
+    /// Index the bundled wordlist of a language if it is needed.
+    ///
+    /// Bundled wordlists are only used for languages without a wordlist of their own when
+    /// there are no shared wordlists.
+    fn load_bundled(&mut self, language: Lang) {
+        if self.languages.contains_key(&language) || !self.shared.is_empty() {
+            return;
+        }
+        let mut index = WordIndex::new(language, self.settings.clone());
+        let words = crate::bundled_wordlist(language).unwrap_or_default();
+        log::debug!(
+            "using the bundled {language:?} wordlist of {} words",
+            words.len()
+        );
+        index.load_words(words);
+        self.languages.insert(language, index);
+    }
+}
+
+impl Replacer for WordlistReplacer {
+    fn replace(&mut self, word: &str, info: &WordInfo, out: &mut dyn Write) -> io::Result<()> {
+        self.last_strategy = Strategy::Random;
+        if info.numeric {
+            let mut significant = false;
+            for c in word.chars() {
+                if !c.is_numeric() {
+                    write!(out, "{c}")?;
+                    continue;
+                }
+                let digit = match self.numbers {
+                    NumberMode::Magnitude if !significant && c == '0' => '0',
+                    NumberMode::Magnitude if !significant => {
+                        significant = true;
+                        *CHARSET_DIGITS[1..].choose(&mut self.rng).unwrap()
+                    }
+                    _ => *CHARSET_DIGITS.choose(&mut self.rng).unwrap(),
+                };
+                write!(out, "{digit}")?;
+            }
+            return Ok(());
+        }
+
+        self.load_bundled(info.language);
+        let index = match self.languages.get_mut(&info.language) {
+            Some(index) => index,
+            None => &mut self.shared,
+        };
+        let count = index.count(word);
+        for strategy in &self.strategies {
+            let words = match strategy {
+                Strategy::Hyphenation => index.hyphenation_bucket(info.hyphenation),
+                Strategy::Length => index.length_bucket(info.length),
+                Strategy::Random => break,
+                // Sentences are replaced before their words.
+                Strategy::Sentences => continue,
+            };
+            if let Some(words) = words {
+                if words.len() >= MINIMUM_WORD_COUNT {
+                    // Draw again if the word was just chosen or already replaces another word,
+                    // keeping the best word so far, of which repetitions are the worst.
+                    let window = RECENT_WORDS.min(words.len() / 2);
+                    let mut chosen = None;
+                    let mut best = u8::MAX;
+                    for _ in 0..ATTEMPTS {
+                        let Some(candidate) = words.choose(&mut self.rng, count) else {
+                            break;
+                        };
+                        let repeated = self
+                            .recent
+                            .iter()
+                            .rev()
+                            .take(window)
+                            .any(|recent| recent == candidate);
+                        let shared = self
+                            .sources
+                            .get(candidate)
+                            .is_some_and(|source| source != word);
+                        let flaws = 2 * u8::from(repeated) + u8::from(shared);
+                        if flaws < best {
+                            chosen = Some(candidate);
+                            best = flaws;
+                        }
+                        if flaws == 0 {
+                            break;
+                        }
+                    }
+                    if let Some(choice) = chosen {
+                        self.last_strategy = *strategy;
+                        self.recent.push_back(choice.clone());
+                        if self.recent.len() > RECENT_WORDS {
+                            self.recent.pop_front();
+                        }
+                        self.sources
+                            .entry(choice.clone())
+                            .or_insert_with(|| word.into());
+                        return write!(out, "{choice}");
+                    }
+                }
+            }
+        }
+
+        for _ in 0..info.length {
+            write!(out, "{}", CHARSET_TEXT.choose(&mut self.rng).unwrap())?;
+        }
+
+        Ok(())
+    }
+
+    fn last_strategy(&self) -> Option<Strategy> {
+        Some(self.last_strategy)
+    }
+
+    /// Keeps the loaded wordlists, which are the same for every document.
+    fn reset(&mut self, options: &MutilateOptions) {
+        self.rng = crate::Context::substream(options.seed, 0);
+        self.strategies = options.strategies.clone();
+        self.numbers = options.numbers;
+        self.last_strategy = Strategy::Random;
+        self.recent.clear();
+        self.sources.clear();
+    }
+}
@@ -0,0 +1,210 @@
+//! Interactive review of planned replacements, like `git add -p`.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use ecow::EcoString;
+use typst_mutilate::Edit;
+
+use crate::diff;
+
+/// How replacements are grouped for review.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// Every distinct replaced text, with all of its occurrences.
+    #[default]
+    Word,
+    /// Every line with replacements.
+    Hunk,
+}
+
+impl FromStr for Grouping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "word" => Ok(Self::Word),
+            "hunk" => Ok(Self::Hunk),
+            _ => Err(format!(
+                "unknown grouping `{s}`, expected one of `word`, `hunk`"
+            )),
+        }
+    }
+}
+
+/// The edits that were accepted.
+pub struct Reviewed {
+    pub edits: Vec<Edit>,
+    /// The ranges of the edits whose replacement was typed in.
+    pub manual: Vec<Range<usize>>,
+}
+
+const HELP: &str = "\
+y - accept the replacement
+n - keep the original
+e - type the replacement
+a - accept this and all later replacements
+d - keep the original of this and all later replacements
+q - quit, keeping the original of this and all later replacements
+? - show this help";
+
+/// What to do with a group.
+enum Answer {
+    Accept,
+    Reject,
+    Replace(EcoString),
+}
+
+/// Ask about every group of `edits` of `code`, reading the answers from `input`.
+pub fn review(
+    input: &mut impl BufRead,
+    file: &str,
+    code: &str,
+    edits: Vec<Edit>,
+    grouping: Grouping,
+    color: bool,
+) -> io::Result<Reviewed> {
+    let groups = groups(code, &edits, grouping);
+    let mut answers = Vec::with_capacity(groups.len());
+    // The answer to all remaining groups, after `a`, `d` or `q`.
+    let mut rest = None;
+    for (i, group) in groups.iter().enumerate() {
+        if let Some(accept) = rest {
+            answers.push(if accept {
+                Answer::Accept
+            } else {
+                Answer::Reject
+            });
+            continue;
+        }
+
+        let shown: Vec<Edit> = group.iter().take(3).map(|&i| edits[i].clone()).collect();
+        let mut err = io::stderr().lock();
+        writeln!(err)?;
+        diff::write(&mut err, file, code, &shown, color)?;
+        if group.len() > shown.len() {
+            writeln!(err, "... and {} more", group.len() - shown.len())?;
+        }
+        let original = match grouping {
+            Grouping::Word => &code[edits[group[0]].range.clone()],
+            Grouping::Hunk => {
+                &code[edits[group[0]].range.start..edits[*group.last().unwrap()].range.end]
+            }
+        };
+        let answer = loop {
+            write!(
+                err,
+                "({}/{}) Accept this replacement [y,n,e,a,d,q,?]? ",
+                i + 1,
+                groups.len()
+            )?;
+            err.flush()?;
+            let Some(line) = read_line(input)? else {
+                break None;
+            };
+            match line.trim() {
+                "y" => break Some(Answer::Accept),
+                "n" => break Some(Answer::Reject),
+                "a" => {
+                    rest = Some(true);
+                    break Some(Answer::Accept);
+                }
+                "d" | "q" => break None,
+                "e" => {
+                    write!(err, "Replacement for `{original}`: ")?;
+                    err.flush()?;
+                    match read_line(input)? {
+                        Some(text) if !text.is_empty() => break Some(Answer::Replace(text.into())),
+                        Some(_) => continue,
+                        None => break None,
+                    }
+                }
+                _ => writeln!(err, "{HELP}")?,
+            }
+        };
+        answers.push(answer.unwrap_or_else(|| {
+            rest = Some(false);
+            Answer::Reject
+        }));
+    }
+
+    let mut reviewed = Reviewed {
+        edits: Vec::new(),
+        manual: Vec::new(),
+    };
+    let mut edits: Vec<Option<Edit>> = edits.into_iter().map(Some).collect();
+    for (group, answer) in groups.iter().zip(answers) {
+        match answer {
+            Answer::Accept => reviewed
+                .edits
+                .extend(group.iter().filter_map(|&i| edits[i].take())),
+            Answer::Reject => {}
+            Answer::Replace(text) if grouping == Grouping::Word => {
+                for &i in group {
+                    let mut edit = edits[i].take().unwrap();
+                    edit.replacement = text.clone();
+                    reviewed.manual.push(edit.range.clone());
+                    reviewed.edits.push(edit);
+                }
+            }
+            Answer::Replace(text) => {
+                // The text replaces the line from the first to the last replacement.
+                let first = edits[group[0]].as_ref().unwrap().range.start;
+                let last = edits[*group.last().unwrap()].as_ref().unwrap().range.end;
+                reviewed.manual.push(first..last);
+                reviewed.edits.push(Edit {
+                    range: first..last,
+                    replacement: text,
+                });
+            }
+        }
+    }
+    reviewed.edits.sort_by_key(|edit| edit.range.start);
+    Ok(reviewed)
+}
+
+/// The indices of the edits in each group, in the order of their first edits.
+fn groups(code: &str, edits: &[Edit], grouping: Grouping) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    match grouping {
+        Grouping::Word => {
+            let mut index: HashMap<&str, usize> = HashMap::new();
+            for (i, edit) in edits.iter().enumerate() {
+                let group = *index.entry(&code[edit.range.clone()]).or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+                groups[group].push(i);
+            }
+        }
+        Grouping::Hunk => {
+            let (mut line, mut cursor) = (0, 0);
+            let mut last_line = None;
+            for (i, edit) in edits.iter().enumerate() {
+                line += code[cursor..edit.range.start].matches('\n').count();
+                cursor = edit.range.start;
+                if last_line != Some(line) {
+                    groups.push(Vec::new());
+                    last_line = Some(line);
+                }
+                groups.last_mut().unwrap().push(i);
+            }
+        }
+    }
+    groups
+}
+
+/// Read a line without its line break, or `None` at the end of the input.
+fn read_line(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let len = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
@@ -0,0 +1,125 @@
+use unicode_segmentation::{UnicodeSegmentation, UnicodeWordIndices};
+
+use crate::Segmentation;
+
+/// An iterator over the words of a text and their byte offsets.
+///
+/// Invisible characters like soft hyphens and joiners belong to a word only between its
+/// letters, so that the ones around it are kept.
+pub(crate) enum Words<'a> {
+    Unicode(UnicodeWordIndices<'a>),
+    Alphanumeric(Runs<'a>),
+}
+
+impl<'a> Words<'a> {
+    pub(crate) fn new(text: &'a str, segmentation: Segmentation) -> Self {
+        match segmentation {
+            Segmentation::Unicode => Self::Unicode(text.unicode_word_indices()),
+            Segmentation::Alphanumeric => Self::Alphanumeric(Runs { text, cursor: 0 }),
+        }
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Unicode(words) => loop {
+                let (start, word) = words.next()?;
+                let trimmed = word.trim_start_matches(is_invisible);
+                let start = start + word.len() - trimmed.len();
+                let trimmed = trimmed.trim_end_matches(is_invisible);
+                if !trimmed.is_empty() {
+                    return Some((start, trimmed));
+                }
+            },
+            Self::Alphanumeric(runs) => runs.next(),
+        }
+    }
+}
+
+/// An iterator over the maximal runs of alphanumeric characters of a text.
+///
+/// ASCII bytes are classified directly, so only non-ASCII text has to be decoded.
+pub(crate) struct Runs<'a> {
+    text: &'a str,
+    cursor: usize,
+}
+
+impl Runs<'_> {
+    /// Whether the character starting at `index` is alphanumeric, and its length in bytes.
+    fn classify(&self, index: usize) -> (bool, usize) {
+        let byte = self.text.as_bytes()[index];
+        if byte.is_ascii() {
+            return (byte.is_ascii_alphanumeric(), 1);
+        }
+        let c = self.text[index..].chars().next().unwrap();
+        (c.is_alphanumeric(), c.len_utf8())
+    }
+
+    /// Whether the character starting at `index` is invisible and, after any other invisible
+    /// characters, followed by an alphanumeric one, so that it continues a run.
+    fn joins(&self, index: usize) -> bool {
+        let rest = &self.text[index..];
+        rest.starts_with(is_invisible)
+            && rest
+                .trim_start_matches(is_invisible)
+                .starts_with(char::is_alphanumeric)
+    }
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.text.len();
+        let start = loop {
+            if self.cursor >= len {
+                return None;
+            }
+            let (alphanumeric, width) = self.classify(self.cursor);
+            if alphanumeric {
+                break self.cursor;
+            }
+            self.cursor += width;
+        };
+        while self.cursor < len {
+            let (alphanumeric, width) = self.classify(self.cursor);
+            if !alphanumeric && !self.joins(self.cursor) {
+                break;
+            }
+            self.cursor += width;
+        }
+        Some((start, &self.text[start..self.cursor]))
+    }
+}
+
+/// The number of characters in `word`, without decoding ASCII text.
+pub(crate) fn char_count(word: &str) -> usize {
+    if word.is_ascii() {
+        word.len()
+    } else {
+        word.chars().count()
+    }
+}
+
+/// Whether a word is a number: numeric characters and the separators between them, like
+/// `1,000`, without any letters.
+pub(crate) fn is_numeric(word: &str) -> bool {
+    word.chars().any(char::is_numeric) && !word.chars().any(char::is_alphabetic)
+}
+
+/// Whether a word mixes letters and numeric characters, like `IPv6` or `A4`.
+pub(crate) fn is_mixed(word: &str) -> bool {
+    word.chars().any(char::is_numeric) && word.chars().any(char::is_alphabetic)
+}
+
+/// Whether a character is invisible formatting that must be kept where it is: a soft hyphen,
+/// a zero-width space, joiner or non-joiner, a word joiner or a zero-width no-break space.
+pub(crate) fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{ad}' | '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}'
+    )
+}
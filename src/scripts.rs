@@ -0,0 +1,111 @@
+use std::{collections::HashMap, ops::Range};
+
+use rand::seq::SliceRandom;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use unicode_script::{Script, UnicodeScript};
+
+/// The ranges of letters that replacement letters of a script are drawn from, of which only
+/// the letters of the script are used.
+const LETTERS: &[(Script, Range<u32>)] = &[
+    (Script::Latin, 0x61..0x7b),
+    (Script::Greek, 0x3b1..0x3ca),
+    (Script::Cyrillic, 0x430..0x450),
+    (Script::Armenian, 0x561..0x587),
+    (Script::Georgian, 0x10d0..0x10f1),
+    (Script::Hebrew, 0x5d0..0x5eb),
+    (Script::Arabic, 0x628..0x63b),
+    (Script::Devanagari, 0x915..0x93a),
+    (Script::Bengali, 0x995..0x9ba),
+    (Script::Tamil, 0xb95..0xbba),
+    (Script::Thai, 0xe01..0xe2f),
+    (Script::Hiragana, 0x3041..0x3097),
+    (Script::Katakana, 0x30a1..0x30fb),
+    (Script::Han, 0x4e00..0x9fa0),
+    (Script::Hangul, 0xac00..0xd7a4),
+];
+
+/// Replaces the parts of words that mix scripts, like `αβtest`, with letters of the same
+/// scripts, so that the output needs the same fonts as the input.
+pub(crate) struct ScriptFiller {
+    rng: Xoshiro256PlusPlus,
+    /// The letters of every script that was needed so far.
+    letters: HashMap<Script, Vec<char>>,
+}
+
+impl ScriptFiller {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x7c3a_91e5_4d20_b86f);
+        Self {
+            rng,
+            letters: HashMap::new(),
+        }
+    }
+
+    /// Replace every letter of a run of a single script with a random letter of the script,
+    /// keeping its case and the marks, digits and other characters in between.
+    ///
+    /// Scripts without known letters draw from the letters of the run itself.
+    pub(crate) fn fill(&mut self, run: &str, script: Script) -> String {
+        let letters = self.letters.entry(script).or_insert_with(|| {
+            LETTERS
+                .iter()
+                .filter(|(other, _)| *other == script)
+                .flat_map(|(_, range)| range.clone())
+                .filter_map(char::from_u32)
+                .filter(|c| c.is_alphabetic() && c.script() == script)
+                .collect()
+        });
+        let own: Vec<char>;
+        let letters = if letters.is_empty() {
+            own = run.chars().filter(|c| c.is_alphabetic()).collect();
+            &own
+        } else {
+            &*letters
+        };
+
+        let mut filled = String::with_capacity(run.len());
+        for c in run.chars() {
+            if !c.is_alphabetic() || c.script() != script {
+                filled.push(c);
+                continue;
+            }
+            let letter = *letters.choose(&mut self.rng).unwrap_or(&c);
+            if c.is_uppercase() {
+                filled.extend(letter.to_uppercase());
+            } else {
+                filled.push(letter);
+            }
+        }
+        filled
+    }
+}
+
+/// The script of the first character of a word that belongs to a particular script.
+pub(crate) fn script(word: &str) -> Option<Script> {
+    word.chars()
+        .map(|c| c.script())
+        .find(|script| !matches!(script, Script::Common | Script::Inherited | Script::Unknown))
+}
+
+/// The runs of a word that are written in a single script, or `None` if the whole word is.
+///
+/// Characters that belong to no particular script, like digits and combining marks, belong to
+/// the run before them, or to the first run at the start of the word.
+pub(crate) fn runs(word: &str) -> Option<Vec<(Range<usize>, Script)>> {
+    let mut runs: Vec<(Range<usize>, Script)> = Vec::new();
+    for (i, c) in word.char_indices() {
+        let script = c.script();
+        if matches!(script, Script::Common | Script::Inherited | Script::Unknown) {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, last)) if *last == script => {}
+            Some((range, _)) => {
+                range.end = i;
+                runs.push((i..word.len(), script));
+            }
+            None => runs.push((0..word.len(), script)),
+        }
+    }
+    (runs.len() > 1).then_some(runs)
+}
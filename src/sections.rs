@@ -0,0 +1,161 @@
+use std::{ops::Range, str::FromStr};
+
+use regex::Regex;
+use typst_syntax::{ast, LinkedNode, SyntaxKind};
+
+use crate::Edit;
+
+/// A pattern that selects the sections of typst documents by the text of their headings.
+///
+/// A pattern in slashes, like `/^Eval/`, is a regular expression that has to match somewhere
+/// in the heading. Any other text has to be the whole heading, ignoring case and the
+/// whitespace around it.
+#[derive(Debug, Clone)]
+pub enum HeadingPattern {
+    /// The text of the heading.
+    Text(String),
+    /// A regular expression that matches somewhere in the heading.
+    Regex(Regex),
+}
+
+impl HeadingPattern {
+    /// Whether the text of a heading matches the pattern.
+    pub fn matches(&self, heading: &str) -> bool {
+        match self {
+            Self::Text(text) => heading.trim().to_lowercase() == text.trim().to_lowercase(),
+            Self::Regex(regex) => regex.is_match(heading),
+        }
+    }
+}
+
+impl FromStr for HeadingPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(pattern) => Regex::new(pattern)
+                .map(Self::Regex)
+                .map_err(|err| format!("invalid heading pattern `{s}`: {err}")),
+            None => Ok(Self::Text(s.into())),
+        }
+    }
+}
+
+/// Keep only the edits in the content of the sections of a typst document whose headings match
+/// one of the `included` patterns, if there are any, and drop those that touch the content of
+/// a section whose heading matches one of the `excluded` ones.
+///
+/// The content of a section is everything after its heading up to the next heading of the same
+/// or a lower depth, including its subsections, but not the heading itself.
+pub(crate) fn filter(
+    root: &LinkedNode,
+    included: &[HeadingPattern],
+    excluded: &[HeadingPattern],
+    mut edits: Vec<Edit>,
+) -> Vec<Edit> {
+    let mut sections = Vec::new();
+    collect(root, &mut sections);
+    let select = |patterns: &[HeadingPattern]| -> Vec<Range<usize>> {
+        sections
+            .iter()
+            .filter(|(heading, _)| patterns.iter().any(|pattern| pattern.matches(heading)))
+            .map(|(_, range)| range.clone())
+            .collect()
+    };
+    let (included, excluded) = (select(included), select(excluded));
+    edits.retain(|edit| {
+        let inside =
+            |range: &Range<usize>| range.start <= edit.range.start && edit.range.end <= range.end;
+        let touches =
+            |range: &Range<usize>| range.start < edit.range.end && edit.range.start < range.end;
+        (included.is_empty() || included.iter().any(inside)) && !excluded.iter().any(touches)
+    });
+    edits
+}
+
+/// Collect the text of the headings in a node and its descendants with the ranges of the
+/// content of their sections.
+fn collect(node: &LinkedNode, sections: &mut Vec<(String, Range<usize>)>) {
+    if node.kind() == SyntaxKind::Markup {
+        let children: Vec<LinkedNode> = node.children().collect();
+        let end = node.offset() + node.len();
+        for (i, child) in children.iter().enumerate() {
+            let Some(heading) = child.cast::<ast::Heading>() else {
+                continue;
+            };
+            let depth = heading.depth();
+            let next = children[i + 1..]
+                .iter()
+                .find(|other| {
+                    other
+                        .cast::<ast::Heading>()
+                        .is_some_and(|other| other.depth() <= depth)
+                })
+                .map_or(end, LinkedNode::offset);
+            let mut text = String::new();
+            heading_text(child, &mut text);
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            sections.push((text, child.offset() + child.len()..next));
+        }
+    }
+    for child in node.children() {
+        collect(&child, sections);
+    }
+}
+
+/// Append the text of a heading, without its marker and label.
+fn heading_text(node: &LinkedNode, text: &mut String) {
+    match node.kind() {
+        SyntaxKind::HeadingMarker | SyntaxKind::Label => {}
+        SyntaxKind::Space => text.push(' '),
+        _ if node.children().len() == 0 => text.push_str(node.text()),
+        _ => {
+            for child in node.children() {
+                heading_text(&child, text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mutilate_str, MutilateOptions};
+
+    const CODE: &str = "= Intro\nHello there.\n\n= Method <method>\nWe measure.\n\n== Setup\nA lab.\n\n= End\nBye now.\n";
+
+    fn mutilate(options: MutilateOptions) -> String {
+        mutilate_str(CODE, &options.seed(1)).unwrap()
+    }
+
+    #[test]
+    fn included_sections() {
+        let output = mutilate(MutilateOptions::new().section("method".parse().unwrap()));
+        assert!(output.starts_with("= Intro\nHello there.\n\n= Method <method>\n"));
+        assert!(output.ends_with("\n= End\nBye now.\n"));
+        // The subsection is part of the section, but its heading is replaced.
+        assert!(!output.contains("We measure.") && !output.contains("A lab."));
+        assert!(!output.contains("Setup"));
+    }
+
+    #[test]
+    fn excluded_sections() {
+        let output = mutilate(MutilateOptions::new().exclude_section("/^Meth/".parse().unwrap()));
+        // The heading of the section is replaced, but not its content.
+        assert!(output.contains(" <method>\nWe measure.\n\n== Setup\nA lab.\n\n"));
+        assert!(!output.contains("Method"));
+        assert!(!output.contains("Intro") && !output.contains("Hello"));
+        assert!(!output.contains("Bye"));
+    }
+
+    #[test]
+    fn patterns() {
+        let pattern: HeadingPattern = " Related WORK ".parse().unwrap();
+        assert!(pattern.matches("Related work"));
+        assert!(!pattern.matches("Related work and more"));
+        let pattern: HeadingPattern = "/^Eval/".parse().unwrap();
+        assert!(pattern.matches("Evaluation of it"));
+        assert!(!pattern.matches("An Evaluation"));
+        assert!("/(/".parse::<HeadingPattern>().is_err());
+    }
+}
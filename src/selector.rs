@@ -0,0 +1,301 @@
+use std::{ops::Range, str::FromStr};
+
+use ecow::EcoString;
+use typst_syntax::{
+    ast::{self, AstNode},
+    LinkedNode, SyntaxKind,
+};
+
+use crate::Edit;
+
+/// A selector of the elements of typst documents, in a small subset of typst's selector
+/// syntax: an element name, optionally with conditions on its fields like
+/// `heading.where(level: 1)`, and elements inside other elements like `figure caption`.
+///
+/// A name selects the calls of the function of that name, the markup that is sugar for them,
+/// like `= Heading` for `heading`, `*strong*` for `strong` and `- item` for `list`, and the
+/// values of the named arguments of that name, like the caption of `figure(caption: [..])`.
+/// The conditions compare the named arguments of calls and the level of markup headings and
+/// the language of raw text with integers, strings, booleans and identifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Selector {
+    /// The elements that are nested in each other, from the outermost to the selected one.
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Step {
+    name: EcoString,
+    /// The fields and the values they must have, which strings are unquoted in.
+    conditions: Vec<(EcoString, EcoString)>,
+}
+
+impl Selector {
+    /// Whether a node is an element that the selector matches.
+    pub fn matches(&self, node: &LinkedNode) -> bool {
+        let Some((last, outer)) = self.steps.split_last() else {
+            return false;
+        };
+        if !last.matches(node) {
+            return false;
+        }
+        let mut ancestor = node.parent().cloned();
+        for step in outer.iter().rev() {
+            loop {
+                let Some(node) = ancestor.take() else {
+                    return false;
+                };
+                ancestor = node.parent().cloned();
+                if step.matches(&node) {
+                    break;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl Step {
+    fn matches(&self, node: &LinkedNode) -> bool {
+        match node.kind() {
+            SyntaxKind::FuncCall => {
+                let call: ast::FuncCall = node.cast().unwrap();
+                call.callee().to_untyped().clone().into_text() == self.name
+                    && self.conditions.iter().all(|(field, value)| {
+                        call.args().items().any(|arg| match arg {
+                            ast::Arg::Named(named) => {
+                                named.name().as_str() == field && literal(named.expr()) == *value
+                            }
+                            _ => false,
+                        })
+                    })
+            }
+            SyntaxKind::Named => {
+                let named: ast::Named = node.cast().unwrap();
+                named.name().as_str() == self.name && self.conditions.is_empty()
+            }
+            kind => {
+                sugar(kind) == Some(self.name.as_str())
+                    && self.conditions.iter().all(|(field, value)| {
+                        let actual = match (kind, field.as_str()) {
+                            (SyntaxKind::Heading, "level" | "depth") => node
+                                .cast::<ast::Heading>()
+                                .map(|heading| heading.depth().to_string().into()),
+                            (SyntaxKind::Raw, "lang") => node
+                                .cast::<ast::Raw>()
+                                .and_then(|raw| Some(raw.lang()?.get().clone())),
+                            _ => None,
+                        };
+                        actual.as_ref() == Some(value)
+                    })
+            }
+        }
+    }
+}
+
+/// The name of the element that markup of a kind is sugar for.
+fn sugar(kind: SyntaxKind) -> Option<&'static str> {
+    Some(match kind {
+        SyntaxKind::Heading => "heading",
+        SyntaxKind::Strong => "strong",
+        SyntaxKind::Emph => "emph",
+        SyntaxKind::Raw => "raw",
+        SyntaxKind::Link => "link",
+        SyntaxKind::Ref => "ref",
+        SyntaxKind::ListItem => "list",
+        SyntaxKind::EnumItem => "enum",
+        SyntaxKind::TermItem => "terms",
+        SyntaxKind::Equation => "math.equation",
+        _ => return None,
+    })
+}
+
+/// The value of a literal argument in the form conditions are written in.
+fn literal(expr: ast::Expr) -> EcoString {
+    match expr {
+        ast::Expr::Str(string) => string.get(),
+        expr => expr.to_untyped().clone().into_text(),
+    }
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("invalid selector `{s}`: {reason}");
+        let mut steps = Vec::new();
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+                .unwrap_or(rest.len());
+            let (mut name, mut after) = rest.split_at(end);
+            let mut conditions = Vec::new();
+            if let Some(prefix) = name.strip_suffix(".where") {
+                let Some(args) = after.strip_prefix('(') else {
+                    return Err(invalid("expected `(` after `where`"));
+                };
+                let close = closing_paren(args).ok_or_else(|| invalid("unclosed `where(`"))?;
+                for condition in split_args(&args[..close]) {
+                    let (field, value) = condition
+                        .split_once(':')
+                        .ok_or_else(|| invalid("expected `field: value` in `where(..)`"))?;
+                    let value = value.trim();
+                    let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                        Some(string) => string,
+                        None => value,
+                    };
+                    conditions.push((field.trim().into(), value.into()));
+                }
+                name = prefix;
+                after = &args[close + 1..];
+            }
+            if name.is_empty() || name.ends_with('.') {
+                return Err(invalid("expected an element name"));
+            }
+            if !after.is_empty() && !after.starts_with(char::is_whitespace) {
+                return Err(invalid("expected a space between elements"));
+            }
+            steps.push(Step {
+                name: name.into(),
+                conditions,
+            });
+            rest = after.trim_start();
+        }
+        if steps.is_empty() {
+            return Err(invalid("expected an element name"));
+        }
+        Ok(Self { steps })
+    }
+}
+
+/// The position of the parenthesis that closes the one before `text`, outside of strings.
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ')' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split the arguments of `where(..)` at the commas outside of strings, without empty ones.
+fn split_args(text: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let (mut quoted, mut start) = (false, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                args.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&text[start..]);
+    args.retain(|arg| !arg.trim().is_empty());
+    args
+}
+
+/// Keep only the edits inside the elements of a typst document that one of the `selected`
+/// selectors matches, if there are any, and drop those that touch an element that one of the
+/// `kept` selectors matches.
+pub(crate) fn filter(
+    root: &LinkedNode,
+    selected: &[Selector],
+    kept: &[Selector],
+    mut edits: Vec<Edit>,
+) -> Vec<Edit> {
+    let (mut selected_ranges, mut kept_ranges) = (Vec::new(), Vec::new());
+    collect(root, selected, &mut selected_ranges);
+    collect(root, kept, &mut kept_ranges);
+    edits.retain(|edit| {
+        let inside =
+            |range: &Range<usize>| range.start <= edit.range.start && edit.range.end <= range.end;
+        let touches =
+            |range: &Range<usize>| range.start < edit.range.end && edit.range.start < range.end;
+        (selected.is_empty() || selected_ranges.iter().any(inside))
+            && !kept_ranges.iter().any(touches)
+    });
+    edits
+}
+
+/// Collect the ranges of the elements in a node and its descendants that one of the selectors
+/// matches, without those inside of them.
+fn collect(node: &LinkedNode, selectors: &[Selector], ranges: &mut Vec<Range<usize>>) {
+    if selectors.iter().any(|selector| selector.matches(node)) {
+        ranges.push(node.range());
+        return;
+    }
+    for child in node.children() {
+        collect(&child, selectors, ranges);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mutilate_str, MutilateOptions};
+
+    const CODE: &str =
+        "= Title\n== Part\nSome *bold words* here.\n\n#figure(rect(), caption: [A small box.])\n";
+
+    fn mutilate(options: MutilateOptions) -> String {
+        mutilate_str(CODE, &options.seed(1)).unwrap()
+    }
+
+    fn selector(text: &str) -> Selector {
+        text.parse().unwrap()
+    }
+
+    /// Whether the selector matches any node of the code.
+    fn matches_in(selector: &Selector, code: &str) -> bool {
+        fn any(node: &LinkedNode, selector: &Selector) -> bool {
+            selector.matches(node) || node.children().any(|child| any(&child, selector))
+        }
+        any(&LinkedNode::new(&typst_syntax::parse(code)), selector)
+    }
+
+    #[test]
+    fn selected_elements() {
+        let output = mutilate(MutilateOptions::new().select(selector("figure caption")));
+        // Everything up to the caption is kept.
+        assert!(output.starts_with(&CODE[..CODE.find("[A").unwrap() + 1]));
+        assert!(!output.contains("small box"));
+    }
+
+    #[test]
+    fn kept_elements() {
+        let options = MutilateOptions::new()
+            .keep(selector("heading.where(level: 1)"))
+            .keep(selector("strong"));
+        let output = mutilate(options);
+        assert!(output.starts_with("= Title\n== "));
+        assert!(output.contains(" *bold words* "));
+        assert!(!output.contains("Part") && !output.contains("Some"));
+        assert!(!output.contains("small box"));
+    }
+
+    #[test]
+    fn conditions() {
+        assert!(matches_in(
+            &selector("raw.where(lang: \"rust\")"),
+            "```rust fn```"
+        ));
+        assert!(!matches_in(
+            &selector("raw.where(lang: \"rust\")"),
+            "```py x```"
+        ));
+        assert!(matches_in(
+            &selector("text.where(fill: red)"),
+            "#text(fill: red)[a]"
+        ));
+        assert!(!matches_in(&selector("figure"), "#text(fill: red)[a]"));
+        assert!("figure.where(kind".parse::<Selector>().is_err());
+        assert!("figure.".parse::<Selector>().is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Mutilating a corpus with several seeds and strategies and checking invariants that every
+//! mutilated document must satisfy.
+
+use std::{io::Write, path::PathBuf};
+
+use typst_mutilate::{Context, Edit, Error, Format, Mode, MutilateOptions, Segmentation, Strategy};
+
+use crate::diff;
+
+/// A document of the corpus.
+pub struct Document {
+    pub path: PathBuf,
+    pub format: Format,
+    pub mode: Mode,
+    pub code: String,
+}
+
+/// The outcome of a self-test.
+pub struct Summary {
+    pub runs: usize,
+    pub failures: usize,
+}
+
+/// Words this short are common enough to be picked as their own replacement, so they are not
+/// checked for leaks.
+const MINIMUM_LEAK_LENGTH: usize = 4;
+
+/// Mutilate every document with the seeds `1..=seeds`, once with every strategy and once with
+/// each strategy on its own, and report the invariants that don't hold to `out`.
+///
+/// The length of a mutilated document may differ from the original by the share `tolerance`.
+pub fn run(
+    out: &mut impl Write,
+    documents: &[Document],
+    options: &MutilateOptions,
+    seeds: u64,
+    tolerance: f64,
+) -> Result<Summary, Error> {
+    let mut sets = vec![Strategy::ALL.to_vec()];
+    sets.extend(Strategy::ALL.iter().map(|&strategy| vec![strategy]));
+
+    let mut summary = Summary {
+        runs: 0,
+        failures: 0,
+    };
+    for seed in 1..=seeds {
+        for strategies in &sets {
+            let options = options
+                .clone()
+                .seed(seed)
+                .strategies(strategies.iter().copied());
+            let mut context = Context::new(&options)?;
+            let label = strategies
+                .iter()
+                .map(|strategy| strategy.name())
+                .collect::<Vec<_>>()
+                .join(",");
+            for document in documents {
+                summary.runs += 1;
+                let file = document.path.to_string_lossy();
+                context.set_format(document.format);
+                context.set_mode(document.mode);
+                let edits = typst_mutilate::plan_str_with(&document.code, &mut context)?;
+                context.take_audit();
+                let failures = check(document, &edits, tolerance, context.segmentation());
+                if !failures.is_empty() {
+                    summary.failures += 1;
+                }
+                for failure in failures {
+                    writeln!(out, "{file} (seed {seed}, {label}): {}", failure.message)?;
+                    diff::write(out, &file, &document.code, &failure.edits, false)?;
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// An invariant that doesn't hold, with the fewest edits that show it.
+struct Failure {
+    message: String,
+    edits: Vec<Edit>,
+}
+
+/// Check the invariants of the mutilated document.
+fn check(
+    document: &Document,
+    edits: &[Edit],
+    tolerance: f64,
+    segmentation: Segmentation,
+) -> Vec<Failure> {
+    let words = |text| segmentation.words(text).map(|(_, word)| word);
+    let code = &document.code;
+    let mut failures = Vec::new();
+
+    if document.format == Format::Typst {
+        let output = typst_mutilate::apply_edits(code, edits);
+        if let Some(offset) = first_error(&output, document.mode) {
+            failures.push(Failure {
+                message: "the mutilated document doesn't parse".into(),
+                edits: reduce_error(document, edits, offset),
+            });
+        }
+    }
+
+    let miscounted: Vec<Edit> = edits
+        .iter()
+        .filter(|edit| words(&code[edit.range.clone()]).count() != words(&edit.replacement).count())
+        .cloned()
+        .collect();
+    if !miscounted.is_empty() {
+        let before: usize = edits
+            .iter()
+            .map(|edit| words(&code[edit.range.clone()]).count())
+            .sum();
+        let after: usize = edits
+            .iter()
+            .map(|edit| words(&edit.replacement).count())
+            .sum();
+        failures.push(Failure {
+            message: format!("{before} replaced words became {after} words"),
+            edits: miscounted.into_iter().take(1).collect(),
+        });
+    }
+
+    let leaked = leaks(code, edits, segmentation);
+    if let Some(first) = leaked.first() {
+        failures.push(Failure {
+            message: format!(
+                "{} {} original words, like `{}`",
+                crate::counted(leaked.len(), "replacement", "replacements"),
+                if leaked.len() == 1 { "keeps" } else { "keep" },
+                &code[first.range.clone()]
+            ),
+            edits: vec![first.clone()],
+        });
+    }
+
+    let before: usize = edits
+        .iter()
+        .map(|edit| code[edit.range.clone()].chars().count())
+        .sum();
+    let after: usize = edits
+        .iter()
+        .map(|edit| edit.replacement.chars().count())
+        .sum();
+    if before.abs_diff(after) as f64 > before as f64 * tolerance {
+        // The replacements whose length differs most show the deviation best.
+        let mut worst = edits.to_vec();
+        worst.sort_by_key(|edit| {
+            std::cmp::Reverse(
+                code[edit.range.clone()]
+                    .chars()
+                    .count()
+                    .abs_diff(edit.replacement.chars().count()),
+            )
+        });
+        worst.truncate(3);
+        worst.sort_by_key(|edit| edit.range.start);
+        failures.push(Failure {
+            message: format!("{before} replaced characters became {after} characters"),
+            edits: worst,
+        });
+    }
+
+    failures
+}
+
+/// The edits whose replacements keep one of the words they replace.
+pub fn leaks(code: &str, edits: &[Edit], segmentation: Segmentation) -> Vec<Edit> {
+    let words = |text| segmentation.words(text).map(|(_, word)| word);
+    edits
+        .iter()
+        .filter(|edit| {
+            let replacement: Vec<String> =
+                words(&edit.replacement).map(str::to_lowercase).collect();
+            words(&code[edit.range.clone()])
+                .filter(|word| word.chars().count() >= MINIMUM_LEAK_LENGTH)
+                .any(|word| replacement.contains(&word.to_lowercase()))
+        })
+        .cloned()
+        .collect()
+}
+
+/// The offset of the first syntax error of a typst document.
+pub fn first_error(code: &str, mode: Mode) -> Option<usize> {
+    let syntax = match mode {
+        Mode::Markup => typst_syntax::parse(code),
+        Mode::Code => typst_syntax::parse_code(code),
+        Mode::Math => typst_syntax::parse_math(code),
+    };
+    let mut node = typst_syntax::LinkedNode::new(&syntax);
+    if !node.erroneous() {
+        return None;
+    }
+    while node.kind() != typst_syntax::SyntaxKind::Error {
+        node = node.children().find(|child| child.erroneous())?;
+    }
+    Some(node.offset())
+}
+
+/// The edits of the original line with the first syntax error of the output, or a single one
+/// of them if it causes an error on its own.
+fn reduce_error(document: &Document, edits: &[Edit], offset: usize) -> Vec<Edit> {
+    let code = &document.code;
+    let original = original_offset(code, edits, offset);
+    let line_start = code[..original].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = code[original..]
+        .find('\n')
+        .map_or(code.len(), |i| original + i);
+    let line: Vec<Edit> = edits
+        .iter()
+        .filter(|edit| edit.range.start <= line_end && edit.range.end >= line_start)
+        .cloned()
+        .collect();
+
+    line.iter()
+        .find(|edit| {
+            let output = typst_mutilate::apply_edits(code, std::slice::from_ref(edit));
+            first_error(&output, document.mode).is_some()
+        })
+        .map_or(line.clone(), |edit| vec![edit.clone()])
+}
+
+/// Map an offset in the output of the edits back to `code` by undoing the length changes of
+/// the edits before it.
+pub fn original_offset(code: &str, edits: &[Edit], offset: usize) -> usize {
+    let mut original = offset as isize;
+    for edit in edits {
+        let start = edit.range.start as isize;
+        if start >= original {
+            break;
+        }
+        original -= edit.replacement.len() as isize - edit.range.len() as isize;
+    }
+    let mut original = (original.max(0) as usize).min(code.len());
+    while !code.is_char_boundary(original) {
+        original -= 1;
+    }
+    original
+}
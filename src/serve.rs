@@ -0,0 +1,334 @@
+//! A minimal HTTP service, enabled by the `serve` feature.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    panic::AssertUnwindSafe,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    Context, Format, HeadingPattern, Mode, MutilateOptions, Normalization, NumberMode,
+    Segmentation, Selector, Strategy,
+};
+
+/// The default limit of the size of request bodies, in bytes.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1 << 20;
+/// The default number of requests that are handled at the same time.
+pub const DEFAULT_WORKERS: usize = 4;
+/// The limit of the size of the request line and headers, in bytes.
+const MAX_HEAD_SIZE: usize = 16 << 10;
+/// How long to wait for a client to send its request.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves `POST /mutilate` requests, which send a document in the body and get back the
+/// mutilated document.
+///
+/// Query parameters override the options of the server: `language`, `seed`, `format`, `mode`,
+/// `strategy` (repeatable), `numbers`, `segmentation`, `normalize-output`, `protect`
+/// (repeatable), `section` (repeatable), `exclude-section` (repeatable), `select`
+/// (repeatable), `keep` (repeatable), `date-offset` and the switches `aggressive`, `alt-text`, `scrub-pii`,
+/// `scrub-metadata`, `names-only` and `shift-dates`, which are enabled by `true` or an empty
+/// value.
+///
+/// Every worker loads the wordlists once and reuses them for all of its requests, except for
+/// requests in another language, which load them again.
+pub struct Server {
+    listener: TcpListener,
+    options: Arc<MutilateOptions>,
+    max_body_size: usize,
+    workers: usize,
+}
+
+impl Server {
+    /// Listen on an address, mutilating with `options` unless a request overrides them.
+    pub fn bind(addr: impl ToSocketAddrs, options: MutilateOptions) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            options: Arc::new(options),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            workers: DEFAULT_WORKERS,
+        })
+    }
+
+    /// Reject requests with bodies larger than this number of bytes.
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// Handle this many requests at the same time, while later connections wait.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Handle requests forever on the workers.
+    pub fn run(self) -> crate::Result<()> {
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(self.workers);
+        let receiver = Arc::new(Mutex::new(receiver));
+        // Contexts can't be sent to other threads, so every worker builds its own and reports
+        // whether that worked before connections are accepted.
+        let (ready, started) = mpsc::channel();
+        for _ in 0..self.workers {
+            let options = Arc::clone(&self.options);
+            let receiver = Arc::clone(&receiver);
+            let ready = ready.clone();
+            let max_body_size = self.max_body_size;
+            std::thread::spawn(move || {
+                let context = match Context::new(&options) {
+                    Ok(context) => context,
+                    Err(err) => return ready.send(Err(err)).unwrap_or(()),
+                };
+                let _ = ready.send(Ok(()));
+                let mut worker = Worker {
+                    options,
+                    context: Some(context),
+                };
+                loop {
+                    let Ok(stream) = receiver.lock().unwrap().recv() else {
+                        return;
+                    };
+                    // The client is gone if the response can't be written, so there's no one
+                    // to tell about it.
+                    let _ = handle(stream, &mut worker, max_body_size);
+                }
+            });
+        }
+        for _ in 0..self.workers {
+            started
+                .recv()
+                .map_err(|_| io::Error::other("a worker failed to start"))??;
+        }
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) if err.kind() == io::ErrorKind::ConnectionAborted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            // This waits while all workers are busy and enough connections are queued.
+            if sender.send(stream).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The state of a thread that handles requests.
+struct Worker {
+    options: Arc<MutilateOptions>,
+    /// The context of the last request, which is gone if mutilating panicked.
+    context: Option<Context>,
+}
+
+impl Worker {
+    /// Mutilate a document with the options of a request.
+    fn mutilate(&mut self, code: &str, options: &MutilateOptions) -> crate::Result<String> {
+        let same_language = options.language == self.options.language;
+        let mut context = match self.context.take() {
+            Some(context) if same_language => context.reuse(options),
+            _ => Context::new(options)?,
+        };
+        let output = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            crate::mutilate_str_with(code, &mut context)
+        }))
+        .map_err(|_| crate::Error::Io(io::Error::other("mutilating the document failed")))?;
+        if same_language {
+            self.context = Some(context);
+        }
+        output
+    }
+}
+
+/// A response to a request.
+struct Response {
+    status: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn error(status: &'static str, message: impl Into<String>) -> Self {
+        let mut body = message.into();
+        body.push('\n');
+        Self { status, body }
+    }
+}
+
+fn handle(stream: TcpStream, worker: &mut Worker, max_body_size: usize) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match respond(&mut reader, worker, max_body_size) {
+        Ok(response) | Err(response) => response,
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+fn respond(
+    reader: &mut impl BufRead,
+    worker: &mut Worker,
+    max_body_size: usize,
+) -> Result<Response, Response> {
+    let bad_request = |message: &str| Response::error("400 Bad Request", message);
+    let mut head = reader.take(MAX_HEAD_SIZE as u64);
+    let request_line = read_line(&mut head).ok_or_else(|| bad_request("malformed request"))?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(_)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(bad_request("malformed request line"));
+    };
+
+    let mut length = None;
+    loop {
+        let line = read_line(&mut head).ok_or_else(|| {
+            Response::error("431 Request Header Fields Too Large", "headers too large")
+        })?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(bad_request("malformed header"));
+        };
+        if name.eq_ignore_ascii_case("content-length") {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| bad_request("bad length"))?,
+            );
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(Response::error(
+                "411 Length Required",
+                "chunked bodies aren't supported, send a Content-Length",
+            ));
+        }
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/mutilate" {
+        return Err(Response::error("404 Not Found", "only /mutilate exists"));
+    }
+    if method != "POST" {
+        return Err(Response::error("405 Method Not Allowed", "use POST"));
+    }
+    let length = length.ok_or_else(|| Response::error("411 Length Required", "no length"))?;
+    if length > max_body_size {
+        return Err(Response::error(
+            "413 Content Too Large",
+            format!("the document is larger than {max_body_size} bytes"),
+        ));
+    }
+
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|_| bad_request("incomplete body"))?;
+    let code = String::from_utf8(body).map_err(|_| bad_request("the body isn't UTF-8"))?;
+    let options =
+        query_options(query, (*worker.options).clone()).map_err(|err| bad_request(&err))?;
+    match worker.mutilate(&code, &options) {
+        Ok(body) => Ok(Response {
+            status: "200 OK",
+            body,
+        }),
+        Err(err @ crate::Error::Io(_)) => Err(Response::error(
+            "500 Internal Server Error",
+            err.to_string(),
+        )),
+        Err(err) => Err(Response::error(
+            "422 Unprocessable Content",
+            err.to_string(),
+        )),
+    }
+}
+
+/// Read a line that ends in CRLF or LF, or `None` if it doesn't end before the limit.
+fn read_line(reader: &mut impl BufRead) -> Option<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let line = line.strip_suffix('\n')?;
+    Some(line.strip_suffix('\r').unwrap_or(line).into())
+}
+
+/// Apply the options in a query string.
+fn query_options(query: &str, mut options: MutilateOptions) -> Result<MutilateOptions, String> {
+    let mut strategies = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value).ok_or_else(|| format!("bad encoding of `{key}`"))?;
+        let switch = || match value.as_str() {
+            "" | "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(format!("`{key}` must be `true` or `false`")),
+        };
+        options = match key {
+            "language" => options.language(
+                crate::parse_language(&value)
+                    .ok_or_else(|| format!("language `{value}` is not supported"))?,
+            ),
+            "seed" => options.seed(parse_number(&value)?),
+            "format" => options.format(value.parse::<Format>()?),
+            "mode" => options.mode(value.parse::<Mode>()?),
+            "strategy" => {
+                strategies.push(value.parse::<Strategy>()?);
+                options
+            }
+            "numbers" => options.numbers(value.parse::<NumberMode>()?),
+            "segmentation" => options.segmentation(value.parse::<Segmentation>()?),
+            "normalize-output" => options.normalize_output(value.parse::<Normalization>()?),
+            "protect" => options.protect(value),
+            "section" => options.section(value.parse::<HeadingPattern>()?),
+            "exclude-section" => options.exclude_section(value.parse::<HeadingPattern>()?),
+            "select" => options.select(value.parse::<Selector>()?),
+            "keep" => options.keep(value.parse::<Selector>()?),
+            "date-offset" => options.date_offset(parse_number(&value)?),
+            "aggressive" => options.aggressive(switch()?),
+            "alt-text" => options.alt_text(switch()?),
+            "scrub-pii" => options.scrub_pii(switch()?),
+            "scrub-metadata" => options.scrub_metadata(switch()?),
+            "names-only" => options.names_only(switch()?),
+            "shift-dates" => options.shift_dates(switch()?),
+            _ => return Err(format!("unknown option `{key}`")),
+        };
+    }
+    if !strategies.is_empty() {
+        options = options.strategies(strategies);
+    }
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad number `{value}`"))
+}
+
+/// Decode `%XX` escapes and `+` as a space.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        bytes.push(match b {
+            b'+' => b' ',
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?
+            }
+            b => b,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
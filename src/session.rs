@@ -0,0 +1,65 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use typst_syntax::Source;
+
+use crate::{apply_edits, plan, Context, Error, Format, Mode, Result};
+
+/// Mutilates files repeatedly, caching everything that can be reused between runs.
+///
+/// The context, including the indexed wordlist, is built only once. For typst markup, the
+/// parsed syntax tree of every file is kept, so that mutilating a file again after a small
+/// edit only reparses the edited part.
+pub struct Session {
+    context: Context,
+    sources: HashMap<PathBuf, Source>,
+}
+
+impl Session {
+    /// Create a session that mutilates using `context`.
+    pub fn new(context: Context) -> Self {
+        Self {
+            context,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// The context used for mutilating.
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /// Mutilate the current contents of the file at `path`.
+    pub fn mutilate(&mut self, path: &Path, code: &str) -> Result<String> {
+        if self.context.format != Format::Typst || self.context.mode != Mode::Markup {
+            return crate::mutilate_str_with(code, &mut self.context);
+        }
+
+        let source = match self.sources.get_mut(path) {
+            Some(source) => {
+                if source.text() != code {
+                    source.replace(code);
+                }
+                source
+            }
+            None => self
+                .sources
+                .entry(path.to_owned())
+                .or_insert_with(|| Source::detached(code)),
+        };
+
+        let errors = source.root().errors();
+        if !errors.is_empty() {
+            return Err(Error::Syntax(errors));
+        }
+        let edits = plan(source.root(), &mut self.context)?;
+        Ok(apply_edits(code, &edits))
+    }
+
+    /// Drop the cached syntax tree of the file at `path`.
+    pub fn forget(&mut self, path: &Path) {
+        self.sources.remove(path);
+    }
+}
@@ -0,0 +1,348 @@
+use std::ops::Range;
+
+use rand::seq::SliceRandom;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use typst_syntax::{ast, LinkedNode, SyntaxKind};
+
+use crate::Edit;
+
+/// Drops a share of the paragraphs, list items and table rows of typst documents.
+pub(crate) struct Shrinker {
+    fraction: f64,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Shrinker {
+    pub(crate) fn new(fraction: f64, seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x3a91_c5e7_0b2d_84f6);
+        Self { fraction, rng }
+    }
+
+    /// Choose the parts of a document to drop.
+    ///
+    /// Only whole units that define or label nothing are dropped, so that the document still
+    /// parses and its references resolve, and at least one unit of every group is kept.
+    pub(crate) fn plan(&mut self, root: &LinkedNode) -> Vec<Edit> {
+        let mut groups = Vec::new();
+        collect_groups(root, &mut groups);
+
+        let mut dropped = Vec::new();
+        for group in groups.iter().filter(|group| !group.is_empty()) {
+            let candidates: Vec<&Unit> = group.iter().filter(|unit| unit.droppable).collect();
+            let count = (candidates.len() as f64 * self.fraction).round() as usize;
+            let count = count.min(group.len() - 1);
+            dropped.extend(
+                candidates
+                    .choose_multiple(&mut self.rng, count)
+                    .map(|unit| unit.range.clone()),
+            );
+        }
+
+        // Units of nested groups may lie in dropped units of outer groups, and the last unit of
+        // a group overlaps the one before it, as they share a separator.
+        dropped.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+        let mut edits: Vec<Edit> = Vec::new();
+        for range in dropped {
+            if edits
+                .last()
+                .is_some_and(|last| range.start < last.range.end)
+            {
+                continue;
+            }
+            edits.push(Edit {
+                range,
+                replacement: "".into(),
+            });
+        }
+        edits
+    }
+}
+
+/// A part of a document that may be dropped, with the separators around it.
+struct Unit {
+    range: Range<usize>,
+    droppable: bool,
+}
+
+/// Collect the sibling paragraphs, list items and table rows of a node and its descendants.
+fn collect_groups(node: &LinkedNode, groups: &mut Vec<Vec<Unit>>) {
+    match node.kind() {
+        SyntaxKind::Markup => {
+            groups.push(paragraphs(node));
+            groups.extend(lists(node));
+        }
+        SyntaxKind::FuncCall => groups.extend(table_rows(node)),
+        _ => {}
+    }
+    for child in node.children() {
+        collect_groups(&child, groups);
+    }
+}
+
+/// The paragraphs of markup, each with the paragraph break after it, or before it for the
+/// last one, which ends at its last node that isn't a space, so that the line break at the end
+/// of the document is kept.
+fn paragraphs(markup: &LinkedNode) -> Vec<Unit> {
+    let mut spans: Vec<(Vec<LinkedNode>, Option<LinkedNode>)> = vec![(Vec::new(), None)];
+    for child in markup.children() {
+        if child.kind() == SyntaxKind::Parbreak {
+            spans.last_mut().unwrap().1 = Some(child);
+            spans.push((Vec::new(), None));
+        } else {
+            spans.last_mut().unwrap().0.push(child);
+        }
+    }
+    spans.retain(|(nodes, _)| nodes.iter().any(|node| node.kind() != SyntaxKind::Space));
+
+    let mut units = Vec::new();
+    for (i, (nodes, parbreak)) in spans.iter().enumerate() {
+        let start = nodes[0].offset();
+        let end = match parbreak {
+            Some(parbreak) => parbreak.offset() + parbreak.len(),
+            None => nodes
+                .iter()
+                .rfind(|node| node.kind() != SyntaxKind::Space)
+                .map_or(start, |node| node.offset() + node.len()),
+        };
+        // The last paragraph takes the break before it instead.
+        let start = match (parbreak, i.checked_sub(1).and_then(|i| spans[i].1.as_ref())) {
+            (None, Some(before)) => before.offset(),
+            _ => start,
+        };
+        units.push(Unit {
+            range: start..end,
+            // Headings, lists, figures, tables and the like are kept, so that the same kinds
+            // of content remain.
+            droppable: nodes.iter().all(prose),
+        });
+    }
+    units
+}
+
+/// The lists of markup, each with the items of the same kind that follow each other, and every
+/// item with the line break before it.
+fn lists(markup: &LinkedNode) -> Vec<Vec<Unit>> {
+    let mut lists: Vec<Vec<Unit>> = Vec::new();
+    let mut current: Option<SyntaxKind> = None;
+    let mut space_before: Option<usize> = None;
+    for child in markup.children() {
+        let kind = child.kind();
+        if matches!(
+            kind,
+            SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem
+        ) {
+            if current != Some(kind) {
+                lists.push(Vec::new());
+                current = Some(kind);
+                space_before = None;
+            }
+            // The first item of a list can't take the line break before it, which separates
+            // the list from what comes before it, so it is always kept.
+            let list = lists.last_mut().unwrap();
+            let start = match space_before {
+                Some(start) if !list.is_empty() => start,
+                _ => child.offset(),
+            };
+            let droppable = !list.is_empty() && space_before.is_some() && !defines(&child);
+            list.push(Unit {
+                range: start..child.offset() + child.len(),
+                droppable,
+            });
+            space_before = None;
+        } else if kind == SyntaxKind::Space {
+            space_before.get_or_insert(child.offset());
+        } else if !matches!(kind, SyntaxKind::LineComment | SyntaxKind::BlockComment) {
+            current = None;
+        }
+    }
+    lists.retain(|list| list.len() > 1);
+    lists
+}
+
+/// The rows of the cells of a `table` or `grid` call, each with the comma after it, or before
+/// it for the last one.
+fn table_rows(call: &LinkedNode) -> Vec<Vec<Unit>> {
+    let Some(func) = call.cast::<ast::FuncCall>() else {
+        return Vec::new();
+    };
+    let ast::Expr::Ident(ident) = func.callee() else {
+        return Vec::new();
+    };
+    if !matches!(ident.as_str(), "table" | "grid") {
+        return Vec::new();
+    }
+    let Some(args) = call
+        .children()
+        .find(|child| child.kind() == SyntaxKind::Args)
+    else {
+        return Vec::new();
+    };
+
+    let mut columns = 1;
+    for arg in func.args().items() {
+        if let ast::Arg::Named(named) = arg {
+            if named.name().as_str() == "columns" {
+                columns = match named.expr() {
+                    ast::Expr::Int(int) => match usize::try_from(int.get()) {
+                        Ok(columns) if columns > 0 => columns,
+                        _ => return Vec::new(),
+                    },
+                    ast::Expr::Array(array) => array.items().count(),
+                    _ => return Vec::new(),
+                };
+            }
+        }
+    }
+    if columns == 0 {
+        return Vec::new();
+    }
+    // Rows with cells on both sides of the closing parenthesis can't be dropped without it.
+    let close = args
+        .children()
+        .find(|child| child.kind() == SyntaxKind::RightParen)
+        .map(|paren| paren.offset());
+
+    // Runs of plain cells, interrupted by named arguments, spreads and special cells like
+    // `table.header`, which may span rows.
+    let arguments: Vec<LinkedNode> = args
+        .children()
+        .filter(|child| {
+            !child.kind().is_trivia()
+                && !matches!(
+                    child.kind(),
+                    SyntaxKind::LeftParen | SyntaxKind::RightParen | SyntaxKind::Comma
+                )
+        })
+        .collect();
+    let plain = |node: &LinkedNode| match node.kind() {
+        SyntaxKind::Named | SyntaxKind::Spread => false,
+        SyntaxKind::FuncCall => !matches!(
+            node.cast::<ast::FuncCall>().map(|call| call.callee()),
+            Some(ast::Expr::FieldAccess(_))
+        ),
+        _ => true,
+    };
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < arguments.len() {
+        if !plain(&arguments[start]) {
+            start += 1;
+            continue;
+        }
+        let run = arguments[start..]
+            .iter()
+            .take_while(|node| plain(node))
+            .count();
+        for row in (start..start + run - run % columns).step_by(columns) {
+            rows.push(row);
+        }
+        start += run;
+    }
+    if rows.len() < 2 {
+        return Vec::new();
+    }
+
+    let units = rows
+        .iter()
+        .map(|&row| {
+            let last = &arguments[row + columns - 1];
+            let range = match arguments.get(row + columns) {
+                Some(next) => arguments[row].offset()..next.offset(),
+                // The last argument takes the comma before it instead.
+                None => match row.checked_sub(1) {
+                    Some(before) => {
+                        let before = &arguments[before];
+                        before.offset() + before.len()..last.offset() + last.len()
+                    }
+                    None => arguments[row].offset()..last.offset() + last.len(),
+                },
+            };
+            let droppable = !close.is_some_and(|close| range.contains(&close))
+                && arguments[row..row + columns]
+                    .iter()
+                    .all(|node| !defines(node));
+            Unit { range, droppable }
+        })
+        .collect();
+    vec![units]
+}
+
+/// Whether a node of a paragraph is running text that defines or labels nothing, unlike
+/// headings, lists, figures and tables.
+pub(crate) fn prose(node: &LinkedNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::Text
+            | SyntaxKind::Space
+            | SyntaxKind::Linebreak
+            | SyntaxKind::Strong
+            | SyntaxKind::Emph
+            | SyntaxKind::SmartQuote
+            | SyntaxKind::Escape
+            | SyntaxKind::Shorthand
+            | SyntaxKind::Link
+            | SyntaxKind::Ref
+            | SyntaxKind::Raw
+            | SyntaxKind::Equation
+            | SyntaxKind::LineComment
+            | SyntaxKind::BlockComment
+    ) && !defines(node)
+}
+
+/// Whether a node has a label or a definition that other parts of the document may use.
+fn defines(node: &LinkedNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::Label
+            | SyntaxKind::LetBinding
+            | SyntaxKind::SetRule
+            | SyntaxKind::ShowRule
+            | SyntaxKind::ModuleImport
+            | SyntaxKind::ModuleInclude
+    ) || node.children().any(|child| defines(&child))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shrink(code: &str, fraction: f64, seed: u64) -> String {
+        let syntax = typst_syntax::parse(code);
+        let edits = Shrinker::new(fraction, Some(seed)).plan(&LinkedNode::new(&syntax));
+        crate::apply_edits(code, &edits)
+    }
+
+    #[test]
+    fn drops_paragraphs() {
+        let code = "Zero.\n\nOne.\n\nTwo.\n";
+        for seed in 0..8 {
+            let output = shrink(code, 0.5, seed);
+            assert!(output.ends_with(".\n"), "{output:?}");
+            // Whole paragraphs are dropped with one of the breaks around them.
+            let kept: Vec<&str> = output.trim_end().split("\n\n").collect();
+            assert!(kept.len() < 3, "{output:?}");
+            assert!(kept
+                .iter()
+                .all(|paragraph| ["Zero.", "One.", "Two."].contains(paragraph)));
+        }
+    }
+
+    #[test]
+    fn keeps_labels_and_headings() {
+        let code = "= Heading\n\nOne.\n\nTwo. <two>\n\nThree.\n";
+        for seed in 0..8 {
+            let output = shrink(code, 1.0, seed);
+            assert!(output.starts_with("= Heading\n\n"), "{output:?}");
+            assert!(output.contains("Two. <two>"), "{output:?}");
+        }
+    }
+
+    #[test]
+    fn drops_list_items_but_the_first() {
+        let code = "- a\n- b\n- c\n";
+        for seed in 0..8 {
+            assert_eq!(shrink(code, 1.0, seed), "- a\n");
+        }
+    }
+}
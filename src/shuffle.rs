@@ -0,0 +1,417 @@
+use std::ops::Range;
+
+use rand::seq::SliceRandom;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use typst_syntax::{ast, LinkedNode, SyntaxKind};
+
+use crate::{shrink, Edit, Shuffle};
+
+/// Moves sibling paragraphs or sections of typst documents to random positions.
+pub(crate) struct Shuffler {
+    unit: Shuffle,
+    rng: Xoshiro256PlusPlus,
+    /// The ranges that received another unit in the last document.
+    moved: Vec<Range<usize>>,
+}
+
+impl Shuffler {
+    pub(crate) fn new(unit: Shuffle, seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x6d2f_94b1_e837_0ac5);
+        Self {
+            unit,
+            rng,
+            moved: Vec::new(),
+        }
+    }
+
+    /// Permute the units of every group of a document, taking the planned `edits` inside them
+    /// along.
+    ///
+    /// Units whose boundaries an edit crosses stay where they are.
+    pub(crate) fn plan(
+        &mut self,
+        root: &LinkedNode,
+        code: &str,
+        mut edits: Vec<Edit>,
+    ) -> Vec<Edit> {
+        self.moved.clear();
+        let mut groups = Vec::new();
+        match self.unit {
+            Shuffle::Paragraphs => collect_paragraphs(root, &mut groups),
+            Shuffle::Sections => collect_sections(root, code, &mut groups),
+            Shuffle::Sentences => collect_sentences(root, &mut groups),
+        }
+        groups.retain(|group| group.len() > 1);
+        // Inner groups go first, so that their moved units are carried along by the units of
+        // the outer groups that contain them.
+        groups.sort_by_key(|group| group.last().unwrap().end - group[0].start);
+
+        for mut group in groups {
+            group.retain(|unit| {
+                !edits.iter().any(|edit| {
+                    edit.range.start < unit.end
+                        && unit.start < edit.range.end
+                        && !(unit.start <= edit.range.start && edit.range.end <= unit.end)
+                })
+            });
+            if group.len() < 2 {
+                continue;
+            }
+            let mut order: Vec<usize> = (0..group.len()).collect();
+            order.shuffle(&mut self.rng);
+
+            let rendered: Vec<String> = group
+                .iter()
+                .map(|unit| render(code, unit, &edits))
+                .collect();
+            let moved: Vec<&Range<usize>> = order
+                .iter()
+                .enumerate()
+                .filter(|&(slot, &unit)| slot != unit)
+                .map(|(slot, _)| &group[slot])
+                .collect();
+            edits.retain(|edit| {
+                !moved
+                    .iter()
+                    .any(|unit| unit.start <= edit.range.start && edit.range.end <= unit.end)
+            });
+            for (slot, &unit) in order.iter().enumerate() {
+                if slot != unit {
+                    self.moved.push(group[slot].clone());
+                    edits.push(Edit {
+                        range: group[slot].clone(),
+                        replacement: rendered[unit].as_str().into(),
+                    });
+                }
+            }
+            edits.sort_by_key(|edit| edit.range.start);
+        }
+        // Ranges of inner groups may have been moved along by outer ones.
+        self.moved.retain(|range| {
+            edits
+                .binary_search_by_key(&range.start, |edit| edit.range.start)
+                .is_ok_and(|i| edits[i].range == *range)
+        });
+        self.moved.sort_by_key(|range| range.start);
+        edits
+    }
+
+    /// The ranges of the last planned document that received the text of another unit.
+    pub(crate) fn moved(&self) -> &[Range<usize>] {
+        &self.moved
+    }
+}
+
+/// The text of a unit with the edits inside it applied.
+fn render(code: &str, unit: &Range<usize>, edits: &[Edit]) -> String {
+    let inside: Vec<Edit> = edits
+        .iter()
+        .filter(|edit| unit.start <= edit.range.start && edit.range.end <= unit.end)
+        .map(|edit| Edit {
+            range: edit.range.start - unit.start..edit.range.end - unit.start,
+            replacement: edit.replacement.clone(),
+        })
+        .collect();
+    crate::apply_edits(&code[unit.clone()], &inside)
+}
+
+/// A part of markup, for splitting it into paragraphs.
+enum Part<'a> {
+    /// The nodes of a paragraph, without the spaces around it.
+    Paragraph(Vec<LinkedNode<'a>>),
+    /// A block-level node, like a heading, a list item or a figure on its own line.
+    Block,
+}
+
+/// Split the children of markup into paragraphs at paragraph breaks and block-level nodes.
+fn parts<'a>(markup: &LinkedNode<'a>) -> Vec<Part<'a>> {
+    let children: Vec<LinkedNode> = markup.children().collect();
+    let breaks_line = |node: Option<&LinkedNode>| {
+        node.is_none_or(|node| match node.kind() {
+            SyntaxKind::Space => node.text().contains('\n'),
+            SyntaxKind::Parbreak => true,
+            _ => false,
+        })
+    };
+    let mut parts = Vec::new();
+    let mut paragraph = Vec::new();
+    for (i, child) in children.iter().enumerate() {
+        let block = matches!(
+            child.kind(),
+            SyntaxKind::Heading
+                | SyntaxKind::ListItem
+                | SyntaxKind::EnumItem
+                | SyntaxKind::TermItem
+        ) || (!shrink::prose(child)
+            && breaks_line(i.checked_sub(1).map(|i| &children[i]))
+            && breaks_line(children.get(i + 1)));
+        if block || child.kind() == SyntaxKind::Parbreak {
+            push_paragraph(&mut parts, std::mem::take(&mut paragraph));
+            if block {
+                parts.push(Part::Block);
+            }
+        } else if !paragraph.is_empty() || child.kind() != SyntaxKind::Space {
+            paragraph.push(child.clone());
+        }
+    }
+    push_paragraph(&mut parts, paragraph);
+    parts
+}
+
+/// Add the nodes of a paragraph without the spaces after them, unless there are none.
+fn push_paragraph<'a>(parts: &mut Vec<Part<'a>>, mut paragraph: Vec<LinkedNode<'a>>) {
+    while paragraph
+        .last()
+        .is_some_and(|node| node.kind() == SyntaxKind::Space)
+    {
+        paragraph.pop();
+    }
+    if !paragraph.is_empty() {
+        parts.push(Part::Paragraph(paragraph));
+    }
+}
+
+/// Collect the runs of sibling paragraphs of running text in a node and its descendants,
+/// without the spaces and paragraph breaks around them, which stay in place.
+///
+/// Headings, lists, figures and the like end a run and stay in place, as do paragraphs with
+/// definitions or embedded code.
+fn collect_paragraphs(node: &LinkedNode, groups: &mut Vec<Vec<Range<usize>>>) {
+    if node.kind() == SyntaxKind::Markup {
+        let mut run = Vec::new();
+        for part in parts(node) {
+            match part {
+                Part::Paragraph(nodes) if nodes.iter().all(shrink::prose) => {
+                    let last = nodes.last().unwrap();
+                    run.push(nodes[0].offset()..last.offset() + last.len());
+                }
+                _ => groups.push(std::mem::take(&mut run)),
+            }
+        }
+        groups.push(run);
+    }
+    for child in node.children() {
+        collect_paragraphs(&child, groups);
+    }
+}
+
+/// Collect the sections of the same depth with the same parent section in a node and its
+/// descendants, each from its heading to the next heading of the same or a lower depth.
+///
+/// Sections with definitions, imports or set and show rules end a group and stay in place, as
+/// the content after them may depend on them, as does a last section without a line break at
+/// its end.
+fn collect_sections(node: &LinkedNode, code: &str, groups: &mut Vec<Vec<Range<usize>>>) {
+    if node.kind() == SyntaxKind::Markup {
+        let children: Vec<LinkedNode> = node.children().collect();
+        let headings: Vec<(usize, usize)> = children
+            .iter()
+            .enumerate()
+            .filter_map(|(i, child)| Some((i, child.cast::<ast::Heading>()?.depth().get())))
+            .collect();
+        let end = node.offset() + node.len();
+        // The groups being built, with the index of the parent heading and the depth.
+        let mut open: Vec<(Option<usize>, usize, Vec<Range<usize>>)> = Vec::new();
+        for (h, &(i, depth)) in headings.iter().enumerate() {
+            let parent = headings[..h]
+                .iter()
+                .rev()
+                .find(|(_, other)| *other < depth)
+                .map(|&(i, _)| i);
+            let next = headings[h + 1..]
+                .iter()
+                .find(|(_, other)| *other <= depth)
+                .map_or(children.len(), |&(i, _)| i);
+            let range = children[i].offset()..children.get(next).map_or(end, LinkedNode::offset);
+            let movable = children[i..next].iter().all(|child| !binds(child))
+                && code[range.clone()].ends_with('\n');
+
+            let position = match open
+                .iter()
+                .position(|group| group.0 == parent && group.1 == depth)
+            {
+                Some(position) => position,
+                None => {
+                    open.push((parent, depth, Vec::new()));
+                    open.len() - 1
+                }
+            };
+            if movable {
+                open[position].2.push(range);
+            } else {
+                let (_, _, group) = open.remove(position);
+                groups.push(group);
+            }
+        }
+        groups.extend(open.into_iter().map(|(_, _, group)| group));
+    }
+    for child in node.children() {
+        collect_sections(&child, code, groups);
+    }
+}
+
+/// Collect the sentences of every paragraph of running text in a node and its descendants,
+/// without the spaces between them, where headings, list items and other blocks end
+/// paragraphs like paragraph breaks.
+///
+/// Sentences end after a `.`, `!`, `?` or `…` and the closing quotes and brackets after it
+/// that are followed by a space, unless the next word starts in lowercase, like after `e.g.`.
+/// Only text directly in the paragraph ends sentences, so that they never split markup
+/// elements like `*strong*` text or links.
+fn collect_sentences(node: &LinkedNode, groups: &mut Vec<Vec<Range<usize>>>) {
+    if node.kind() == SyntaxKind::Markup {
+        for part in parts(node) {
+            if let Part::Paragraph(paragraph) = part {
+                if paragraph.iter().all(shrink::prose) {
+                    groups.push(sentences(&paragraph));
+                }
+            }
+        }
+    }
+    for child in node.children() {
+        collect_sentences(&child, groups);
+    }
+}
+
+/// The sentences of the nodes of a paragraph.
+pub(crate) fn sentences(paragraph: &[LinkedNode]) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = None;
+    // The end of the last node that isn't a space, and of the text that the sentences so far
+    // took, including smart quotes after their ends.
+    let mut end = 0;
+    let mut taken = 0;
+    for (i, child) in paragraph.iter().enumerate() {
+        if child.offset() < taken || child.kind() == SyntaxKind::Space {
+            continue;
+        }
+        end = child.offset() + child.len();
+        if child.kind() != SyntaxKind::Text {
+            start.get_or_insert(child.offset());
+            continue;
+        }
+        let text = child.text();
+        let mut chars = text.char_indices().peekable();
+        while let Some((j, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            start.get_or_insert(child.offset() + j);
+            if !matches!(c, '.' | '!' | '?' | '…') {
+                continue;
+            }
+            let mut stop = j + c.len_utf8();
+            while let Some((k, close)) = chars.next_if(|&(_, close)| closes(close)) {
+                stop = k + close.len_utf8();
+            }
+            let rest = &text[stop..];
+            let next = rest.trim_start();
+            let mut stop = child.offset() + stop;
+            if rest.is_empty() {
+                // The sentence ends with the text if a space or the end of the paragraph
+                // follows, after any smart quotes, which belong to it.
+                let quotes = paragraph[i + 1..]
+                    .iter()
+                    .take_while(|next| next.kind() == SyntaxKind::SmartQuote)
+                    .count();
+                if paragraph
+                    .get(i + 1 + quotes)
+                    .is_some_and(|next| next.kind() != SyntaxKind::Space)
+                {
+                    continue;
+                }
+                if let Some(quote) = paragraph[i + 1..i + 1 + quotes].last() {
+                    stop = quote.offset() + quote.len();
+                    end = stop;
+                    taken = stop;
+                }
+            } else if next.len() == rest.len() || next.starts_with(char::is_lowercase) {
+                continue;
+            }
+            if let Some(start) = start.take() {
+                sentences.push(start..stop);
+            }
+        }
+    }
+    if let Some(start) = start {
+        sentences.push(start..end);
+    }
+    sentences
+}
+
+/// Whether a character may follow the punctuation at the end of a sentence, like a closing
+/// quote or bracket.
+fn closes(c: char) -> bool {
+    matches!(
+        c,
+        '.' | '!' | '?' | '…' | ')' | ']' | '"' | '\'' | '’' | '”' | '»'
+    )
+}
+
+/// Whether a node has a definition, import or rule that the content after it may depend on.
+fn binds(node: &LinkedNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::LetBinding
+            | SyntaxKind::SetRule
+            | SyntaxKind::ShowRule
+            | SyntaxKind::ModuleImport
+            | SyntaxKind::ModuleInclude
+    ) || node.children().any(|child| binds(&child))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shuffle(unit: Shuffle, code: &str, seed: u64) -> String {
+        let syntax = typst_syntax::parse(code);
+        let root = LinkedNode::new(&syntax);
+        let edits = Shuffler::new(unit, Some(seed)).plan(&root, code, Vec::new());
+        crate::apply_edits(code, &edits)
+    }
+
+    #[test]
+    fn paragraphs_under_headings() {
+        let code = "= Intro\nFirst one.\n\nSecond one.\n\nThird one.\n= Next\n- Item\n";
+        let outputs: Vec<String> = (0..20)
+            .map(|seed| shuffle(Shuffle::Paragraphs, code, seed))
+            .collect();
+        for output in &outputs {
+            let body = output
+                .strip_prefix("= Intro\n")
+                .and_then(|rest| rest.strip_suffix("\n= Next\n- Item\n"))
+                .unwrap();
+            let mut paragraphs: Vec<&str> = body.split("\n\n").collect();
+            paragraphs.sort();
+            assert_eq!(paragraphs, ["First one.", "Second one.", "Third one."]);
+        }
+        // The first and the last paragraph are moved too.
+        assert!(outputs
+            .iter()
+            .any(|output| !output.contains("Intro\nFirst")));
+        assert!(outputs
+            .iter()
+            .any(|output| !output.contains("Third one.\n=")));
+    }
+
+    #[test]
+    fn paragraphs_with_code_stay() {
+        let code = "One.\n\n#let x = 1\n\nTwo.\n\nThree.\n";
+        for seed in 0..10 {
+            let output = shuffle(Shuffle::Paragraphs, code, seed);
+            assert!(output.starts_with("One.\n\n#let x = 1\n\n"), "{output}");
+        }
+    }
+
+    #[test]
+    fn sentences_under_heading() {
+        let code = "= Title\nOne is here. Two is there. Three is everywhere.\n";
+        let outputs: Vec<String> = (0..10)
+            .map(|seed| shuffle(Shuffle::Sentences, code, seed))
+            .collect();
+        assert!(outputs.iter().all(|output| output.starts_with("= Title\n")));
+        assert!(outputs.iter().any(|output| output != code));
+        assert!(outputs.iter().all(|output| output.len() == code.len()));
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::{BufRead, Write};
+
+use typst_syntax::{LinkedNode, SyntaxKind};
+
+use crate::{apply_edits, plan_str_with, Context, Error, Format, Mode, Result};
+
+/// Mutilate a document piece by piece, keeping memory proportional to the largest block.
+///
+/// The input is split at blank lines into blocks that parse on their own, so a raw block or
+/// function call containing blank lines is kept together. Only the typst and plain text
+/// formats can be streamed.
+pub fn mutilate_stream<R: BufRead, W: Write>(
+    mut input: R,
+    context: &mut Context,
+    mut output: W,
+) -> Result<()> {
+    if !matches!(context.format, Format::Typst | Format::Plain) {
+        return Err(Error::Unsupported(format!(
+            "the {} format cannot be streamed",
+            context.format
+        )));
+    }
+
+    let mut block = String::new();
+    // The size of the block when it last failed to parse. It has to grow considerably before
+    // parsing is attempted again, so that long unclosed blocks aren't reparsed at every line.
+    let mut failed_len = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+        block.push_str(&line);
+
+        if line.trim().is_empty() && block.len() >= 2 * failed_len && !is_open(&block, context) {
+            match plan_str_with(&block, context) {
+                Ok(edits) => {
+                    output.write_all(apply_edits(&block, &edits).as_bytes())?;
+                    block.clear();
+                    failed_len = 0;
+                }
+                Err(Error::Syntax(_)) => failed_len = block.len(),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    let edits = plan_str_with(&block, context)?;
+    output.write_all(apply_edits(&block, &edits).as_bytes())?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Whether a block of a typst document ends in a delimiter that is still open, which parses
+/// without errors: a block comment that isn't closed yet.
+fn is_open(block: &str, context: &Context) -> bool {
+    if context.format != Format::Typst || !block.contains("/*") {
+        return false;
+    }
+    let syntax = match context.mode {
+        Mode::Markup => typst_syntax::parse(block),
+        Mode::Code => typst_syntax::parse_code(block),
+        Mode::Math => typst_syntax::parse_math(block),
+    };
+    let root = LinkedNode::new(&syntax);
+    let Some(last) = root.leaf_at(block.len()) else {
+        return false;
+    };
+    last.kind() == SyntaxKind::BlockComment && !is_closed(last.text())
+}
+
+/// Whether a block comment is closed, counting the comments nested in it.
+fn is_closed(comment: &str) -> bool {
+    let bytes = comment.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            b"/*" => depth += 1,
+            b"*/" => depth -= 1,
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+        if depth == 0 {
+            return i == bytes.len();
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MutilateOptions;
+
+    fn stream(code: &str) -> String {
+        let mut context = Context::new(&MutilateOptions::new().seed(1)).unwrap();
+        let mut output = Vec::new();
+        mutilate_stream(code.as_bytes(), &mut context, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn block_comment_across_blank_line() {
+        let code = "Some text.\n\n/* A comment\n\nthat goes on. */\n\nMore text.\n";
+        let output = stream(code);
+        assert!(typst_syntax::parse(&output).errors().is_empty());
+        assert_eq!(output.lines().count(), code.lines().count());
+        assert!(output.contains("/*") && output.contains("*/"));
+    }
+
+    #[test]
+    fn nested_block_comment() {
+        assert!(is_closed("/* a /* b */ c */"));
+        assert!(!is_closed("/* a /* b */ c"));
+        assert!(!is_closed("/*"));
+    }
+}
@@ -0,0 +1,192 @@
+use hypher::Lang;
+use rand::{seq::SliceRandom, Rng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// The words of a language by their part of speech, separated by spaces, in forms that fit
+/// together in any combination: plural nouns, the determiners and adjectives that go with
+/// them and verbs that agree with them.
+struct Vocabulary {
+    determiners: &'static str,
+    adjectives: &'static str,
+    nouns: &'static str,
+    /// Verbs that take an object.
+    transitive: &'static str,
+    intransitive: &'static str,
+    adverbs: &'static str,
+    /// Conjunctions that join two main clauses without changing their word order.
+    conjunctions: &'static str,
+}
+
+const ENGLISH: Vocabulary = Vocabulary {
+    determiners: "the these those some many all our their several",
+    adjectives: "old red quiet small bright green warm heavy narrow gentle distant cold busy \
+                 empty golden hidden little modern simple strange silent wooden careful curious \
+                 tired happy ancient rapid soft wild",
+    nouns: "dogs houses rivers gardens teachers windows letters mountains children farmers \
+            bridges trees cities ships birds doctors students flowers roads clouds stones lamps \
+            horses villages books engines markets islands painters sailors",
+    transitive: "see follow build carry find paint visit answer watch open move cross clean \
+                 read describe collect protect greet admire count",
+    intransitive: "sleep wait rest travel smile listen arrive wander work remain shine grow \
+                   gather breathe sing",
+    adverbs: "quietly often slowly today again early together rarely happily soon",
+    conjunctions: "and but so yet",
+};
+
+// Plurals have the same article for every gender, and after it, adjectives always end in -en.
+const GERMAN: Vocabulary = Vocabulary {
+    determiners: "die diese alle jene meine unsere eure",
+    adjectives: "alten roten stillen kleinen hellen grünen warmen schweren engen sanften fernen \
+                 kalten leeren goldenen neuen einfachen seltsamen müden frohen wilden weichen \
+                 klugen langen dunklen",
+    nouns: "Hunde Häuser Flüsse Gärten Lehrer Fenster Briefe Berge Kinder Bauern Brücken Bäume \
+            Städte Schiffe Vögel Ärzte Blumen Straßen Wolken Steine Lampen Pferde Dörfer Bücher \
+            Märkte Inseln Maler Tische Wälder",
+    transitive: "sehen suchen bauen tragen finden malen besuchen beobachten öffnen lesen \
+                 schreiben sammeln schützen grüßen bewundern holen zählen",
+    intransitive: "schlafen warten ruhen reisen lächeln arbeiten bleiben wachsen wandern singen",
+    adverbs: "oft langsam heute wieder früh zusammen selten leise bald gern",
+    conjunctions: "und aber doch denn",
+};
+
+/// A part of speech in a template.
+#[derive(Clone, Copy)]
+enum Slot {
+    Determiner,
+    Adjective,
+    Noun,
+    Transitive,
+    Intransitive,
+    Adverb,
+    Conjunction,
+}
+
+/// The templates of clauses, of which one with the needed number of words is chosen at
+/// random.
+const CLAUSES: &[&[Slot]] = {
+    use Slot::*;
+    &[
+        &[Noun],
+        &[Noun, Intransitive],
+        &[Determiner, Noun, Intransitive],
+        &[Determiner, Adjective, Noun, Intransitive],
+        &[Determiner, Noun, Intransitive, Adverb],
+        &[Determiner, Noun, Transitive, Determiner, Noun],
+        &[Determiner, Adjective, Noun, Intransitive, Adverb],
+        &[Determiner, Adjective, Noun, Transitive, Determiner, Noun],
+        &[Determiner, Noun, Transitive, Determiner, Adjective, Noun],
+        &[
+            Determiner, Adjective, Noun, Transitive, Determiner, Adjective, Noun,
+        ],
+    ]
+};
+
+/// The longest clause, after which sentences are joined from several clauses.
+const LONGEST_CLAUSE: usize = 7;
+
+/// The shortest clause that follows a conjunction.
+const SHORTEST_JOINED_CLAUSE: usize = 3;
+
+/// Generates sentences of simple clauses for the `sentences` strategy.
+pub(crate) struct SentenceGenerator {
+    rng: Xoshiro256PlusPlus,
+}
+
+impl SentenceGenerator {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let rng = crate::Context::substream(seed, 0x2b7e_1516_28ae_d2a6);
+        Self { rng }
+    }
+
+    /// Whether there are templates for a language.
+    pub(crate) fn supports(language: Lang) -> bool {
+        vocabulary(language).is_some()
+    }
+
+    /// Generate the words of a sentence with the given number of words, or `None` if there are
+    /// no templates for the language.
+    ///
+    /// The words of the original sentence, given in `avoid`, aren't used, so that none of
+    /// them is kept by chance.
+    pub(crate) fn generate(
+        &mut self,
+        words: usize,
+        language: Lang,
+        capitalize: bool,
+        avoid: &[&str],
+    ) -> Option<Vec<String>> {
+        let vocabulary = vocabulary(language)?;
+        let mut slots = Vec::with_capacity(words);
+        let mut left = words;
+        while left > LONGEST_CLAUSE {
+            let length = self.rng.gen_range(
+                SHORTEST_JOINED_CLAUSE..=LONGEST_CLAUSE.min(left - 1 - SHORTEST_JOINED_CLAUSE),
+            );
+            slots.extend_from_slice(self.clause(length));
+            slots.push(Slot::Conjunction);
+            left -= length + 1;
+        }
+        if left > 0 {
+            slots.extend_from_slice(self.clause(left));
+        }
+
+        let mut sentence = Vec::with_capacity(words);
+        for (i, slot) in slots.into_iter().enumerate() {
+            let candidates = match slot {
+                Slot::Determiner => vocabulary.determiners,
+                Slot::Adjective => vocabulary.adjectives,
+                Slot::Noun => vocabulary.nouns,
+                Slot::Transitive => vocabulary.transitive,
+                Slot::Intransitive => vocabulary.intransitive,
+                Slot::Adverb => vocabulary.adverbs,
+                Slot::Conjunction => vocabulary.conjunctions,
+            };
+            let candidates: Vec<&str> = candidates.split_whitespace().collect();
+            let fresh: Vec<&str> = candidates
+                .iter()
+                .copied()
+                .filter(|word| {
+                    !avoid
+                        .iter()
+                        .any(|avoid| avoid.to_lowercase() == word.to_lowercase())
+                })
+                .collect();
+            let word = fresh
+                .choose(&mut self.rng)
+                .or_else(|| candidates.choose(&mut self.rng))
+                .unwrap();
+            if i == 0 && capitalize {
+                let mut chars = word.chars();
+                sentence.push(
+                    chars
+                        .next()
+                        .into_iter()
+                        .flat_map(char::to_uppercase)
+                        .chain(chars)
+                        .collect(),
+                );
+            } else {
+                sentence.push(word.to_string());
+            }
+        }
+        Some(sentence)
+    }
+
+    /// A random clause template with the given number of words.
+    fn clause(&mut self, length: usize) -> &'static [Slot] {
+        let fitting: Vec<&'static [Slot]> = CLAUSES
+            .iter()
+            .copied()
+            .filter(|clause| clause.len() == length)
+            .collect();
+        fitting.choose(&mut self.rng).unwrap()
+    }
+}
+
+fn vocabulary(language: Lang) -> Option<&'static Vocabulary> {
+    match language {
+        Lang::English => Some(&ENGLISH),
+        Lang::German => Some(&GERMAN),
+        _ => None,
+    }
+}
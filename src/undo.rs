@@ -0,0 +1,145 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use sha2::{Digest, Sha256};
+
+use crate::{Edit, Error, Result};
+
+/// The first bytes of undo files.
+const MAGIC: &[u8] = b"typst-mutilate undo 1\n";
+
+/// The undo file of a document, which is next to it with `.mutilate-undo` appended to its name.
+pub fn undo_path(document: &Path) -> PathBuf {
+    let mut name = document.file_name().unwrap_or_default().to_owned();
+    name.push(".mutilate-undo");
+    document.with_file_name(name)
+}
+
+/// Record in the undo file of `document` how to restore `original` from the document after
+/// the `edits` were applied to it.
+///
+/// Every run adds a layer with the replaced spans and their original bytes, compressed, so
+/// that documents mutilated several times can still be restored to the first original.
+pub fn record_undo(document: &Path, original: &str, edits: &[Edit]) -> io::Result<()> {
+    let mut layer = Vec::new();
+    let mut output = Sha256::new();
+    let mut cursor = 0;
+    write_number(&mut layer, edits.len());
+    for edit in edits {
+        // The unchanged text before the edit, which is the same in the output.
+        output.update(&original[cursor..edit.range.start]);
+        output.update(edit.replacement.as_bytes());
+        write_number(&mut layer, edit.range.start - cursor);
+        write_number(&mut layer, edit.replacement.len());
+        write_number(&mut layer, edit.range.len());
+        layer.extend_from_slice(original[edit.range.clone()].as_bytes());
+        cursor = edit.range.end;
+    }
+    output.update(&original[cursor..]);
+    layer.extend_from_slice(&output.finalize());
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&layer)?;
+    let compressed = encoder.finish()?;
+
+    let path = undo_path(document);
+    let exists = path.exists();
+    let mut file = File::options().create(true).append(true).open(&path)?;
+    if !exists {
+        file.write_all(MAGIC)?;
+    }
+    file.write_all(&(compressed.len() as u64).to_le_bytes())?;
+    file.write_all(&compressed)
+}
+
+/// Restore the original of a document from its undo file, undoing every recorded run.
+///
+/// Fails if the document was changed since it was last mutilated.
+pub fn restore_undo(document: &Path, current: &str) -> Result<String> {
+    let path = undo_path(document);
+    let data = std::fs::read(&path)?;
+    let corrupt = || invalid(&path);
+    if !data.starts_with(MAGIC) {
+        return Err(corrupt().into());
+    }
+
+    let mut layers = Vec::new();
+    let mut rest = &data[MAGIC.len()..];
+    while !rest.is_empty() {
+        let (len, after) = rest.split_first_chunk::<8>().ok_or_else(corrupt)?;
+        let len = usize::try_from(u64::from_le_bytes(*len)).map_err(|_| corrupt())?;
+        let compressed = after.get(..len).ok_or_else(corrupt)?;
+        let mut layer = Vec::new();
+        DeflateDecoder::new(compressed).read_to_end(&mut layer)?;
+        layers.push(layer);
+        rest = &after[len..];
+    }
+
+    let mut text = current.as_bytes().to_vec();
+    for layer in layers.iter().rev() {
+        let split = layer.len().checked_sub(32).ok_or_else(corrupt)?;
+        let (spans, hash) = layer.split_at(split);
+        if Sha256::digest(&text).as_slice() != hash {
+            return Err(Error::Unsupported(format!(
+                "{} was changed since it was mutilated",
+                document.display()
+            )));
+        }
+        text = undo_layer(spans, &text).ok_or_else(corrupt)?;
+    }
+    String::from_utf8(text).map_err(|_| corrupt().into())
+}
+
+/// Apply the spans of a layer to the output they produced.
+fn undo_layer(mut spans: &[u8], output: &[u8]) -> Option<Vec<u8>> {
+    let count = read_number(&mut spans)?;
+    let mut original = Vec::with_capacity(output.len());
+    let mut cursor: usize = 0;
+    for _ in 0..count {
+        let unchanged = read_number(&mut spans)?;
+        let replacement = read_number(&mut spans)?;
+        let len = read_number(&mut spans)?;
+        let start = cursor.checked_add(unchanged)?;
+        original.extend_from_slice(output.get(cursor..start)?);
+        cursor = start.checked_add(replacement)?;
+        let (bytes, after) = spans.split_at_checked(len)?;
+        original.extend_from_slice(bytes);
+        spans = after;
+    }
+    original.extend_from_slice(output.get(cursor..)?);
+    spans.is_empty().then_some(original)
+}
+
+fn invalid(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} is not a valid undo file", path.display()),
+    )
+}
+
+/// Write a number as LEB128.
+fn write_number(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Read a number written by [`write_number`].
+fn read_number(bytes: &mut &[u8]) -> Option<usize> {
+    let mut value = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= usize::from(byte & 0x7f).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
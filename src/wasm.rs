@@ -0,0 +1,63 @@
+//! JavaScript bindings, enabled by the `wasm` feature.
+
+use ecow::EcoString;
+use wasm_bindgen::prelude::*;
+
+use crate::MutilateOptions;
+
+/// Settings that control how a document is mutilated.
+#[wasm_bindgen(js_name = Options)]
+#[derive(Default)]
+pub struct JsOptions {
+    inner: MutilateOptions,
+}
+
+#[wasm_bindgen(js_class = Options)]
+impl JsOptions {
+    /// Create the default options.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hyphenation language using a language tag, like `de` or `de-AT`.
+    pub fn language(&mut self, code: &str) -> Result<(), JsError> {
+        let language = crate::parse_language(code)
+            .ok_or_else(|| JsError::new(&format!("language `{code}` is not supported")))?;
+        self.update(|options| options.language(language));
+        Ok(())
+    }
+
+    /// Use the words of a line-separated wordlist as replacements.
+    pub fn wordlist(&mut self, text: &str) {
+        let words: Vec<EcoString> = text.lines().map(EcoString::from).collect();
+        self.update(|options| options.wordlist(words));
+    }
+
+    /// Whether to replace elements that are more likely to change behavior, like strings.
+    pub fn aggressive(&mut self, aggressive: bool) {
+        self.update(|options| options.aggressive(aggressive));
+    }
+
+    /// Keep a word unchanged wherever it occurs.
+    pub fn protect(&mut self, word: &str) {
+        self.update(|options| options.protect(word));
+    }
+
+    /// Seed the random number generator to get reproducible output.
+    pub fn seed(&mut self, seed: u64) {
+        self.update(|options| options.seed(seed));
+    }
+}
+
+impl JsOptions {
+    fn update(&mut self, f: impl FnOnce(MutilateOptions) -> MutilateOptions) {
+        self.inner = f(std::mem::take(&mut self.inner));
+    }
+}
+
+/// Replace all words in a typst document with random garbage.
+#[wasm_bindgen]
+pub fn mutilate(code: &str, options: &JsOptions) -> Result<String, JsError> {
+    crate::mutilate_str(code, &options.inner).map_err(|err| JsError::new(&err.to_string()))
+}
@@ -0,0 +1,530 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    ops::Deref,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use ecow::{EcoString, EcoVec};
+use hypher::Lang;
+use memmap2::Mmap;
+use rand::{seq::SliceRandom, Rng};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::{
+    cache::HyphenationCache, replacer::hyphenation, MutilateOptions, Patterns, WordlistSource,
+};
+
+/// Words bucketed by their length and hyphenation pattern.
+///
+/// Loading a wordlist only records where its words are, grouped by their length. The words of
+/// a length are only decoded, deduplicated and hyphenated once a word of that length needs to
+/// be replaced, so that large wordlists are cheap to load.
+pub(crate) struct WordIndex {
+    /// The language used to hyphenate the words.
+    language: Lang,
+    settings: IndexSettings,
+    texts: Vec<Text>,
+    /// The cached hyphenation patterns of each text, if it has a cache.
+    caches: Vec<Option<HyphenationCache>>,
+    /// The words that weren't needed yet, by their number of characters.
+    pending: HashMap<usize, Vec<WordRef>>,
+    /// The words that were needed, by their number of characters.
+    groups: HashMap<usize, Group>,
+}
+
+/// The contents of a wordlist.
+pub(crate) enum Text {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for Text {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// The location of a word in one of the texts.
+struct WordRef {
+    text: u32,
+    len: u32,
+    start: usize,
+    count: u64,
+}
+
+/// The words of one length.
+///
+/// Every word is stored once, and the buckets refer to the words by their index.
+struct Group {
+    words: Vec<EcoString>,
+    /// The frequency of every word, or nothing if all words have the frequency 1.
+    counts: Vec<u64>,
+    /// The index of every word by the hash of its key.
+    ///
+    /// Only the hashes are stored to save memory. A collision merely makes a word count as a
+    /// duplicate, which is unlikely enough to not matter.
+    keys: HashMap<u64, u32>,
+    all: Bucket,
+    by_hyphenation: Option<HashMap<PatternKey, Bucket>>,
+}
+
+impl Group {
+    fn count(&self, index: u32) -> u64 {
+        self.counts.get(index as usize).copied().unwrap_or(1)
+    }
+
+    fn push(&mut self, word: EcoString, key: u64, count: u64) -> Option<u32> {
+        if self.keys.contains_key(&key) {
+            return None;
+        }
+        let index = u32::try_from(self.words.len()).ok()?;
+        self.keys.insert(key, index);
+        self.words.push(word);
+        self.counts.push(count);
+        Some(index)
+    }
+
+    /// Prepare the buckets for sampling once all words are added.
+    fn finish(&mut self) {
+        if self.counts.iter().all(|&count| count == 1) {
+            self.counts = Vec::new();
+        }
+        let counts = &self.counts;
+        self.all.finish(counts);
+        for bucket in self.by_hyphenation.iter_mut().flat_map(HashMap::values_mut) {
+            bucket.finish(counts);
+        }
+    }
+}
+
+/// A hyphenation pattern that is stored inline.
+///
+/// Patterns with more syllables than fit are truncated, which only affects extremely long
+/// words.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PatternKey {
+    len: u8,
+    syllables: [u8; 15],
+}
+
+impl From<&[u8]> for PatternKey {
+    fn from(pattern: &[u8]) -> Self {
+        let mut syllables = [0; 15];
+        let len = pattern.len().min(syllables.len());
+        syllables[..len].copy_from_slice(&pattern[..len]);
+        Self {
+            len: len as u8,
+            syllables,
+        }
+    }
+}
+
+/// How wordlists are indexed, shared by all indices of a replacer.
+#[derive(Clone)]
+pub(crate) struct IndexSettings {
+    /// Whether words that only differ in case are duplicates.
+    pub(crate) fold_case: bool,
+    /// Where the hyphenation patterns of wordlist files are cached.
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// Custom hyphenation patterns that replace the built-in ones.
+    pub(crate) patterns: Option<Arc<Patterns>>,
+    /// Whether the words of a length are sorted instead of kept in the order of the wordlists.
+    pub(crate) sort: bool,
+}
+
+impl IndexSettings {
+    pub(crate) fn new(options: &MutilateOptions) -> Self {
+        Self {
+            fold_case: options.fold_wordlist_case,
+            cache_dir: options.hyphenation_cache.clone(),
+            patterns: options.hyphenation_patterns.clone(),
+            sort: options.sort_wordlists,
+        }
+    }
+}
+
+impl WordIndex {
+    pub(crate) fn new(language: Lang, settings: IndexSettings) -> Self {
+        Self {
+            language,
+            settings,
+            texts: Vec::new(),
+            caches: Vec::new(),
+            pending: HashMap::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.groups.is_empty()
+    }
+
+    /// Load a wordlist of one word per line.
+    ///
+    /// Lines may be annotated with the frequency of the word, like `word<TAB>1234`, which is
+    /// used to prefer frequent words and to replace words by ones of similar frequency. Lines
+    /// that aren't valid UTF-8 are skipped.
+    ///
+    /// If there is a cache directory, the hyphenation patterns of the words of a file are
+    /// computed once and loaded from the cache afterwards.
+    pub(crate) fn load(&mut self, wordlist: &WordlistSource) -> io::Result<()> {
+        let text = match wordlist {
+            WordlistSource::Path(path) => {
+                let file = File::open(path)?;
+                // SAFETY: The wordlist must not be modified while it is in use, like any other
+                // input. Only valid UTF-8 is ever read from the mapping.
+                match unsafe { Mmap::map(&file) } {
+                    Ok(map) => Text::Mapped(map),
+                    // Mapping fails for empty files and on some platforms.
+                    Err(_) => Text::Owned(std::fs::read(path)?),
+                }
+            }
+            WordlistSource::Words(words) => {
+                self.add_text(Text::Owned(words.join("\n").into_bytes()));
+                return Ok(());
+            }
+        };
+
+        let counts = self.add_text(text);
+        if let WordlistSource::Path(path) = wordlist {
+            log::debug!(
+                "loaded {} {:?} words from {}",
+                counts.values().sum::<usize>(),
+                self.language,
+                path.display()
+            );
+        }
+        if let Some(dir) = &self.settings.cache_dir {
+            let index = self.texts.len() - 1;
+            let path = HyphenationCache::path(
+                dir,
+                &self.texts[index],
+                self.language,
+                self.settings.patterns.as_deref(),
+            );
+            let mut cache = HyphenationCache::open(&path, &counts);
+            if cache.is_none() {
+                // A cache that can't be written is only a missed optimization.
+                let patterns = self.hyphenate_text(index);
+                match HyphenationCache::write(&path, &patterns) {
+                    Ok(()) => {
+                        log::debug!("cached the hyphenation in {}", path.display());
+                        cache = HyphenationCache::open(&path, &counts);
+                    }
+                    Err(err) => log::debug!("could not cache the hyphenation: {err}"),
+                }
+            } else {
+                log::trace!("read the hyphenation from {}", path.display());
+            }
+            self.caches[index] = cache;
+        }
+        Ok(())
+    }
+
+    /// Load words from memory.
+    pub(crate) fn load_words(&mut self, words: &[&str]) {
+        self.add_text(Text::Owned(words.join("\n").into_bytes()));
+    }
+
+    /// Record where the words of a text are, returning the number of words of each length.
+    ///
+    /// Stray byte order marks and the `\r` of CRLF line endings are removed.
+    fn add_text(&mut self, text: Text) -> HashMap<usize, usize> {
+        let mut counts = HashMap::new();
+        let index = u32::try_from(self.texts.len()).expect("too many wordlists");
+        let base = text.as_ptr() as usize;
+        for line in text.split(|&b| b == b'\n') {
+            let Ok(line) = std::str::from_utf8(line) else {
+                continue;
+            };
+            let (word, count) = parse_line(line.trim_end());
+            let word = word.trim_matches('\u{feff}');
+            let Ok(len) = u32::try_from(word.len()) else {
+                continue;
+            };
+            let length = if is_nfc(word) {
+                word.chars().count()
+            } else {
+                word.nfc().count()
+            };
+            if length == 0 {
+                continue;
+            }
+            *counts.entry(length).or_default() += 1;
+            self.pending.entry(length).or_default().push(WordRef {
+                text: index,
+                len,
+                start: word.as_ptr() as usize - base,
+                count,
+            });
+        }
+        self.texts.push(text);
+        self.caches.push(None);
+        counts
+    }
+
+    /// Hyphenate all words of a text, grouped by their length and in their order in the text.
+    fn hyphenate_text(&self, index: usize) -> HashMap<usize, Vec<EcoVec<u8>>> {
+        let mut patterns = HashMap::new();
+        for (&length, refs) in &self.pending {
+            let group: Vec<_> = refs
+                .iter()
+                .filter(|word| word.text as usize == index)
+                .map(|word| match self.decode(word) {
+                    Some(word) => self.hyphenate(&word),
+                    None => EcoVec::new(),
+                })
+                .collect();
+            if !group.is_empty() {
+                patterns.insert(length, group);
+            }
+        }
+        patterns
+    }
+
+    /// The normalized word at a location.
+    fn decode(&self, word: &WordRef) -> Option<EcoString> {
+        let text = &self.texts[word.text as usize];
+        let word = std::str::from_utf8(&text[word.start..word.start + word.len as usize]).ok()?;
+        Some(if is_nfc(word) {
+            word.into()
+        } else {
+            word.nfc().collect()
+        })
+    }
+
+    /// The words with the given number of characters.
+    pub(crate) fn length_bucket(&mut self, length: usize) -> Option<Candidates<'_>> {
+        let group = self.group(length)?;
+        Some(Candidates {
+            bucket: &group.all,
+            group,
+        })
+    }
+
+    /// The words with the given hyphenation pattern.
+    pub(crate) fn hyphenation_bucket(&mut self, pattern: &[u8]) -> Option<Candidates<'_>> {
+        let language = self.language;
+        let patterns = self.settings.patterns.clone();
+        let length = pattern.iter().map(|&len| usize::from(len)).sum();
+        let group = self.group(length)?;
+        if group.by_hyphenation.is_none() {
+            let mut buckets: HashMap<PatternKey, Bucket> = HashMap::new();
+            for (index, word) in group.words.iter().enumerate() {
+                let pattern = hyphenation(word, language, patterns.as_deref());
+                buckets
+                    .entry(PatternKey::from(pattern.as_slice()))
+                    .or_default()
+                    .indices
+                    .push(index as u32);
+            }
+            for bucket in buckets.values_mut() {
+                bucket.finish(&group.counts);
+            }
+            group.by_hyphenation = Some(buckets);
+        }
+        let bucket = group
+            .by_hyphenation
+            .as_ref()?
+            .get(&PatternKey::from(pattern))?;
+        Some(Candidates { bucket, group })
+    }
+
+    /// The frequency of a word, if the wordlist contains it.
+    pub(crate) fn count(&mut self, word: &str) -> Option<u64> {
+        let key = self.key(word);
+        let lowercase = hash(&word.to_lowercase());
+        let group = self.group(word.chars().count())?;
+        let index = group
+            .keys
+            .get(&key)
+            .or_else(|| group.keys.get(&lowercase))?;
+        Some(group.count(*index))
+    }
+
+    /// The words of a length, decoding and deduplicating them if they weren't needed yet.
+    ///
+    /// Only words of the same length are compared to find duplicates, as words that only differ
+    /// in their encoding or case almost always have the same length.
+    fn group(&mut self, length: usize) -> Option<&mut Group> {
+        if let Some(refs) = self.pending.remove(&length) {
+            // If the patterns of all words are cached, the hyphenation buckets are built right
+            // away, as that is cheap.
+            let mut cursors: Vec<_> = self
+                .caches
+                .iter()
+                .map(|cache| cache.as_ref()?.start(length))
+                .collect();
+            let cached = refs
+                .iter()
+                .all(|word| cursors[word.text as usize].is_some());
+
+            let mut group = Group {
+                words: Vec::new(),
+                counts: Vec::new(),
+                keys: HashMap::new(),
+                all: Bucket::default(),
+                by_hyphenation: cached.then(HashMap::new),
+            };
+            // The cursors of the caches are read in the order of the words in their texts, so
+            // the words are only sorted once their patterns are known.
+            let mut words = Vec::with_capacity(refs.len());
+            for word_ref in &refs {
+                let text = word_ref.text as usize;
+                let pattern = match (&self.caches[text], &mut cursors[text]) {
+                    (Some(cache), Some(cursor)) => cache.read(cursor).map(PatternKey::from),
+                    _ => None,
+                };
+                if let Some(word) = self.decode(word_ref) {
+                    words.push((word, pattern, word_ref.count));
+                }
+            }
+            if self.settings.sort {
+                words.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+            }
+
+            for (word, pattern, count) in words {
+                let key = self.key(&word);
+                let pattern = match (&group.by_hyphenation, pattern) {
+                    (Some(_), None) => Some(PatternKey::from(self.hyphenate(&word).as_slice())),
+                    (_, pattern) => pattern,
+                };
+                let Some(index) = group.push(word, key, count) else {
+                    continue;
+                };
+                group.all.indices.push(index);
+                if let (Some(buckets), Some(pattern)) = (&mut group.by_hyphenation, pattern) {
+                    buckets.entry(pattern).or_default().indices.push(index);
+                }
+            }
+
+            group.finish();
+            self.groups.insert(length, group);
+        }
+        self.groups.get_mut(&length)
+    }
+
+    fn hyphenate(&self, word: &str) -> EcoVec<u8> {
+        hyphenation(word, self.language, self.settings.patterns.as_deref())
+    }
+
+    /// The hash of the key that identifies duplicates of a word.
+    fn key(&self, word: &str) -> u64 {
+        if self.settings.fold_case {
+            hash(&word.to_lowercase())
+        } else {
+            hash(word)
+        }
+    }
+}
+
+/// Split a line into the word and its frequency after a tab, which is 1 if the line has none,
+/// so that entries like `Route 66` stay whole.
+fn parse_line(line: &str) -> (&str, u64) {
+    if let Some((word, count)) = line.rsplit_once('\t') {
+        if let Ok(count) = count.parse() {
+            if !word.trim_end().is_empty() {
+                return (word.trim_end(), count);
+            }
+        }
+    }
+    (line, 1)
+}
+
+fn hash(word: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    word.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Words that are interchangeable for one strategy.
+#[derive(Default)]
+struct Bucket {
+    /// The indices of the words in their group.
+    indices: Vec<u32>,
+    /// The running total of the counts, if the words have distinct frequencies.
+    cumulative: Vec<u64>,
+}
+
+impl Bucket {
+    fn finish(&mut self, counts: &[u64]) {
+        if counts.is_empty() {
+            return;
+        }
+
+        // Sort by descending frequency, so that words of similar frequency are close.
+        let count = |index: u32| counts[index as usize];
+        self.indices
+            .sort_by_key(|&index| std::cmp::Reverse(count(index)));
+
+        let mut total = 0u64;
+        self.cumulative = self
+            .indices
+            .iter()
+            .map(|&index| {
+                total = total.saturating_add(count(index).max(1));
+                total
+            })
+            .collect();
+    }
+}
+
+/// The words of a bucket.
+pub(crate) struct Candidates<'a> {
+    bucket: &'a Bucket,
+    group: &'a Group,
+}
+
+impl<'a> Candidates<'a> {
+    pub(crate) fn len(&self) -> usize {
+        self.bucket.indices.len()
+    }
+
+    /// Choose a word, preferring frequent ones.
+    ///
+    /// If the frequency of the original word is known, a word of similar frequency is chosen.
+    pub(crate) fn choose<R: Rng>(&self, rng: &mut R, count: Option<u64>) -> Option<&'a EcoString> {
+        let indices = &self.bucket.indices;
+        let index = match (self.bucket.cumulative.last(), count) {
+            (None, _) => indices.choose(rng)?,
+            (Some(_), Some(count)) => {
+                /// The number of neighbours by rank that a word is chosen from.
+                const WINDOW: usize = 16;
+                let rank = indices.partition_point(|&index| self.group.count(index) > count);
+                let start = rank
+                    .saturating_sub(WINDOW / 2)
+                    .min(indices.len().saturating_sub(WINDOW));
+                let end = (start + WINDOW).min(indices.len());
+                indices[start..end].choose(rng)?
+            }
+            (Some(&total), None) => {
+                let target = rng.gen_range(0..total);
+                indices.get(self.bucket.cumulative.partition_point(|&sum| sum <= target))?
+            }
+        };
+        self.group.words.get(*index as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequencies() {
+        assert_eq!(parse_line("apple\t42"), ("apple", 42));
+        assert_eq!(parse_line("apple"), ("apple", 1));
+        assert_eq!(parse_line("Route 66"), ("Route 66", 1));
+        assert_eq!(parse_line("Route 66\t7"), ("Route 66", 7));
+        assert_eq!(parse_line("\t3"), ("\t3", 1));
+    }
+}
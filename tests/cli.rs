@@ -0,0 +1,171 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const BROKEN: &str = "#let x = (\n";
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_typst-mutilate"))
+        .args(["--seed", "1", "-q"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Write a document to a file of its own for a test.
+fn document(name: &str, code: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, code).unwrap();
+    path
+}
+
+#[test]
+fn mutilate() {
+    let code = "Typst makes writing fun. *Try* it!\n";
+    let output = run(&["--protect", "Typst"], code);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    let (before, after) = (words(code), words(&stdout));
+    assert_eq!(before.len(), after.len());
+    assert_eq!(after[0], "Typst");
+    for (original, replaced) in before.iter().zip(&after).skip(1) {
+        assert_ne!(original, replaced);
+    }
+    // Everything but the words stays as it is.
+    let shape = |text: &str| text.replace(char::is_alphanumeric, "");
+    assert_eq!(shape(&stdout), shape(code));
+}
+
+#[test]
+fn filter_syntax_error() {
+    let output = run(&["--filter"], BROKEN);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("<stdin>:1:10: error"));
+    assert!(stderr.contains("<stdin> has 1 syntax error\n"));
+}
+
+#[test]
+fn check_syntax_error() {
+    let path = document("check-broken.typ", BROKEN);
+    let output = run(&["check", path.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("check-broken.typ:1:10: error"));
+    assert!(stderr.contains("1 file has syntax errors"));
+}
+
+#[test]
+fn hook_syntax_error() {
+    let broken = document("hook-broken.typ", BROKEN);
+    let fine = document("hook-fine.typ", "Hello world.\n");
+    let output = run(
+        &["hook", broken.to_str().unwrap(), fine.to_str().unwrap()],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(std::fs::read_to_string(&broken).unwrap(), BROKEN);
+    // The files after the broken one are still mutilated.
+    assert_ne!(std::fs::read_to_string(&fine).unwrap(), "Hello world.\n");
+}
+
+#[test]
+fn audit_log_without_salt() {
+    let log = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("unsalted.jsonl");
+    let output = run(&["--audit-log", log.to_str().unwrap()], "Hello world.");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn verify_latin1() {
+    let original = b"Caf\xe9 cr\xe8me au lait.\n";
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("verify-latin1.typ");
+    let copy = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("verify-latin1-original.typ");
+    let log = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("verify-latin1.jsonl");
+    std::fs::write(&path, original).unwrap();
+    std::fs::write(&copy, original).unwrap();
+    let _ = std::fs::remove_file(&log);
+    let audit = [
+        "-i",
+        path.to_str().unwrap(),
+        "--audit-log",
+        log.to_str().unwrap(),
+        "--audit-salt",
+        "salt",
+    ];
+    assert!(run(&audit, "").status.success());
+    let verify = |salt| {
+        let args = [
+            "verify",
+            log.to_str().unwrap(),
+            "--salt",
+            salt,
+            "--original",
+            copy.to_str().unwrap(),
+        ];
+        run(&args, "")
+    };
+    assert!(verify("salt").status.success());
+    let output = verify("pepper");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("bytes 0..5 don't match"));
+}
+
+#[test]
+fn since_revision() {
+    let repo = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("since-repo");
+    let _ = std::fs::remove_dir_all(&repo);
+    std::fs::create_dir_all(&repo).unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .arg("-C")
+            .arg(&repo)
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    let path = repo.join("doc.typ");
+    std::fs::write(&path, "= Title\n\nOld words.\n\nLast words.\n").unwrap();
+    git(&["init", "-q"]);
+    git(&["add", "doc.typ"]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    let code = "= Title\n\nNew words.\n\nLast words.\n";
+    let output = run(
+        &[
+            "--since",
+            "HEAD",
+            "--stdin-filepath",
+            path.to_str().unwrap(),
+        ],
+        code,
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[..2], ["= Title", ""]);
+    assert_ne!(lines[2], "New words.");
+    assert_eq!(lines[3..], ["", "Last words."]);
+}